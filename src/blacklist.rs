@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+/// The name of the file the failure counts are stored in, inside the cache directory.
+pub const BLACKLIST_FILE: &str = "failures.json";
+
+/// The `FailureBlacklist` struct counts consecutive fetch failures per URL and blacklists URLs
+/// once they reach a threshold, so permanently dead pages aren't retried on every run.
+///
+/// Counts are kept in memory and, if a path is given, saved as JSON after every change so they
+/// survive between runs. A successful fetch resets the count of its URL. Blacklisted URLs stay
+/// blacklisted until they are cleared with `clear` or `clear_url`.
+#[derive(Debug)]
+pub struct FailureBlacklist {
+    /// The file the counts are persisted to, or `None` to keep them in memory only.
+    path: Option<PathBuf>,
+    /// The number of consecutive failures after which a URL is skipped.
+    threshold: u32,
+    /// The number of consecutive failures of each URL.
+    failures: Mutex<HashMap<String, u32>>,
+}
+
+impl FailureBlacklist {
+    /// Creates a new `FailureBlacklist`, loading the counts saved at `path` if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file the counts are persisted to, or `None` to keep them in memory only.
+    /// * `threshold` - The number of consecutive failures after which a URL is skipped.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `FailureBlacklist`. An unreadable file is logged and treated as empty.
+    pub fn new(path: Option<PathBuf>, threshold: u32) -> Self {
+        let failures = path
+            .as_ref()
+            .and_then(|path| match std::fs::read_to_string(path) {
+                Ok(json) => serde_json::from_str(&json)
+                    .inspect_err(|e| warn!("Ignoring corrupt failure blacklist {}: {}", path.display(), e))
+                    .ok(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    warn!("Ignoring unreadable failure blacklist {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            threshold,
+            failures: Mutex::new(failures),
+        }
+    }
+
+    /// Checks whether a URL has failed often enough to be skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the URL has reached the failure threshold, `false` otherwise.
+    pub fn is_blacklisted(&self, url: &str) -> bool {
+        self.failures
+            .lock()
+            .unwrap()
+            .get(url)
+            .is_some_and(|&count| count >= self.threshold)
+    }
+
+    /// Records a failed fetch of a URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL that failed.
+    ///
+    /// # Returns
+    ///
+    /// The number of consecutive failures of the URL, including this one.
+    pub fn record_failure(&self, url: &str) -> u32 {
+        let mut failures = self.failures.lock().unwrap();
+        let count = failures.entry(url.to_string()).or_insert(0);
+        *count += 1;
+        let count = *count;
+
+        if count == self.threshold {
+            warn!("Blacklisting {} after {} consecutive failures", url, count);
+        }
+        self.save(&failures);
+        count
+    }
+
+    /// Records a successful fetch of a URL, resetting its failure count.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL that was fetched.
+    pub fn record_success(&self, url: &str) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.remove(url).is_some() {
+            self.save(&failures);
+        }
+    }
+
+    /// Removes a single URL from the blacklist, resetting its failure count.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to clear.
+    pub fn clear_url(&self, url: &str) {
+        self.record_success(url);
+    }
+
+    /// Clears every failure count, removing all URLs from the blacklist.
+    pub fn clear(&self) {
+        let mut failures = self.failures.lock().unwrap();
+        failures.clear();
+        self.save(&failures);
+    }
+
+    /// Returns the URLs that are currently blacklisted, in sorted order.
+    pub fn blacklisted(&self) -> Vec<String> {
+        let mut urls: Vec<String> = self
+            .failures
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, count)| **count >= self.threshold)
+            .map(|(url, _)| url.clone())
+            .collect();
+        urls.sort();
+        urls
+    }
+
+    /// Saves the failure counts to the blacklist file, if there is one. Errors are logged, since
+    /// losing the counts only means dead URLs are retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `failures` - The failure counts to save.
+    fn save(&self, failures: &HashMap<String, u32>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| {
+                let json = serde_json::to_string_pretty(failures).map_err(std::io::Error::other)?;
+                let temp_path = path.with_extension("json.tmp");
+                std::fs::write(&temp_path, json)?;
+                std::fs::rename(&temp_path, path)
+            });
+
+        match result {
+            Ok(()) => debug!("Saved {} failure counts to {}", failures.len(), path.display()),
+            Err(e) => warn!("Failed to save failure blacklist {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that URLs are blacklisted after the threshold and reset by a success or a clear.
+    #[test]
+    fn test_threshold_and_reset() {
+        let blacklist = FailureBlacklist::new(None, 2);
+        let url = "https://example.com/dead";
+
+        assert_eq!(blacklist.record_failure(url), 1);
+        assert!(!blacklist.is_blacklisted(url));
+        blacklist.record_success(url);
+        assert_eq!(blacklist.record_failure(url), 1);
+        assert_eq!(blacklist.record_failure(url), 2);
+        assert!(blacklist.is_blacklisted(url));
+        assert_eq!(blacklist.blacklisted(), vec![url]);
+
+        blacklist.clear();
+        assert!(!blacklist.is_blacklisted(url));
+        assert!(blacklist.blacklisted().is_empty());
+    }
+
+    /// Tests that failure counts persist between instances sharing a file.
+    #[test]
+    fn test_persistence() {
+        let dir = std::env::temp_dir().join(format!("sollama-blacklist-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join(BLACKLIST_FILE);
+        let url = "https://example.com/gone";
+
+        let blacklist = FailureBlacklist::new(Some(path.clone()), 1);
+        blacklist.record_failure(url);
+        assert!(FailureBlacklist::new(Some(path.clone()), 1).is_blacklisted(url));
+
+        blacklist.clear_url(url);
+        assert!(!FailureBlacklist::new(Some(path.clone()), 1).is_blacklisted(url));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(FailureBlacklist::new(Some(path), 1).blacklisted().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
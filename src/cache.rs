@@ -0,0 +1,397 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+/// The version of the on-disk entry format, written in every entry header.
+const CACHE_FORMAT_VERSION: u32 = 2;
+/// The prefix of every entry header.
+const HEADER_PREFIX: &str = "SOLLAMA-CACHE/";
+
+/// A counter making temporary file names unique within the process.
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A cache entry: the cached body and the named fields stored alongside it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheEntry {
+    /// The cached body, e.g. the raw HTML of a page.
+    pub body: String,
+    /// The fields stored with the body as name and value pairs, in the order they were written.
+    pub fields: Vec<(String, String)>,
+}
+
+/// The `ContentCache` struct stores the raw HTML of fetched pages on disk, keyed by URL.
+///
+/// Each entry starts with a header line recording the format version and the encoding of the
+/// body (`gzip` or `plain`), so entries written with and without compression can be read back
+/// regardless of the current `compress` setting. The header line is followed by one `name: value`
+/// line per field and an empty line.
+#[derive(Debug, Clone)]
+pub struct ContentCache {
+    /// The directory the entries are stored in.
+    dir: PathBuf,
+    /// How long an entry stays valid, or `None` if entries never expire.
+    ttl: Option<Duration>,
+    /// Whether new entries are gzip-compressed.
+    compress: bool,
+}
+
+impl ContentCache {
+    /// Creates a new `ContentCache` storing entries in the given directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory the entries are stored in. It is created on first write.
+    /// * `ttl` - How long an entry stays valid, or `None` if entries never expire.
+    /// * `compress` - Whether new entries are gzip-compressed.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `ContentCache`.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Option<Duration>, compress: bool) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            compress,
+        }
+    }
+
+    /// Returns the cached HTML of a URL, if there is a valid entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the cached HTML, or `None` if the entry is missing, expired or unreadable.
+    /// Corrupt entries are logged and treated as misses, so the next fetch overwrites them.
+    pub fn get(&self, url: &str) -> Option<String> {
+        self.get_entry(url).map(|entry| entry.body)
+    }
+
+    /// Returns the cached entry of a URL, including its fields, if there is a valid entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `CacheEntry`, or `None` if the entry is missing, expired or unreadable.
+    pub fn get_entry(&self, url: &str) -> Option<CacheEntry> {
+        if let Some(ttl) = self.ttl {
+            let modified = std::fs::metadata(self.path(url)).and_then(|m| m.modified()).ok()?;
+            let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+            if age > ttl {
+                debug!("Cache entry for {} expired", url);
+                return None;
+            }
+        }
+
+        self.read_entry(url)
+    }
+
+    /// Returns the cached HTML of a URL even if the entry has expired, e.g. to compare an old
+    /// version of a page with a fresh one.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the cached HTML, or `None` if the entry is missing or unreadable.
+    pub fn get_stale(&self, url: &str) -> Option<String> {
+        self.read_entry(url).map(|entry| entry.body)
+    }
+
+    /// Reads and decodes the entry of a URL, regardless of its age.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `CacheEntry`, or `None` if the entry is missing or unreadable.
+    fn read_entry(&self, url: &str) -> Option<CacheEntry> {
+        let path = self.path(url);
+
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Ignoring unreadable cache entry {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        let entry = decode(&bytes);
+        if entry.is_none() {
+            warn!("Ignoring corrupt cache entry for {}", url);
+        }
+        entry
+    }
+
+    /// Stores the HTML of a URL, replacing any existing entry.
+    ///
+    /// The entry is written to a temporary file and renamed into place, so a crash mid-write
+    /// never leaves a truncated entry behind.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    /// * `html` - The raw HTML to cache.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether the entry was written.
+    pub fn put(&self, url: &str, html: &str) -> std::io::Result<()> {
+        self.put_entry(url, html, &[])
+    }
+
+    /// Stores the HTML of a URL together with named fields, replacing any existing entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    /// * `html` - The raw HTML to cache.
+    /// * `fields` - The name and value pairs to store with the HTML. Neither may contain a line break.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether the entry was written.
+    pub fn put_entry(&self, url: &str, html: &str, fields: &[(String, String)]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        let path = self.path(url);
+        let temp_path = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        std::fs::write(&temp_path, encode(html, fields, self.compress)?)?;
+        std::fs::rename(&temp_path, &path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp_path);
+        })
+    }
+
+    /// Returns the path of the entry for a URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// The path of the entry file.
+    fn path(&self, url: &str) -> PathBuf {
+        let key = Sha256::digest(url.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        Path::new(&self.dir).join(format!("{}.cache", key))
+    }
+}
+
+/// Encodes HTML into an entry: a versioned header line and the field lines, followed by the
+/// (optionally compressed) body.
+///
+/// # Arguments
+///
+/// * `html` - The raw HTML to encode.
+/// * `fields` - The name and value pairs to store with the HTML.
+/// * `compress` - Whether to gzip-compress the body.
+///
+/// # Returns
+///
+/// An `io::Result` containing the encoded entry.
+fn encode(html: &str, fields: &[(String, String)], compress: bool) -> std::io::Result<Vec<u8>> {
+    let encoding = if compress { "gzip" } else { "plain" };
+    let mut header = format!("{}{} {}\n", HEADER_PREFIX, CACHE_FORMAT_VERSION, encoding);
+    for (name, value) in fields {
+        header.push_str(&format!("{}: {}\n", name, value));
+    }
+    header.push('\n');
+    let mut entry = header.into_bytes();
+
+    if compress {
+        let mut encoder = GzEncoder::new(entry, Compression::default());
+        encoder.write_all(html.as_bytes())?;
+        entry = encoder.finish()?;
+    } else {
+        entry.extend_from_slice(html.as_bytes());
+    }
+
+    Ok(entry)
+}
+
+/// Decodes an entry written by `encode`, using the encoding recorded in its header.
+///
+/// # Arguments
+///
+/// * `bytes` - The entry contents.
+///
+/// # Returns
+///
+/// An `Option` containing the `CacheEntry`, or `None` if the entry has an unknown format or
+/// cannot be decoded.
+fn decode(bytes: &[u8]) -> Option<CacheEntry> {
+    let mut lines = bytes.splitn(2, |&b| b == b'\n');
+    let header = std::str::from_utf8(lines.next()?).ok()?;
+    let mut rest = lines.next()?;
+
+    let (version, encoding) = header.strip_prefix(HEADER_PREFIX)?.split_once(' ')?;
+    if version.parse::<u32>().ok()? != CACHE_FORMAT_VERSION {
+        return None;
+    }
+
+    // The field lines run up to the first empty line
+    let mut fields = Vec::new();
+    loop {
+        let line_end = rest.iter().position(|&b| b == b'\n')?;
+        let line = std::str::from_utf8(&rest[..line_end]).ok()?;
+        rest = &rest[line_end + 1..];
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(": ")?;
+        fields.push((name.to_string(), value.to_string()));
+    }
+
+    let body = match encoding {
+        "gzip" => {
+            let mut html = String::new();
+            GzDecoder::new(rest).read_to_string(&mut html).ok()?;
+            html
+        }
+        "plain" => String::from_utf8(rest.to_vec()).ok()?,
+        _ => return None,
+    };
+
+    Some(CacheEntry { body, fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sollama-cache-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Tests that entries round-trip with and without compression, and compression shrinks them.
+    #[test]
+    fn test_round_trip() {
+        let html = "<html><body><p>Cached paragraph.</p></body></html>".repeat(100);
+
+        let compressed = encode(&html, &[], true).unwrap();
+        let plain = encode(&html, &[], false).unwrap();
+
+        assert!(compressed.len() < plain.len() / 4);
+        assert_eq!(decode(&compressed).unwrap().body, html);
+        assert_eq!(decode(&plain).unwrap().body, html);
+    }
+
+    /// Tests that fields are stored with the body and read back in order.
+    #[test]
+    fn test_entry_fields() {
+        let dir = temp_dir("fields");
+        let cache = ContentCache::new(&dir, None, true);
+        let fields = vec![
+            ("redirect".to_string(), "https://example.com/a".to_string()),
+            ("redirect".to_string(), "https://example.com/b".to_string()),
+            ("x-robots-tag".to_string(), "noarchive, max-snippet: 50".to_string()),
+        ];
+
+        cache.put_entry("https://example.com", "<p>page</p>", &fields).unwrap();
+        let entry = cache.get_entry("https://example.com").unwrap();
+
+        assert_eq!(entry.body, "<p>page</p>");
+        assert_eq!(entry.fields, fields);
+        assert_eq!(cache.get("https://example.com").unwrap(), "<p>page</p>");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Tests that compressed and uncompressed entries can be read by either cache setting.
+    #[test]
+    fn test_mixed_entries() {
+        let dir = temp_dir("mixed");
+        let plain_cache = ContentCache::new(&dir, None, false);
+        let gzip_cache = ContentCache::new(&dir, None, true);
+
+        plain_cache.put("https://example.com/plain", "<p>plain</p>").unwrap();
+        gzip_cache.put("https://example.com/gzip", "<p>gzip</p>").unwrap();
+
+        assert_eq!(gzip_cache.get("https://example.com/plain").unwrap(), "<p>plain</p>");
+        assert_eq!(plain_cache.get("https://example.com/gzip").unwrap(), "<p>gzip</p>");
+        assert!(gzip_cache.get("https://example.com/missing").is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Tests that unknown versions and encodings are treated as misses.
+    #[test]
+    fn test_unknown_format() {
+        assert!(decode(b"SOLLAMA-CACHE/99 plain\n\n<p>future</p>").is_none());
+        assert!(decode(b"SOLLAMA-CACHE/1 plain\n<p>old format</p>").is_none());
+        assert!(decode(b"SOLLAMA-CACHE/2 brotli\n\n<p>unknown</p>").is_none());
+        assert!(decode(b"<p>no header</p>").is_none());
+    }
+
+    /// Tests that entries older than the TTL are not returned.
+    #[test]
+    fn test_ttl_expiry() {
+        let dir = temp_dir("ttl");
+        ContentCache::new(&dir, None, true)
+            .put("https://example.com", "<p>old</p>")
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(ContentCache::new(&dir, Some(Duration::from_millis(1)), true)
+            .get("https://example.com")
+            .is_none());
+        assert!(ContentCache::new(&dir, Some(Duration::from_secs(60)), true)
+            .get("https://example.com")
+            .is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Tests that corrupt entries are treated as misses and replaced by the next write.
+    #[test]
+    fn test_corrupt_entry() {
+        let dir = temp_dir("corrupt");
+        let cache = ContentCache::new(&dir, None, true);
+        let url = "https://example.com/page";
+
+        cache.put(url, &"<p>complete entry</p>".repeat(50)).unwrap();
+        let entry = std::fs::read(cache.path(url)).unwrap();
+
+        // Simulate a crash mid-write by truncating the compressed body
+        std::fs::write(cache.path(url), &entry[..entry.len() / 2]).unwrap();
+        assert!(cache.get(url).is_none());
+
+        std::fs::write(cache.path(url), b"SOLLAMA-CACHE/2 plain\n\n\xff\xfe").unwrap();
+        assert!(cache.get(url).is_none());
+
+        cache.put(url, "<p>fresh</p>").unwrap();
+        assert_eq!(cache.get(url).unwrap(), "<p>fresh</p>");
+
+        let leftovers = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+use crate::types::ScrapedContent;
+use similar::{ChangeTag, TextDiff};
+use std::collections::HashMap;
+
+/// Produces a human-readable, sentence-level diff between two summaries.
+///
+/// Each sentence is printed on its own line, prefixed with `-` if it was removed, `+` if it was
+/// added, or a space if it is unchanged.
+///
+/// # Arguments
+///
+/// * `old` - The previous summary.
+/// * `new` - The current summary.
+///
+/// # Returns
+///
+/// The formatted diff, or an empty string if the summaries contain the same sentences.
+pub fn diff_summaries(old: &str, new: &str) -> String {
+    let old_sentences = sentences(old);
+    let new_sentences = sentences(new);
+    let diff = TextDiff::from_slices(&old_sentences, &new_sentences);
+
+    if diff.iter_all_changes().all(|change| change.tag() == ChangeTag::Equal) {
+        return String::new();
+    }
+
+    diff.iter_all_changes()
+        .map(|change| {
+            let marker = match change.tag() {
+                ChangeTag::Delete => "-",
+                ChangeTag::Insert => "+",
+                ChangeTag::Equal => " ",
+            };
+            format!("{} {}", marker, change.value())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Produces a human-readable diff between two sets of scraped content, matched by URL.
+///
+/// Sources only present in `new` are reported as added, sources only present in `old` as removed,
+/// and sources present in both are diffed sentence by sentence.
+///
+/// # Arguments
+///
+/// * `old` - The previously scraped content.
+/// * `new` - The currently scraped content.
+///
+/// # Returns
+///
+/// The formatted diff, or an empty string if nothing changed.
+pub fn diff_contents(old: &[ScrapedContent], new: &[ScrapedContent]) -> String {
+    let previous: HashMap<&str, &ScrapedContent> =
+        old.iter().map(|c| (c.url.as_str(), c)).collect();
+    let mut sections = Vec::new();
+
+    for content in new {
+        match previous.get(content.url.as_str()) {
+            None => sections.push(format!("+++ Added source: {}", content.url)),
+            Some(before) => {
+                let diff = diff_summaries(&before.content, &content.content);
+                if !diff.is_empty() {
+                    sections.push(format!("*** Changed source: {}\n{}", content.url, diff));
+                }
+            }
+        }
+    }
+
+    for content in old {
+        if !new.iter().any(|c| c.url == content.url) {
+            sections.push(format!("--- Removed source: {}", content.url));
+        }
+    }
+
+    sections.join("\n\n")
+}
+
+/// Splits text into sentences, treating line breaks as sentence boundaries.
+///
+/// # Arguments
+///
+/// * `text` - The text to split.
+///
+/// # Returns
+///
+/// A vector of trimmed, non-empty sentences.
+pub(crate) fn sentences(text: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+
+    for line in text.lines() {
+        let mut start = 0;
+
+        for (i, c) in line.char_indices() {
+            if matches!(c, '.' | '!' | '?') {
+                let end = i + c.len_utf8();
+                if end == line.len() || line[end..].starts_with(char::is_whitespace) {
+                    result.push(line[start..end].trim());
+                    start = end;
+                }
+            }
+        }
+
+        result.push(line[start..].trim());
+    }
+
+    result.retain(|sentence| !sentence.is_empty());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    /// Tests that changed sentences are marked as removed and added.
+    #[test]
+    fn test_diff_summaries() {
+        let old = "Rust 1.80 is the latest release. It is fast. It is safe.";
+        let new = "Rust 1.81 is the latest release. It is fast. It is safe.";
+
+        let diff = diff_summaries(old, new);
+
+        assert!(diff.contains("- Rust 1.80 is the latest release."));
+        assert!(diff.contains("+ Rust 1.81 is the latest release."));
+        assert!(diff.contains("  It is fast."));
+    }
+
+    /// Tests that identical summaries produce an empty diff.
+    #[test]
+    fn test_diff_summaries_unchanged() {
+        let text = "Nothing changed here. Still the same.";
+        assert!(diff_summaries(text, text).is_empty());
+    }
+
+    /// Tests that added, removed and changed sources are reported.
+    #[test]
+    fn test_diff_contents() {
+        let page = |url: &str, content: &str| ScrapedContent {
+            url: url.to_string(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+
+        let old = vec![
+            page("https://a.example", "Version one."),
+            page("https://b.example", "Gone soon."),
+        ];
+        let new = vec![
+            page("https://a.example", "Version two."),
+            page("https://c.example", "Brand new."),
+        ];
+
+        let diff = diff_contents(&old, &new);
+
+        assert!(diff.contains("*** Changed source: https://a.example"));
+        assert!(diff.contains("+ Version two."));
+        assert!(diff.contains("+++ Added source: https://c.example"));
+        assert!(diff.contains("--- Removed source: https://b.example"));
+    }
+}
@@ -1,3 +1,10 @@
+//! Sollama searches the web, extracts the content of the result pages and summarizes it with an
+//! Ollama model.
+//!
+//! [`Sollama::research`] is the main entry point: it runs the whole pipeline in one call and
+//! returns the summary with its sources and statistics. The modules it ties together (`search`,
+//! `scraper`, `prompt`, `llm`, ...) can also be used on their own.
+
 use std::time::Duration;
 use thiserror::Error;
 
@@ -7,10 +14,21 @@ pub mod scraper;
 pub mod search;
 pub mod types;
 pub mod llm;
+pub mod urls;
+pub mod quality;
+pub mod diff;
+pub mod cache;
+pub mod blacklist;
+pub mod robots;
+pub mod output;
+pub mod pipeline;
+pub mod research;
 
 // Re-export commonly used types
-pub use config::ScraperConfig;
-pub use types::{ScrapedContent, SearchResult};
+pub use config::{ScraperConfig, ScraperConfigBuilder};
+pub use crate::scraper::ContentScraper;
+pub use research::{ResearchOptions, ResearchResult, Sollama};
+pub use types::{ScrapedContent, SearchHit, SearchResult};
 
 /// The `ScraperError` enum represents various errors that can occur in the scraper application.
 #[derive(Error, Debug)]
@@ -21,6 +39,9 @@ pub enum ScraperError {
     /// Represents an error that occurs when the rate limit is exceeded.
     #[error("Rate limit exceeded")]
     RateLimitError,
+    /// Represents a response with a non-success HTTP status code.
+    #[error("HTTP status {0}")]
+    HttpStatus(u16),
     /// Represents an error that occurs during content extraction.
     #[error("Content extraction failed: {0}")]
     ExtractionError(String),
@@ -30,11 +51,41 @@ pub enum ScraperError {
     /// Represents an error that occurs during a search operation.
     #[error("Search failed: {0}")]
     SearchError(String),
+    /// Represents a fetch skipped because the run's download budget (in bytes) was used up.
+    #[error("Download limit of {0} bytes exceeded")]
+    BandwidthExceeded(usize),
+    /// Represents a page skipped because its `X-Robots-Tag` header disallows harvesting it.
+    #[error("Disallowed by robots directives: {0}")]
+    RobotsDisallowed(String),
+    /// Represents a page skipped because the site's robots.txt disallows fetching it.
+    #[error("Disallowed by robots.txt: {0}")]
+    DisallowedByRobots(String),
+    /// Represents a page skipped because it failed too many times in a row.
+    #[error("Skipped after repeated failures: {0}")]
+    Blacklisted(String),
+    /// Represents an error that occurs while loading the configuration.
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
 }
 
 /// A type alias for `Result` with the `ScraperError` error type.
 pub type Result<T> = std::result::Result<T, ScraperError>;
 
+/// Extracts the main content and metadata from HTML that has already been fetched, without any
+/// network access, using the default `ContentScraper`.
+///
+/// # Arguments
+///
+/// * `html` - The HTML string to be parsed.
+/// * `url` - The URL of the HTML document.
+///
+/// # Returns
+///
+/// A `Result` containing the `ScrapedContent`, or an error if no content could be extracted.
+pub fn extract_from_html(html: &str, url: &str) -> Result<ScrapedContent> {
+    ContentScraper::default().extract(html, url)
+}
+
 // Constants
 
 /// The default timeout duration for HTTP requests.
@@ -42,4 +93,6 @@ pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
 /// The default number of concurrent requests.
 pub const DEFAULT_CONCURRENT_REQUESTS: usize = 5;
 /// The default maximum number of retries for failed requests.
-pub const DEFAULT_MAX_RETRIES: u32 = 3;
\ No newline at end of file
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// The default number of concurrent LLM requests.
+pub const DEFAULT_LLM_CONCURRENCY: usize = 2;
\ No newline at end of file
@@ -1,9 +1,40 @@
-use crate::{config::LLMConfig, Result, ScraperError};
+use crate::{
+    config::{ApiFormat, LLMConfig},
+    prompt::{build_classify_prompt, build_group_reduce_prompt, build_map_prompt, build_reduce_prompt},
+    types::PageSummary,
+    Result, ScrapedContent, ScraperError,
+};
+use futures::{future, stream, Stream, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::json;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{debug, instrument};
+use tracing::{debug, info, instrument, warn};
+
+/// The category assigned by `LLMProcessor::classify` when the model's reply matches no category.
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+/// The system prompt sent with every request.
+const SYSTEM_PROMPT: &str = "You are a helpful assistant that analyzes text content to answer questions. \
+    you will receive a lot of content and a statement or a query, Your responses should be \
+    about the question or query or statement that was given as a prompt and nothing more :\n\
+    1. Make your reply Accurate and based on the provided content\n\
+    2. Well-structured and easy to understand\n\
+    3. Directly addressing the original question or prompt\n\
+    4. Including relevant citations when appropriate";
+
+/// The number of times `LLMProcessor::process_json` asks the model before giving up on output
+/// that doesn't parse.
+const JSON_ATTEMPTS: usize = 3;
+
+/// A callback asked whether a prompt of the given estimated token count should be sent.
+/// Returning `false` aborts the request.
+pub type PromptConfirmation = Arc<dyn Fn(usize) -> bool + Send + Sync>;
+
+/// A callback invoked with each token of a streamed response as it arrives.
+pub type TokenCallback = Arc<dyn Fn(&str) + Send + Sync>;
 
 /// The `LLMProcessor` struct is responsible for processing prompts using a Language Model (LLM).
 /// It handles the configuration, HTTP client setup, and the processing of prompts to generate responses.
@@ -12,6 +43,28 @@ pub struct LLMProcessor {
     client: Client,
     /// The configuration for the LLM, including endpoint, temperature, and max tokens.
     config: LLMConfig,
+    /// The callback asked before a prompt is sent, if any.
+    confirm: Option<PromptConfirmation>,
+    /// The callback `process` streams tokens to, if any.
+    on_token: Option<TokenCallback>,
+}
+
+/// The `SamplingOptions` struct holds the optional sampling parameters sent with a request.
+/// Parameters that aren't set are left out, so the server's defaults apply.
+#[derive(Debug, Default, Serialize)]
+struct SamplingOptions {
+    /// The nucleus sampling probability mass.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    /// The number of most likely tokens considered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    /// The penalty for repeated tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat_penalty: Option<f32>,
+    /// The random seed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 /// The `ProcessedResponse` struct holds the details of the response generated by the LLM.
@@ -41,9 +94,63 @@ impl LLMProcessor {
         Self {
             client: Client::new(),
             config,
+            confirm: None,
+            on_token: None,
         }
     }
 
+    /// Sets a timeout for every request sent to the LLM server.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time a request may take, including reading the response.
+    ///
+    /// # Returns
+    ///
+    /// The updated `LLMProcessor` instance.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        self
+    }
+
+    /// Sets a callback that is asked to confirm every prompt before it is sent.
+    ///
+    /// The callback receives the prompt's estimated token count and runs after the
+    /// `max_prompt_tokens` check, so it is never asked about a prompt that would be refused anyway.
+    ///
+    /// # Arguments
+    ///
+    /// * `confirm` - The callback; returning `false` aborts the request with an `LLMError`.
+    ///
+    /// # Returns
+    ///
+    /// The updated `LLMProcessor` instance.
+    pub fn with_confirmation(mut self, confirm: PromptConfirmation) -> Self {
+        self.confirm = Some(confirm);
+        self
+    }
+
+    /// Sets a callback that makes `process` stream its response, receiving each token as it is
+    /// generated instead of waiting silently for the whole response.
+    ///
+    /// Only `process` streams; the intermediate requests of map-reduce summarization,
+    /// classification and JSON output don't.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_token` - The callback invoked with each generated token.
+    ///
+    /// # Returns
+    ///
+    /// The updated `LLMProcessor` instance.
+    pub fn with_token_callback(mut self, on_token: TokenCallback) -> Self {
+        self.on_token = Some(on_token);
+        self
+    }
+
     /// Creates a progress bar with a spinner style and a custom message.
     ///
     /// # Arguments
@@ -67,6 +174,9 @@ impl LLMProcessor {
 
     /// Processes a prompt using the LLM and returns the generated response as a string.
     ///
+    /// With a token callback set, the response is streamed and each token is passed to the
+    /// callback as it arrives.
+    ///
     /// # Arguments
     ///
     /// * `prompt` - The prompt to be processed by the LLM.
@@ -77,6 +187,10 @@ impl LLMProcessor {
     /// A `Result` containing the generated response as a string, or an error if the processing fails.
     #[instrument(skip(self, prompt), fields(prompt_length = prompt.len()))]
     pub async fn process(&self, prompt: &str, model: &str) -> Result<String> {
+        if let Some(on_token) = &self.on_token {
+            return self.process_with_callback(prompt, model, |token| on_token(token)).await;
+        }
+
         let response = self.process_with_details(prompt, model).await?;
         Ok(response.content)
     }
@@ -93,32 +207,17 @@ impl LLMProcessor {
     /// A `Result` containing a `ProcessedResponse` with detailed information about the response, or an error if the processing fails.
     pub async fn process_with_details(&self, prompt: &str, model: &str) -> Result<ProcessedResponse> {
         debug!("Processing LLM request with prompt: {}", prompt);
+        self.check_prompt(prompt)?;
+        self.dump_prompt(prompt);
+        let request = self.request_body(prompt, model)?;
         let spinner = self.create_progress_bar("Preparing LLM request...");
         let start_time = std::time::Instant::now();
 
-        let request = json!({
-            "system" : String::from(
-                "You are a helpful assistant that analyzes text content to answer questions. \
-                you will receive a lot of content and a statement or a query, Your responses should be \
-                about the question or query or statement that was given as a prompt and nothing more :\n\
-                1. Make your reply Accurate and based on the provided content\n\
-                2. Well-structured and easy to understand\n\
-                3. Directly addressing the original question or prompt\n\
-                4. Including relevant citations when appropriate"
-            ),
-            "model": model,
-            "prompt": prompt,
-            "temperature": self.config.temperature,
-            "max_tokens": self.config.max_tokens,
-            "stream": false
-        });
-
         // info!("Sending request to LLM model: {}", request.to_string());
 
         // Request phase
         spinner.set_message(format!("Sending request to {}...", model));
-        let response = match self.client
-            .post(&self.config.endpoint)
+        let response = match self.post(&self.config.endpoint)
             .json(&request)
             .send()
             .await
@@ -140,9 +239,7 @@ impl LLMProcessor {
             }
         };
 
-        let response_text = result["response"]
-            .as_str()
-            .map(String::from)
+        let response_text = self.response_text(&result)
             .ok_or_else(|| {
                 spinner.finish_with_message("❌ Invalid LLM response format!");
                 ScraperError::LLMError("Invalid LLM response format".to_string())
@@ -164,4 +261,1362 @@ impl LLMProcessor {
             model: model.to_string(),
         })
     }
-}
\ No newline at end of file
+
+    /// Processes a prompt using the LLM, yielding the response token by token as it is generated.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt to be processed by the LLM.
+    /// * `model` - The model to be used for processing the prompt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a stream of response tokens, or an error if the request fails.
+    /// The stream ends after the first error.
+    pub async fn process_stream(
+        &self,
+        prompt: &str,
+        model: &str,
+    ) -> Result<impl Stream<Item = Result<String>> + Send> {
+        self.check_prompt(prompt)?;
+        self.dump_prompt(prompt);
+        let mut request = self.request_body(prompt, model)?;
+        request["stream"] = json!(true);
+
+        let response = self.post(&self.config.endpoint)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        // Ollama streams one JSON object per line, OpenAI-compatible servers one `data:` event per line
+        let format = self.config.api_format;
+        let state = (response.bytes_stream().boxed(), Vec::new(), false);
+        Ok(stream::unfold(state, move |(mut bytes, mut buffer, done)| async move {
+            if done {
+                return None;
+            }
+
+            loop {
+                let line = match buffer.iter().position(|&b| b == b'\n') {
+                    Some(end) => Some(buffer.drain(..=end).collect::<Vec<u8>>()),
+                    None => match bytes.next().await {
+                        Some(Ok(chunk)) => {
+                            buffer.extend_from_slice(&chunk);
+                            None
+                        }
+                        Some(Err(e)) => {
+                            return Some((Err(ScraperError::LLMError(e.to_string())), (bytes, buffer, true)));
+                        }
+                        None => {
+                            // The last line may lack a trailing newline
+                            let line = std::mem::take(&mut buffer);
+                            return match parse_stream_line(&line, format) {
+                                Some(Ok((token, _))) if !token.is_empty() => Some((Ok(token), (bytes, buffer, true))),
+                                Some(Err(e)) => Some((Err(e), (bytes, buffer, true))),
+                                _ => None,
+                            };
+                        }
+                    },
+                };
+
+                match line.as_deref().and_then(|line| parse_stream_line(line, format)) {
+                    Some(Ok((token, true))) if token.is_empty() => return None,
+                    Some(Ok((token, false))) if token.is_empty() => {}
+                    Some(Ok((token, finished))) => return Some((Ok(token), (bytes, buffer, finished))),
+                    Some(Err(e)) => return Some((Err(e), (bytes, buffer, true))),
+                    None => {}
+                }
+            }
+        }))
+    }
+
+    /// Processes a prompt using the LLM, calling `on_token` for each token as it is generated.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt to be processed by the LLM.
+    /// * `model` - The model to be used for processing the prompt.
+    /// * `on_token` - The callback invoked with each generated token.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the full generated response, or an error if the processing fails.
+    pub async fn process_with_callback(
+        &self,
+        prompt: &str,
+        model: &str,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<String> {
+        let tokens = self.process_stream(prompt, model).await?;
+        futures::pin_mut!(tokens);
+
+        let mut text = String::new();
+        while let Some(token) = tokens.next().await {
+            let token = token?;
+            on_token(&token);
+            text.push_str(&token);
+        }
+
+        Ok(text)
+    }
+
+    /// Summarizes pages as they arrive, running up to `concurrency` LLM requests at once.
+    ///
+    /// This is the map step of map-reduce summarization. Because it consumes a stream, it can be fed
+    /// directly from `SearchEngine::fetch_stream` so that fetching and summarizing overlap in time.
+    /// Pages that fail to summarize are logged and skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - A stream of scraped pages to summarize.
+    /// * `query` - The query or question the summaries should focus on.
+    /// * `model` - The model to be used for processing the prompts.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `PageSummary` in completion order.
+    pub async fn map_stream<S>(&self, contents: S, query: &str, model: &str) -> Vec<PageSummary>
+    where
+        S: Stream<Item = ScrapedContent>,
+    {
+        let spinner = self.create_progress_bar("Summarizing pages as they arrive...");
+        let progress = spinner.clone();
+        let mut summarized = 0;
+
+        let summaries: Vec<PageSummary> = contents
+            .map(move |content| async move {
+                let prompt = build_map_prompt(query, &content);
+                match self.generate(&prompt, model).await {
+                    Ok(summary) => Some(PageSummary {
+                        url: content.url,
+                        summary,
+                    }),
+                    Err(e) => {
+                        warn!("Failed to summarize {}: {}", content.url, e);
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(self.config.concurrency.max(1))
+            .filter_map(future::ready)
+            .inspect(move |summary| {
+                summarized += 1;
+                progress.set_message(format!("Summarized {} pages (latest: {})", summarized, summary.url));
+            })
+            .collect()
+            .await;
+
+        spinner.finish_with_message(format!("✨ Summarized {} pages", summaries.len()));
+        summaries
+    }
+
+    /// Answers a query about many pages with map-reduce summarization.
+    ///
+    /// Each page is summarized on its own, with up to `concurrency` requests at once, and the
+    /// summaries are then combined into the answer by `reduce`. Unlike a single prompt holding every
+    /// page, no page is cut off by the model's context window. Pages that fail to summarize are
+    /// skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - The scraped pages to answer from.
+    /// * `query` - The query or question to be answered.
+    /// * `model` - The model to be used for processing the prompts.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the final answer, or an error if no page could be summarized or the
+    /// reduce step fails.
+    pub async fn process_map_reduce(&self, contents: Vec<ScrapedContent>, query: &str, model: &str) -> Result<String> {
+        let urls: Vec<String> = contents.iter().map(|content| content.url.clone()).collect();
+        let mut summaries = self.map_stream(stream::iter(contents), query, model).await;
+
+        // Summaries complete in any order; reduce them in the order the pages were given
+        summaries.sort_by_key(|summary| urls.iter().position(|url| *url == summary.url));
+        self.reduce(query, &summaries, model).await
+    }
+
+    /// Combines per-page summaries into a final answer to the query.
+    ///
+    /// This is the reduce step of map-reduce summarization. When the reduce prompt would exceed
+    /// `reduce_token_budget`, the summaries are first merged in groups of at most
+    /// `reduce_group_size`, and the group summaries are reduced in turn until they fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query or question to be answered.
+    /// * `summaries` - The per-page summaries produced by the map step.
+    /// * `model` - The model to be used for processing the prompt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the final answer, or an error if there is nothing to reduce or the processing fails.
+    pub async fn reduce(&self, query: &str, summaries: &[PageSummary], model: &str) -> Result<String> {
+        if summaries.is_empty() {
+            return Err(ScraperError::LLMError("No pages could be summarized".to_string()));
+        }
+
+        let mut level = summaries.to_vec();
+        loop {
+            let prompt = build_reduce_prompt(query, &level);
+            if level.len() == 1 || estimate_tokens(&prompt) <= self.config.reduce_token_budget {
+                return self.process(&prompt, model).await;
+            }
+
+            let groups = self.reduce_groups(query, &level);
+            debug!("Reducing {} summaries in {} groups", level.len(), groups.len());
+            let merged = stream::iter(groups)
+                .map(|group| self.reduce_group(query, group, model))
+                .buffered(self.config.concurrency.max(1))
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+            level = merged;
+        }
+    }
+
+    /// Splits summaries into consecutive groups whose merge prompts fit the reduce token budget.
+    ///
+    /// Each group holds at most `reduce_group_size` summaries, and at least two unless it is the
+    /// last one, so every level of the reduction shrinks the number of summaries.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query or question the summaries focus on.
+    /// * `summaries` - The summaries to group.
+    ///
+    /// # Returns
+    ///
+    /// The groups, in order.
+    fn reduce_groups<'a>(&self, query: &str, summaries: &'a [PageSummary]) -> Vec<&'a [PageSummary]> {
+        let max_size = self.config.reduce_group_size.max(2);
+        let mut groups = Vec::new();
+        let mut start = 0;
+
+        while start < summaries.len() {
+            let mut end = (start + 2).min(summaries.len());
+            while end < summaries.len()
+                && end - start < max_size
+                && estimate_tokens(&build_group_reduce_prompt(query, &summaries[start..=end]))
+                    <= self.config.reduce_token_budget
+            {
+                end += 1;
+            }
+            groups.push(&summaries[start..end]);
+            start = end;
+        }
+
+        groups
+    }
+
+    /// Merges a group of summaries into a single summary that cites all of the group's sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query or question the summary should focus on.
+    /// * `group` - The summaries to merge.
+    /// * `model` - The model to be used for processing the prompt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the merged summary, or an error if the processing fails.
+    async fn reduce_group(&self, query: &str, group: &[PageSummary], model: &str) -> Result<PageSummary> {
+        if let [summary] = group {
+            return Ok(summary.clone());
+        }
+
+        let prompt = build_group_reduce_prompt(query, group);
+        Ok(PageSummary {
+            url: group.iter().map(|s| s.url.as_str()).collect::<Vec<_>>().join(", "),
+            summary: self.generate(&prompt, model).await?,
+        })
+    }
+
+    /// Asks the model which of the given categories best fits a page, and stores the result in
+    /// the page's `category` metadata.
+    ///
+    /// The reply is matched against the categories case-insensitively, ignoring surrounding quotes
+    /// and punctuation. A reply that names no listed category yields `UNCATEGORIZED`.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The scraped page to classify.
+    /// * `categories` - The categories to choose from.
+    /// * `model` - The model to be used for processing the prompt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the chosen category, or an error if the processing fails.
+    pub async fn classify(&self, content: &mut ScrapedContent, categories: &[String], model: &str) -> Result<String> {
+        let prompt = build_classify_prompt(content, categories);
+        let reply = self.generate(&prompt, model).await?;
+
+        let answer = reply.trim().trim_matches(|c: char| !c.is_alphanumeric());
+        let category = categories
+            .iter()
+            .find(|category| category.trim().eq_ignore_ascii_case(answer))
+            .cloned()
+            .unwrap_or_else(|| {
+                debug!("Model replied with unlisted category '{}' for {}", reply.trim(), content.url);
+                UNCATEGORIZED.to_string()
+            });
+
+        content.metadata.insert("category".to_string(), category.clone());
+        Ok(category)
+    }
+
+    /// Processes a prompt and parses the model's answer as JSON into `T`.
+    ///
+    /// The request sets Ollama's `format` parameter to the given JSON schema, or to `"json"` when
+    /// no schema is given, so the model is constrained to emit JSON. Answers that still don't
+    /// parse into `T` are retried, up to three attempts in total.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt to be processed by the LLM.
+    /// * `model` - The model to be used for processing the prompt.
+    /// * `schema` - The JSON schema the answer must follow, or `None` for any JSON.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the parsed answer, or an error if the request fails or no attempt
+    /// produced JSON matching `T`.
+    pub async fn process_json<T: DeserializeOwned>(
+        &self,
+        prompt: &str,
+        model: &str,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<T> {
+        self.dump_prompt(prompt);
+        let mut body = self.request_body(prompt, model)?;
+        match self.config.api_format {
+            ApiFormat::OllamaGenerate => body["format"] = schema.cloned().unwrap_or_else(|| json!("json")),
+            ApiFormat::OpenAiChat => {
+                body["response_format"] = match schema {
+                    Some(schema) => json!({
+                        "type": "json_schema",
+                        "json_schema": { "name": "response", "schema": schema },
+                    }),
+                    None => json!({ "type": "json_object" }),
+                };
+            }
+        }
+
+        let mut last_error = None;
+        for attempt in 1..=JSON_ATTEMPTS {
+            let response = self.send(&body).await?;
+            match serde_json::from_str(response.trim()) {
+                Ok(parsed) => return Ok(parsed),
+                Err(e) => {
+                    warn!("Attempt {} of {}: model output is not the expected JSON: {}", attempt, JSON_ATTEMPTS, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(ScraperError::LLMError(format!(
+            "Model output did not match the expected JSON after {} attempts: {}",
+            JSON_ATTEMPTS,
+            last_error.map(|e| e.to_string()).unwrap_or_default()
+        )))
+    }
+
+    /// Builds the JSON request body for the LLM endpoint in the configured `api_format`.
+    ///
+    /// Sampling parameters are sent in Ollama's `options` object, or as top-level fields for
+    /// OpenAI-compatible servers, and only when they are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt to be processed by the LLM.
+    /// * `model` - The model to be used for processing the prompt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the request body as a JSON value, or an `LLMError` if a sampling
+    /// parameter is out of range.
+    fn request_body(&self, prompt: &str, model: &str) -> Result<serde_json::Value> {
+        let options = json!(self.sampling_options()?);
+
+        if self.config.api_format == ApiFormat::OpenAiChat {
+            let mut body = json!({
+                "model": model,
+                "messages": [
+                    { "role": "system", "content": SYSTEM_PROMPT },
+                    { "role": "user", "content": prompt },
+                ],
+                "temperature": self.config.temperature,
+                "max_tokens": self.config.max_tokens,
+                "stream": false
+            });
+            if let Some(options) = options.as_object() {
+                for (key, value) in options {
+                    // vLLM and LocalAI call the repeat penalty `repetition_penalty`
+                    let key = if key == "repeat_penalty" { "repetition_penalty" } else { key.as_str() };
+                    body[key] = value.clone();
+                }
+            }
+            return Ok(body);
+        }
+
+        let mut body = json!({
+            "system" : SYSTEM_PROMPT,
+            "model": model,
+            "prompt": prompt,
+            "temperature": self.config.temperature,
+            "max_tokens": self.config.max_tokens,
+            "stream": false
+        });
+        if options.as_object().is_some_and(|options| !options.is_empty()) {
+            body["options"] = options;
+        }
+
+        // Ollama takes plain numbers as seconds and strings as durations like "5m"
+        if let Some(keep_alive) = &self.config.keep_alive {
+            body["keep_alive"] = keep_alive
+                .parse::<i64>()
+                .map(|seconds| json!(seconds))
+                .unwrap_or_else(|_| json!(keep_alive));
+        }
+
+        Ok(body)
+    }
+
+    /// Collects the configured sampling parameters, checking that each is in range.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the parameters, or an `LLMError` if `top_p` is outside 0.0 to 1.0,
+    /// `top_k` is 0, or `repeat_penalty` isn't a positive number.
+    fn sampling_options(&self) -> Result<SamplingOptions> {
+        let config = &self.config;
+
+        if let Some(top_p) = config.top_p.filter(|top_p| !(0.0..=1.0).contains(top_p)) {
+            return Err(ScraperError::LLMError(format!("top_p must be between 0.0 and 1.0, got {}", top_p)));
+        }
+        if config.top_k == Some(0) {
+            return Err(ScraperError::LLMError("top_k must be at least 1".to_string()));
+        }
+        if let Some(penalty) = config.repeat_penalty.filter(|penalty| !(penalty.is_finite() && *penalty > 0.0)) {
+            return Err(ScraperError::LLMError(format!("repeat_penalty must be positive, got {}", penalty)));
+        }
+
+        Ok(SamplingOptions {
+            top_p: config.top_p,
+            top_k: config.top_k,
+            repeat_penalty: config.repeat_penalty,
+            seed: config.seed,
+        })
+    }
+
+    /// Reads the generated text out of a response body in the configured `api_format`.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The parsed response body.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the generated text, or `None` if the body has an unexpected shape.
+    fn response_text(&self, result: &serde_json::Value) -> Option<String> {
+        let text = match self.config.api_format {
+            ApiFormat::OllamaGenerate => &result["response"],
+            ApiFormat::OpenAiChat => &result["choices"][0]["message"]["content"],
+        };
+        text.as_str().map(String::from)
+    }
+
+    /// Starts a POST request to the LLM server, authenticated with `api_key` when one is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to post to.
+    ///
+    /// # Returns
+    ///
+    /// The request builder.
+    fn post(&self, url: &str) -> RequestBuilder {
+        let request = self.client.post(url);
+        match &self.config.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    /// Sends a prompt to the LLM without progress output and returns the generated text.
+    ///
+    /// Used for the many small requests of the map step, where a spinner per request would be noise.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt to be processed by the LLM.
+    /// * `model` - The model to be used for processing the prompt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the generated text, or an error if the request or parsing fails.
+    async fn generate(&self, prompt: &str, model: &str) -> Result<String> {
+        self.send(&self.request_body(prompt, model)?).await
+    }
+
+    /// Sends a request body to the LLM endpoint and returns the generated text.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The request body, as built by `request_body`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the generated text, or an error if the request or parsing fails.
+    async fn send(&self, body: &serde_json::Value) -> Result<String> {
+        let response = self.post(&self.config.endpoint)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        let result: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        self.response_text(&result)
+            .ok_or_else(|| ScraperError::LLMError("Invalid LLM response format".to_string()))
+    }
+
+    /// Computes embeddings for many texts concurrently, failing if any text cannot be embedded.
+    ///
+    /// Requests run with the configured LLM concurrency and the embeddings are returned in the
+    /// same order as `texts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - The texts to embed.
+    /// * `model` - The embedding model to use.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing one embedding per text, or the first error encountered.
+    pub async fn embed_batch(&self, texts: &[String], model: &str) -> Result<Vec<Vec<f32>>> {
+        self.embed_batch_results(texts, model).await.into_iter().collect()
+    }
+
+    /// Computes embeddings for many texts concurrently, keeping the result of each text.
+    ///
+    /// Unlike `embed_batch`, a failure for one text does not discard the embeddings of the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - The texts to embed.
+    /// * `model` - The embedding model to use.
+    ///
+    /// # Returns
+    ///
+    /// One embedding result per text, in the same order as `texts`.
+    pub async fn embed_batch_results(&self, texts: &[String], model: &str) -> Vec<Result<Vec<f32>>> {
+        stream::iter(texts)
+            .map(|text| self.embed(text, model))
+            .buffered(self.config.concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Computes the embedding of a single text using Ollama's `/api/embed` endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to embed.
+    /// * `model` - The embedding model to use.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the embedding, or an error if the request or parsing fails.
+    async fn embed(&self, text: &str, model: &str) -> Result<Vec<f32>> {
+        let response = self.post(&self.ollama_url("/api/embed"))
+            .json(&json!({
+                "model": model,
+                "input": text,
+            }))
+            .send()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        let result: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        result["embeddings"][0]
+            .as_array()
+            .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+            .ok_or_else(|| ScraperError::LLMError("Invalid embedding response format".to_string()))
+    }
+
+    /// Picks the smallest candidate model whose context window fits the prompt and the response.
+    ///
+    /// Context limits are queried from Ollama's `/api/show` endpoint; candidates that cannot be
+    /// queried are skipped. The prompt size is estimated with `estimate_tokens`, and room for
+    /// `max_tokens` of output is reserved.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt that will be sent.
+    /// * `candidates` - The names of the models to choose from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the name of the selected model, or an error if no candidate fits.
+    pub async fn select_model(&self, prompt: &str, candidates: &[String]) -> Result<String> {
+        let needed = estimate_tokens(prompt) + self.config.max_tokens as usize;
+        let mut best: Option<(usize, &String)> = None;
+
+        for model in candidates {
+            match self.context_length(model).await {
+                Ok(context) if context >= needed => {
+                    if best.is_none_or(|(best_context, _)| context < best_context) {
+                        best = Some((context, model));
+                    }
+                }
+                Ok(context) => debug!("{} context ({} tokens) is too small for ~{} tokens", model, context, needed),
+                Err(e) => warn!("Could not query context length of {}: {}", model, e),
+            }
+        }
+
+        best.map(|(_, model)| model.clone()).ok_or_else(|| {
+            ScraperError::LLMError(format!(
+                "No candidate model has a context window large enough for ~{} tokens",
+                needed
+            ))
+        })
+    }
+
+    /// Lists the models available on the Ollama server using the `/api/tags` endpoint.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the model names, or an error if the server cannot be queried.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self.client
+            .get(self.ollama_url("/api/tags"))
+            .send()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        let result: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        result["models"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|model| model["name"].as_str().map(String::from))
+                    .collect()
+            })
+            .ok_or_else(|| ScraperError::LLMError("Invalid model list format".to_string()))
+    }
+
+    /// Queries the context window size of a model using Ollama's `/api/show` endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - The name of the model.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the context length in tokens, or an error if it cannot be determined.
+    pub async fn context_length(&self, model: &str) -> Result<usize> {
+        let response = self.post(&self.ollama_url("/api/show"))
+            .json(&json!({ "model": model }))
+            .send()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        let result: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| ScraperError::LLMError(e.to_string()))?;
+
+        // The key is prefixed with the model architecture, e.g. `llama.context_length`
+        result["model_info"]
+            .as_object()
+            .and_then(|info| {
+                info.iter()
+                    .find(|(key, _)| key.ends_with(".context_length"))
+                    .and_then(|(_, value)| value.as_u64())
+            })
+            .map(|length| length as usize)
+            .ok_or_else(|| ScraperError::LLMError(format!("No context length reported for {}", model)))
+    }
+
+    /// Reports a prompt's estimated size and checks that it may be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt about to be sent.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that is an `LLMError` if the prompt exceeds `max_prompt_tokens` or the
+    /// confirmation callback declined it.
+    fn check_prompt(&self, prompt: &str) -> Result<()> {
+        let tokens = estimate_tokens(prompt);
+        info!("Prompt is ~{} estimated tokens", tokens);
+
+        if let Some(limit) = self.config.max_prompt_tokens.filter(|&limit| tokens > limit) {
+            return Err(ScraperError::LLMError(format!(
+                "The prompt is ~{} estimated tokens, over the limit of {}",
+                tokens, limit
+            )));
+        }
+        if self.confirm.as_ref().is_some_and(|confirm| !confirm(tokens)) {
+            return Err(ScraperError::LLMError("The prompt was not confirmed".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Writes a prompt to the configured `dump_prompt` target, if any.
+    ///
+    /// This runs before the request's progress bar is created, so the dump never interleaves with it.
+    /// Failing to write the dump is logged and doesn't stop the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt about to be sent.
+    fn dump_prompt(&self, prompt: &str) {
+        match self.config.dump_prompt.as_deref() {
+            Some(path) if path == std::path::Path::new("-") => {
+                eprintln!(
+                    "----- prompt ({} estimated tokens) -----\n{}\n----- end of prompt -----",
+                    estimate_tokens(prompt),
+                    prompt
+                );
+            }
+            Some(path) => {
+                if let Err(e) = std::fs::write(path, prompt) {
+                    warn!("Failed to write the prompt to {}: {}", path.display(), e);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Builds the URL of another Ollama API endpoint on the same server as the configured endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The API path, e.g. `/api/embed`.
+    ///
+    /// # Returns
+    ///
+    /// The full URL of the endpoint.
+    fn ollama_url(&self, path: &str) -> String {
+        let endpoint = &self.config.endpoint;
+        let base = match endpoint.find("/api/") {
+            Some(index) => &endpoint[..index],
+            None => endpoint.trim_end_matches('/'),
+        };
+        format!("{}{}", base, path)
+    }
+}
+
+/// Parses one line of a streamed response.
+///
+/// # Arguments
+///
+/// * `line` - The raw line: a single JSON object for Ollama, or a `data:` server-sent event for
+///   OpenAI-compatible servers.
+/// * `format` - The API format of the response.
+///
+/// # Returns
+///
+/// `None` for blank lines and other events, otherwise a `Result` containing the token and whether
+/// generation is done, or an error if the line is invalid or reports an error.
+fn parse_stream_line(line: &[u8], format: ApiFormat) -> Option<Result<(String, bool)>> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return None;
+    }
+
+    let line = match format {
+        ApiFormat::OllamaGenerate => line,
+        ApiFormat::OpenAiChat => {
+            let data = line.trim_ascii().strip_prefix(b"data:")?.trim_ascii();
+            if data == b"[DONE]" {
+                return Some(Ok((String::new(), true)));
+            }
+            data
+        }
+    };
+
+    let value: serde_json::Value = match serde_json::from_slice(line) {
+        Ok(value) => value,
+        Err(e) => return Some(Err(ScraperError::LLMError(e.to_string()))),
+    };
+
+    let error = &value["error"];
+    if let Some(error) = error.as_str().or_else(|| error["message"].as_str()) {
+        return Some(Err(ScraperError::LLMError(error.to_string())));
+    }
+
+    let (token, done) = match format {
+        ApiFormat::OllamaGenerate => (&value["response"], value["done"].as_bool().unwrap_or(false)),
+        ApiFormat::OpenAiChat => {
+            let choice = &value["choices"][0];
+            (&choice["delta"]["content"], choice["finish_reason"].is_string())
+        }
+    };
+    Some(Ok((token.as_str().unwrap_or_default().to_string(), done)))
+}
+
+/// Estimates the number of tokens in a text, assuming roughly four characters per token.
+///
+/// # Arguments
+///
+/// * `text` - The text to estimate.
+///
+/// # Returns
+///
+/// The estimated token count.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    fn processor(server: &mockito::Server) -> LLMProcessor {
+        LLMProcessor::new(LLMConfig {
+            endpoint: format!("{}/api/generate", server.url()),
+            ..LLMConfig::default()
+        })
+    }
+
+    /// Tests that batch embeddings come back in input order, with failures kept per item.
+    #[tokio::test]
+    async fn test_embed_batch() {
+        let mut server = mockito::Server::new_async().await;
+        for (input, value) in [("first", 1.0), ("second", 2.0), ("third", 3.0)] {
+            server
+                .mock("POST", "/api/embed")
+                .match_body(Matcher::PartialJson(json!({ "input": input })))
+                .with_body(json!({ "embeddings": [[value, 0.5]] }).to_string())
+                .create_async()
+                .await;
+        }
+        server
+            .mock("POST", "/api/embed")
+            .match_body(Matcher::PartialJson(json!({ "input": "broken" })))
+            .with_status(500)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let processor = processor(&server);
+        let texts: Vec<String> = ["first", "second", "third"].map(String::from).to_vec();
+        let embeddings = processor.embed_batch(&texts, "nomic-embed-text").await.unwrap();
+        assert_eq!(embeddings, vec![vec![1.0, 0.5], vec![2.0, 0.5], vec![3.0, 0.5]]);
+
+        let texts: Vec<String> = ["first", "broken"].map(String::from).to_vec();
+        assert!(processor.embed_batch(&texts, "nomic-embed-text").await.is_err());
+
+        let results = processor.embed_batch_results(&texts, "nomic-embed-text").await;
+        assert_eq!(results[0].as_ref().unwrap(), &vec![1.0, 0.5]);
+        assert!(results[1].is_err());
+    }
+
+    /// Tests that the smallest model with a large enough context window is selected.
+    #[tokio::test]
+    async fn test_select_model() {
+        let mut server = mockito::Server::new_async().await;
+        for (model, context) in [("small", 4096), ("large", 32768), ("medium", 8192)] {
+            server
+                .mock("POST", "/api/show")
+                .match_body(Matcher::PartialJson(json!({ "model": model })))
+                .with_body(json!({ "model_info": { "llama.context_length": context } }).to_string())
+                .create_async()
+                .await;
+        }
+        server
+            .mock("POST", "/api/show")
+            .match_body(Matcher::PartialJson(json!({ "model": "missing" })))
+            .with_status(404)
+            .with_body(json!({ "error": "model not found" }).to_string())
+            .create_async()
+            .await;
+
+        let processor = processor(&server);
+        let candidates: Vec<String> = ["missing", "large", "small", "medium"].map(String::from).to_vec();
+
+        // max_tokens (2048) is reserved for the response
+        let short_prompt = "a".repeat(4 * 1000);
+        assert_eq!(processor.select_model(&short_prompt, &candidates).await.unwrap(), "small");
+
+        let long_prompt = "a".repeat(4 * 5000);
+        assert_eq!(processor.select_model(&long_prompt, &candidates).await.unwrap(), "medium");
+
+        let huge_prompt = "a".repeat(4 * 40000);
+        assert!(processor.select_model(&huge_prompt, &candidates).await.is_err());
+    }
+
+    /// Tests the character-based token estimate.
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    /// Tests that the callback sees each streamed token and the full text is returned.
+    #[tokio::test]
+    async fn test_process_with_callback() {
+        let mut server = mockito::Server::new_async().await;
+        let lines = [
+            json!({ "response": "Rust", "done": false }),
+            json!({ "response": " is", "done": false }),
+            json!({ "response": " fast.", "done": false }),
+            json!({ "response": "", "done": true }),
+        ];
+        let body: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJson(json!({ "stream": true })))
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let processor = processor(&server);
+        let mut tokens = Vec::new();
+        let text = processor
+            .process_with_callback("prompt", "llama3.2", |token| tokens.push(token.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(tokens, vec!["Rust", " is", " fast."]);
+        assert_eq!(text, "Rust is fast.");
+    }
+
+    /// Tests that an error reported mid-stream ends the stream with an error.
+    #[tokio::test]
+    async fn test_process_stream_error() {
+        let mut server = mockito::Server::new_async().await;
+        let body = format!(
+            "{}\n{}\n",
+            json!({ "response": "Partial", "done": false }),
+            json!({ "error": "model crashed" })
+        );
+        server
+            .mock("POST", "/api/generate")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let processor = processor(&server);
+        let result = processor.process_with_callback("prompt", "llama3.2", |_| {}).await;
+        assert!(matches!(result, Err(ScraperError::LLMError(message)) if message == "model crashed"));
+    }
+
+    /// Tests that summaries exceeding the reduce budget are merged in groups before the final reduce.
+    #[tokio::test]
+    async fn test_hierarchical_reduce() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/generate")
+            .with_body(json!({ "response": "Merged.", "done": true }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let processor = LLMProcessor::new(LLMConfig {
+            endpoint: format!("{}/api/generate", server.url()),
+            reduce_token_budget: 300,
+            reduce_group_size: 2,
+            ..LLMConfig::default()
+        });
+        let summaries: Vec<PageSummary> = ["a", "b", "c"]
+            .iter()
+            .map(|name| PageSummary {
+                url: format!("https://{}.example", name),
+                summary: name.repeat(400),
+            })
+            .collect();
+
+        // Three summaries exceed the budget, so the first two are merged before the final reduce
+        let groups = processor.reduce_groups("query", &summaries);
+        assert_eq!(groups.iter().map(|g| g.len()).collect::<Vec<_>>(), vec![2, 1]);
+
+        let answer = processor.reduce("query", &summaries, "llama3.2").await.unwrap();
+        assert_eq!(answer, "Merged.");
+        mock.assert_async().await;
+    }
+
+    /// Tests that every page is summarized on its own and the summaries are reduced in page order.
+    #[tokio::test]
+    async fn test_process_map_reduce() {
+        let mut server = mockito::Server::new_async().await;
+        let mut mocks = Vec::new();
+        for (name, summary) in [("alpha", "Alpha summary."), ("beta", "Beta summary.")] {
+            let mock = server
+                .mock("POST", "/api/generate")
+                .match_body(Matcher::Regex(format!(r"Summarize the following content.*{}\.example", name)))
+                .with_body(json!({ "response": summary, "done": true }).to_string())
+                .expect(1)
+                .create_async()
+                .await;
+            mocks.push(mock);
+        }
+        let reduce = server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::Regex(r"Alpha summary\..*Beta summary\.".to_string()))
+            .with_body(json!({ "response": "Final answer.", "done": true }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let page = |url: &str, text: &str| ScrapedContent {
+            url: url.to_string(),
+            content: text.to_string(),
+            metadata: std::collections::HashMap::new(),
+            timestamp: chrono::Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+        let contents = vec![
+            page("https://alpha.example", "Alpha page content."),
+            page("https://beta.example", "Beta page content."),
+        ];
+
+        let answer = processor(&server).process_map_reduce(contents, "query", "llama3.2").await.unwrap();
+        assert_eq!(answer, "Final answer.");
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+        reduce.assert_async().await;
+    }
+
+    /// Tests that classification picks a listed category and falls back to `UNCATEGORIZED`.
+    #[tokio::test]
+    async fn test_classify() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::Regex("Rust".to_string()))
+            .with_body(json!({ "response": " \"programming\". ", "done": true }).to_string())
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::Regex("Bread".to_string()))
+            .with_body(json!({ "response": "Cooking", "done": true }).to_string())
+            .create_async()
+            .await;
+
+        let processor = processor(&server);
+        let categories = vec!["Programming".to_string(), "Sports".to_string()];
+        let page = |url: &str, text: &str| ScrapedContent {
+            url: url.to_string(),
+            content: text.to_string(),
+            metadata: std::collections::HashMap::new(),
+            timestamp: chrono::Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+
+        let mut rust = page("https://rust.example", "Rust is a systems language.");
+        let category = processor.classify(&mut rust, &categories, "llama3.2").await.unwrap();
+        assert_eq!(category, "Programming");
+        assert_eq!(rust.metadata.get("category").map(String::as_str), Some("Programming"));
+
+        let mut bread = page("https://bread.example", "Bread needs flour and yeast.");
+        let category = processor.classify(&mut bread, &categories, "llama3.2").await.unwrap();
+        assert_eq!(category, UNCATEGORIZED);
+        assert_eq!(bread.metadata.get("category").map(String::as_str), Some(UNCATEGORIZED));
+    }
+
+    /// Tests that JSON output is requested with the schema, parsed into a struct and retried when invalid.
+    #[tokio::test]
+    async fn test_process_json() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Research {
+            title: String,
+            key_points: Vec<String>,
+            confidence: f32,
+        }
+
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "title": { "type": "string" },
+                "key_points": { "type": "array", "items": { "type": "string" } },
+                "confidence": { "type": "number" }
+            },
+            "required": ["title", "key_points", "confidence"]
+        });
+
+        let mut server = mockito::Server::new_async().await;
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let generate = server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJson(json!({ "format": schema })))
+            .with_body_from_request(move |_| {
+                let answer = match counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                    0 => "Here is the JSON: {\"title\": ".to_string(),
+                    _ => json!({ "title": "Rust", "key_points": ["Safe", "Fast"], "confidence": 0.9 }).to_string(),
+                };
+                json!({ "response": answer, "done": true }).to_string().into()
+            })
+            .expect(2)
+            .create_async()
+            .await;
+
+        let research: Research = processor(&server)
+            .process_json("Summarize Rust", "llama3.2", Some(&schema))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            research,
+            Research {
+                title: "Rust".to_string(),
+                key_points: vec!["Safe".to_string(), "Fast".to_string()],
+                confidence: 0.9,
+            }
+        );
+        generate.assert_async().await;
+
+        // Without a schema the plain JSON mode is requested, and persistent garbage is an error
+        let mut server = mockito::Server::new_async().await;
+        let garbage = server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJson(json!({ "format": "json" })))
+            .with_body(json!({ "response": "not json", "done": true }).to_string())
+            .expect(JSON_ATTEMPTS)
+            .create_async()
+            .await;
+
+        let result = processor(&server)
+            .process_json::<Research>("Summarize Rust", "llama3.2", None)
+            .await;
+        assert!(matches!(result, Err(ScraperError::LLMError(_))));
+        garbage.assert_async().await;
+    }
+
+    /// Tests listing the models installed on the Ollama server.
+    #[tokio::test]
+    async fn test_list_models() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/tags")
+            .with_body(json!({ "models": [{ "name": "llama3.2:latest" }, { "name": "mistral:7b" }] }).to_string())
+            .create_async()
+            .await;
+
+        let models = processor(&server).list_models().await.unwrap();
+        assert_eq!(models, vec!["llama3.2:latest", "mistral:7b"]);
+    }
+
+    /// Tests that the exact prompt sent to the model is written to the dump file.
+    #[tokio::test]
+    async fn test_dump_prompt() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/generate")
+            .with_body(json!({ "response": "Answer.", "done": true }).to_string())
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join(format!("sollama-prompt-{}.txt", std::process::id()));
+        let processor = LLMProcessor::new(LLMConfig {
+            endpoint: format!("{}/api/generate", server.url()),
+            dump_prompt: Some(path.clone()),
+            ..LLMConfig::default()
+        });
+
+        processor.process("What is Rust? Source: https://example.com", "llama3.2").await.unwrap();
+        let dumped = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(dumped, "What is Rust? Source: https://example.com");
+    }
+
+    /// Tests that prompts over `max_prompt_tokens` or declined by the confirmation callback are
+    /// never sent, and that confirmed prompts are.
+    #[tokio::test]
+    async fn test_prompt_limits() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/generate")
+            .with_body(json!({ "response": "Answer.", "done": true }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let prompt = "What is Rust? ".repeat(10);
+
+        let limited = LLMProcessor::new(LLMConfig {
+            endpoint: format!("{}/api/generate", server.url()),
+            max_prompt_tokens: Some(10),
+            ..LLMConfig::default()
+        });
+        assert!(matches!(limited.process(&prompt, "llama3.2").await, Err(ScraperError::LLMError(_))));
+
+        let declined = processor(&server).with_confirmation(Arc::new(|_| false));
+        assert!(matches!(declined.process(&prompt, "llama3.2").await, Err(ScraperError::LLMError(_))));
+
+        let expected = estimate_tokens(&prompt);
+        let confirmed = processor(&server).with_confirmation(Arc::new(move |tokens| tokens == expected));
+        assert_eq!(confirmed.process(&prompt, "llama3.2").await.unwrap(), "Answer.");
+
+        mock.assert_async().await;
+    }
+
+    /// Tests the request body serialized for each API format.
+    #[test]
+    fn test_request_body() {
+        let ollama = LLMProcessor::new(LLMConfig {
+            keep_alive: Some("300".to_string()),
+            ..LLMConfig::default()
+        });
+        let body = ollama.request_body("What is Rust?", "llama3.2").unwrap();
+        assert_eq!(body["prompt"], "What is Rust?");
+        assert_eq!(body["system"], SYSTEM_PROMPT);
+        assert_eq!(body["model"], "llama3.2");
+        assert_eq!(body["keep_alive"], 300);
+        assert!(body.get("messages").is_none());
+        assert!(body.get("options").is_none());
+
+        let openai = LLMProcessor::new(LLMConfig {
+            api_format: ApiFormat::OpenAiChat,
+            keep_alive: Some("300".to_string()),
+            ..LLMConfig::default()
+        });
+        let body = openai.request_body("What is Rust?", "qwen2.5").unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "model": "qwen2.5",
+                "messages": [
+                    { "role": "system", "content": SYSTEM_PROMPT },
+                    { "role": "user", "content": "What is Rust?" },
+                ],
+                "temperature": LLMConfig::default().temperature,
+                "max_tokens": 2048,
+                "stream": false
+            })
+        );
+    }
+
+    /// Tests chat completions against an OpenAI-compatible server, with the API key as a bearer token.
+    #[tokio::test]
+    async fn test_openai_chat() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/v1/chat/completions")
+            .match_header("authorization", "Bearer secret")
+            .match_body(Matcher::PartialJson(json!({ "stream": false })))
+            .with_body(json!({ "choices": [{ "message": { "role": "assistant", "content": "Answer." } }] }).to_string())
+            .create_async()
+            .await;
+        let events = [
+            json!({ "choices": [{ "delta": { "role": "assistant" }, "finish_reason": null }] }),
+            json!({ "choices": [{ "delta": { "content": "Rust" }, "finish_reason": null }] }),
+            json!({ "choices": [{ "delta": { "content": " is fast." }, "finish_reason": null }] }),
+            json!({ "choices": [{ "delta": {}, "finish_reason": "stop" }] }),
+        ];
+        let body: String = events.iter().map(|event| format!("data: {}\n\n", event)).collect();
+        server
+            .mock("POST", "/v1/chat/completions")
+            .match_header("authorization", "Bearer secret")
+            .match_body(Matcher::PartialJson(json!({ "stream": true })))
+            .with_body(format!("{}data: [DONE]\n\n", body))
+            .create_async()
+            .await;
+
+        let processor = LLMProcessor::new(LLMConfig {
+            endpoint: format!("{}/v1/chat/completions", server.url()),
+            api_format: ApiFormat::OpenAiChat,
+            api_key: Some("secret".to_string()),
+            ..LLMConfig::default()
+        });
+        assert_eq!(processor.process("What is Rust?", "qwen2.5").await.unwrap(), "Answer.");
+
+        let mut tokens = Vec::new();
+        let text = processor
+            .process_with_callback("What is Rust?", "qwen2.5", |token| tokens.push(token.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(tokens, vec!["Rust", " is fast."]);
+        assert_eq!(text, "Rust is fast.");
+    }
+
+    /// Tests that `process` streams its response to the token callback when one is set.
+    #[tokio::test]
+    async fn test_process_with_token_callback() {
+        let mut server = mockito::Server::new_async().await;
+        let lines = [
+            json!({ "response": "Streamed", "done": false }),
+            json!({ "response": " answer.", "done": false }),
+            json!({ "response": "", "done": true }),
+        ];
+        let body: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let mock = server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJson(json!({ "stream": true })))
+            .with_body(body)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let tokens = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = tokens.clone();
+        let processor = processor(&server)
+            .with_token_callback(Arc::new(move |token| sink.lock().unwrap().push(token.to_string())));
+
+        assert_eq!(processor.process("prompt", "llama3.2").await.unwrap(), "Streamed answer.");
+        assert_eq!(*tokens.lock().unwrap(), vec!["Streamed", " answer."]);
+        mock.assert_async().await;
+    }
+
+    /// Tests that only the sampling parameters that are set are sent, and that out-of-range
+    /// values are rejected.
+    #[test]
+    fn test_sampling_options() {
+        let processor = LLMProcessor::new(LLMConfig {
+            top_p: Some(0.9),
+            seed: Some(42),
+            ..LLMConfig::default()
+        });
+        let body = processor.request_body("prompt", "llama3.2").unwrap();
+        assert_eq!(body["options"], json!({ "top_p": 0.9f32, "seed": 42 }));
+
+        let processor = LLMProcessor::new(LLMConfig {
+            api_format: ApiFormat::OpenAiChat,
+            top_k: Some(40),
+            repeat_penalty: Some(1.1),
+            ..LLMConfig::default()
+        });
+        let body = processor.request_body("prompt", "qwen2.5").unwrap();
+        assert_eq!(body["top_k"], 40);
+        assert_eq!(body["repetition_penalty"], json!(1.1f32));
+        assert!(body.get("top_p").is_none());
+
+        let invalid = [
+            LLMConfig { top_p: Some(1.5), ..LLMConfig::default() },
+            LLMConfig { top_k: Some(0), ..LLMConfig::default() },
+            LLMConfig { repeat_penalty: Some(-1.0), ..LLMConfig::default() },
+        ];
+        for config in invalid {
+            let result = LLMProcessor::new(config).request_body("prompt", "llama3.2");
+            assert!(matches!(result, Err(ScraperError::LLMError(_))));
+        }
+    }
+}
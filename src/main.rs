@@ -1,84 +1,527 @@
-use std::time::Instant;
-use tracing::{error};
+use clap::{ArgAction, Parser, Subcommand};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{error, warn, Level};
 use sollama::{
-    config::ScraperConfig,
-    prompt::PromptBuilder,
-    search::SearchEngine,
+    config::{Recency, ScraperConfig},
     llm::LLMProcessor,
-    Result,
+    output::{parse_sections, truncate_words, ResultView, Section},
+    pipeline::{EXIT_FAILURE, EXIT_PARTIAL, EXIT_SUCCESS},
+    research::{DEFAULT_MODEL, DEFAULT_RESULTS},
+    search::SearchEngine,
+    urls::parse_url_list,
+    ResearchOptions, ResearchResult, Result, Sollama,
 };
 
+/// The `Cli` struct holds the command line options shared by every subcommand.
+#[derive(Parser)]
+#[command(name = "sollama", version, about = "Search the web and summarize the results with Ollama models")]
+struct Cli {
+    /// The configuration file to load instead of the defaults.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Increases logging verbosity (-v for debug, -vv for trace).
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Ignores the page and result caches for this run, fetching every page again.
+    #[arg(long, global = true)]
+    no_cache: bool,
+    /// The subcommand to run.
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// The `Command` enum lists the subcommands of the CLI, each with its own options.
+#[derive(Subcommand)]
+enum Command {
+    /// Searches the web and prints the result URLs.
+    Search {
+        /// The search query.
+        query: String,
+        /// The number of search results to return.
+        #[arg(short = 'n', long, default_value_t = DEFAULT_RESULTS)]
+        results: usize,
+        /// Restricts results to a recent time window (hour, day, week, month or year).
+        #[arg(long)]
+        recency: Option<Recency>,
+    },
+    /// Fetches pages and prints their extracted content.
+    Fetch {
+        /// The URLs to fetch.
+        #[arg(required_unless_present = "url_file")]
+        urls: Vec<String>,
+        /// A file with more URLs to fetch, one per line (blank lines and `#` comments are ignored).
+        #[arg(long)]
+        url_file: Option<PathBuf>,
+    },
+    /// Searches the web, fetches the results and answers a question about them.
+    Ask {
+        /// The search query.
+        #[arg(value_name = "QUERY", required_unless_present = "query", conflicts_with = "query")]
+        search_query: Option<String>,
+        /// The search query, as an alternative to the positional argument.
+        #[arg(long)]
+        query: Option<String>,
+        /// Comma-separated URLs to fetch instead of searching.
+        #[arg(long, value_delimiter = ',')]
+        urls: Vec<String>,
+        /// The question for the model (defaults to asking what the search query is).
+        #[arg(short, long)]
+        prompt: Option<String>,
+        /// The number of search results to fetch.
+        #[arg(short = 'n', long, default_value_t = DEFAULT_RESULTS)]
+        results: usize,
+        /// The model used to answer [default: $SOLLAMA_MODEL, or llama3.2:latest].
+        #[arg(short, long)]
+        model: Option<String>,
+        /// The comma-separated output sections to print (sources, query, timing, pages, summary).
+        #[arg(long)]
+        show: Option<String>,
+        /// Truncates the printed summary to this many words.
+        #[arg(long)]
+        max_output_words: Option<usize>,
+        /// Writes the full, untruncated result to this file.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Restricts results to a recent time window (hour, day, week, month or year).
+        #[arg(long)]
+        recency: Option<Recency>,
+        /// Writes the final prompt to this file (or stderr when no path is given) before calling the model.
+        #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-")]
+        dump_prompt: Option<PathBuf>,
+        /// Refuses to send a prompt estimated to be larger than this many tokens.
+        #[arg(long)]
+        max_prompt_tokens: Option<usize>,
+        /// Asks for confirmation with the prompt's estimated size before sending it (interactive only).
+        #[arg(long)]
+        confirm: bool,
+        /// Ignores any cached result and researches the query again.
+        #[arg(long)]
+        refresh: bool,
+        /// Waits for the whole answer instead of printing it as it is generated.
+        #[arg(long)]
+        no_stream: bool,
+        /// Prints the assembled prompt and its estimated size instead of calling the model.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Lists the models available on the Ollama server.
+    Models,
+}
+
 /// The main entry point of the application.
 ///
-/// This function initializes logging, loads the configuration, processes command line arguments,
-/// performs a search, fetches content from URLs, and processes the content using a Language Model (LLM).
+/// This function parses the command line, initializes logging, loads the configuration and
+/// dispatches to the selected subcommand. The process exits with code 0 when every stage
+/// succeeded, 2 when a result was produced despite some failures, and 1 when no result was produced.
 ///
 /// # Returns
 ///
 /// A `Result` indicating the success or failure of the operation.
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     // Initialize logging
-    tracing_subscriber::fmt::init();
+    let level = match cli.verbose {
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt().with_max_level(level).init();
 
-    // Load configuration
-    let config = ScraperConfig::default();
+    // Load configuration, with SOLLAMA_* environment variables taking precedence over the file
+    let mut config = match &cli.config {
+        Some(path) => ScraperConfig::load(path)?,
+        None => ScraperConfig::default(),
+    };
+    if let Err(e) = config.apply_env() {
+        error!("{}", e);
+        std::process::exit(EXIT_FAILURE);
+    }
+    if cli.no_cache {
+        // Both caches live in cache_dir; failure counts are then only kept for this run
+        config.cache_dir = None;
+        config.result_cache_ttl = None;
+    }
+    if let Err(problems) = config.validate() {
+        for problem in problems {
+            error!("Invalid configuration: {}", problem);
+        }
+        std::process::exit(EXIT_FAILURE);
+    }
+
+    let exit_code = match cli.command {
+        Command::Search { query, results, recency } => {
+            config.recency = recency.or(config.recency);
+            search(config, &query, results).await
+        }
+        Command::Fetch { mut urls, url_file } => {
+            if let Some(path) = url_file {
+                let text = match std::fs::read_to_string(&path) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        error!("Failed to read URL file {}: {}", path.display(), e);
+                        std::process::exit(EXIT_FAILURE);
+                    }
+                };
+                let (valid, malformed) = parse_url_list(&text);
+                for (line, entry) in malformed {
+                    warn!("Skipping malformed URL on line {} of {}: {}", line, path.display(), entry);
+                }
+                urls.extend(valid);
+            }
+            fetch(config, urls).await
+        }
+        Command::Ask {
+            search_query,
+            query,
+            urls,
+            prompt,
+            results,
+            model,
+            show,
+            max_output_words,
+            output,
+            recency,
+            dump_prompt,
+            max_prompt_tokens,
+            confirm,
+            refresh,
+            no_stream,
+            dry_run,
+        } => {
+            config.recency = recency.or(config.recency);
+            config.llm_config.dump_prompt = dump_prompt.or(config.llm_config.dump_prompt);
+            config.llm_config.max_prompt_tokens = max_prompt_tokens.or(config.llm_config.max_prompt_tokens);
+            // clap requires one of the two
+            let search_query = search_query.or(query).unwrap_or_default();
+            let options = ResearchOptions {
+                question: prompt,
+                results,
+                model: model
+                    .or_else(|| std::env::var("SOLLAMA_MODEL").ok().filter(|model| !model.is_empty()))
+                    .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+                refresh,
+                urls: urls
+                    .into_iter()
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty())
+                    .collect(),
+                ..ResearchOptions::default()
+            };
+            let sections = match show.as_deref().map(parse_sections) {
+                Some(Ok(sections)) => sections,
+                Some(Err(e)) => {
+                    error!("{}", e);
+                    std::process::exit(EXIT_FAILURE);
+                }
+                None => Section::ALL.to_vec(),
+            };
+            let mut sollama = Sollama::new(config);
+            if dry_run {
+                std::process::exit(print_dry_run(&sollama, &search_query, &options).await);
+            }
+            if confirm && std::io::stdin().is_terminal() {
+                sollama = sollama.with_confirmation(Arc::new(confirm_prompt));
+            }
+            // A truncated summary can only be printed once it is complete
+            let stream = !no_stream && max_output_words.is_none() && sections.contains(&Section::Summary);
+            if stream {
+                sollama = sollama.with_token_callback(Arc::new(print_token()));
+            }
+            ask(&sollama, &search_query, &options, &sections, max_output_words, output, stream).await
+        }
+        Command::Models => models(config).await,
+    };
 
-    // Get query from command line arguments
-    let search_query = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "rust programming".to_string());
+    std::process::exit(exit_code);
+}
 
-    let query = std::env::args()
-        .nth(2)
-        .unwrap_or_else(|| format!("based on the content provided what is : {}", search_query));
+/// Runs the `search` subcommand, printing one result URL per line.
+///
+/// # Arguments
+///
+/// * `config` - The scraper configuration.
+/// * `query` - The search query.
+/// * `results` - The number of search results to return.
+///
+/// # Returns
+///
+/// The process exit code.
+async fn search(config: ScraperConfig, query: &str, results: usize) -> i32 {
+    let results = match SearchEngine::new(config) {
+        Ok(engine) => engine.search(query, results).await,
+        Err(e) => Err(e),
+    };
 
-    let results_count = std::env::args()
-        .nth(3)
-        .unwrap_or_else(|| "5".to_string());
+    match results {
+        Ok(results) => {
+            for result in results {
+                println!("{}", result.url);
+            }
+            EXIT_SUCCESS
+        }
+        Err(e) => {
+            error!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
 
-    let model = std::env::args()
-        .nth(4)
-        .unwrap_or_else(|| "llama3.2:latest".to_string());
+/// Runs the `fetch` subcommand, printing the extracted content of each page.
+///
+/// # Arguments
+///
+/// * `config` - The scraper configuration.
+/// * `urls` - The URLs to fetch.
+///
+/// # Returns
+///
+/// The process exit code.
+async fn fetch(config: ScraperConfig, urls: Vec<String>) -> i32 {
+    let engine = match SearchEngine::new(config) {
+        Ok(engine) => engine,
+        Err(e) => {
+            error!("{}", e);
+            return EXIT_FAILURE;
+        }
+    };
 
-    let start_time = Instant::now();
+    let mut fetched = 0;
+    let mut failed = 0;
+    for (url, result) in engine.fetch_all_results(urls).await {
+        match result {
+            Ok(content) => {
+                fetched += 1;
+                println!("Source: {}\n{}\n", content.url, content.content);
+            }
+            Err(e) => {
+                failed += 1;
+                warn!("Failed to fetch {}: {}", url, e);
+            }
+        }
+    }
 
-    // Initialize search engine
-    let search_engine = SearchEngine::new(config.clone())?;
+    match (fetched, failed) {
+        (0, _) => EXIT_FAILURE,
+        (_, 0) => EXIT_SUCCESS,
+        _ => EXIT_PARTIAL,
+    }
+}
 
-    // Perform search and content gathering
-    let urls = search_engine.search(&search_query, &results_count).await?;
+/// Runs the `ask` subcommand: the full search, fetch and LLM pipeline, or a cached result.
+///
+/// # Arguments
+///
+/// * `sollama` - The pipeline to run.
+/// * `search_query` - The search query.
+/// * `options` - The question, result count and model.
+/// * `sections` - The output sections to print.
+/// * `max_output_words` - The maximum number of words of the printed summary, if limited.
+/// * `output_path` - The file the full result is written to, if any.
+/// * `streamed` - Whether the summary is printed by the token callback as it is generated.
+///
+/// # Returns
+///
+/// The process exit code.
+async fn ask(
+    sollama: &Sollama,
+    search_query: &str,
+    options: &ResearchOptions,
+    sections: &[Section],
+    max_output_words: Option<usize>,
+    output_path: Option<PathBuf>,
+    streamed: bool,
+) -> i32 {
+    let query = options.question_for(search_query);
 
-    if urls.len() == 0 {
-        error!("No URLs found for the query: {}", query);
-        return Ok(());
+    if let Some(result) = sollama.cached_result(search_query, options) {
+        let view = ResultView {
+            search_query,
+            query: &query,
+            sources: &result.sources,
+            processing_time: result.stats.elapsed,
+            pages_analyzed: result.stats.pages_used,
+            summary: &result.summary,
+        };
+        print_result(view, sections, max_output_words, output_path.as_deref(), false);
+        return EXIT_SUCCESS;
     }
 
-    // Fetch content from all URLs
-    let contents = search_engine.fetch_all(urls.clone()).await?;
+    let outcome = sollama.run(search_query, options).await;
 
-    // Process with LLM
-    let llm_processor = LLMProcessor::new(config.llm_config);
-    let prompt = PromptBuilder::new(query.clone())
-        .with_contents(contents.clone())
-        .build();
+    if let Some(broadened) = &outcome.broadened_query {
+        warn!("Results are for the broadened search query '{}'", broadened);
+    }
 
-    match llm_processor.process(&prompt, &model).await {
-        Ok(summary) => {
-            let elapsed = start_time.elapsed();
+    for (url, e) in outcome.failed_fetches() {
+        warn!("Failed to fetch {}: {}", url, e);
+    }
 
-            println!("{}", format!("\n=== Search Results Summary ===\n {}\n", &urls.join("\n")));
-            println!("\n=== Search Results Summary ===");
-            println!("Search Query: {}", search_query);
-            println!("Query: {}", query);
-            println!("Processing time: {:.2?}", elapsed);
-            println!("Pages analyzed: {}", contents.len());
-            println!("\nSummary:\n{}", summary);
+    match outcome.summary_text() {
+        Some(summary) => {
+            let view = ResultView {
+                search_query,
+                query: &query,
+                sources: outcome.urls(),
+                processing_time: outcome.elapsed,
+                pages_analyzed: outcome.pages_analyzed(),
+                summary,
+            };
+            print_result(view, sections, max_output_words, output_path.as_deref(), streamed);
         }
-        Err(e) => {
-            error!("Failed to process with LLM: {}", e);
+        None => {
+            if let Some(e) = outcome.error() {
+                error!("{}", e);
+            }
+        }
+    }
+
+    let exit_code = outcome.exit_code();
+    if let Ok(result) = ResearchResult::try_from(outcome) {
+        sollama.cache_result(search_query, options, &result);
+    }
+    exit_code
+}
+
+/// Runs the `ask` subcommand with `--dry-run`: searches and fetches, then prints the prompt that
+/// would have been sent and its estimated token count without calling the model.
+///
+/// # Arguments
+///
+/// * `sollama` - The pipeline to run.
+/// * `search_query` - The search query.
+/// * `options` - The question and result count.
+///
+/// # Returns
+///
+/// The process exit code: `EXIT_SUCCESS` if the prompt was built, `EXIT_FAILURE` otherwise.
+async fn print_dry_run(sollama: &Sollama, search_query: &str, options: &ResearchOptions) -> i32 {
+    let outcome = sollama.dry_run(search_query, options).await;
+
+    if let Some(broadened) = &outcome.broadened_query {
+        warn!("Results are for the broadened search query '{}'", broadened);
+    }
+
+    for (url, e) in outcome.failed_fetches() {
+        warn!("Failed to fetch {}: {}", url, e);
+    }
+
+    match (&outcome.prompt, outcome.prompt_tokens) {
+        (Some(prompt), Some(tokens)) => {
+            println!("Dry run: no model was invoked.");
+            println!("Pages analyzed: {}", outcome.pages_analyzed());
+            println!("Estimated prompt tokens: {}", tokens);
+            println!("\nPrompt:\n{}", prompt);
+            EXIT_SUCCESS
+        }
+        _ => {
+            if let Some(e) = outcome.error() {
+                error!("{}", e);
+            }
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Prints a result and writes it to the output file, if any.
+///
+/// # Arguments
+///
+/// * `view` - The result to print.
+/// * `sections` - The output sections to print.
+/// * `max_output_words` - The maximum number of words of the printed summary, if limited.
+/// * `output_path` - The file the full result is written to, if any.
+/// * `streamed` - Whether the summary was already printed as it was generated, in which case only
+///   the other sections are printed after it.
+fn print_result(
+    view: ResultView<'_>,
+    sections: &[Section],
+    max_output_words: Option<usize>,
+    output_path: Option<&Path>,
+    streamed: bool,
+) {
+    // The output file always gets the full summary
+    if let Some(path) = output_path {
+        if let Err(e) = std::fs::write(path, view.render(sections)) {
+            error!("Failed to write output to {}: {}", path.display(), e);
+        }
+    }
+
+    if streamed {
+        let rest: Vec<Section> = sections.iter().copied().filter(|&section| section != Section::Summary).collect();
+        println!();
+        print!("{}", view.render(&rest));
+        return;
+    }
+
+    match max_output_words {
+        Some(max_words) => {
+            let truncated = truncate_words(view.summary, max_words);
+            print!("{}", ResultView { summary: &truncated, ..view }.render(sections));
         }
+        None => print!("{}", view.render(sections)),
     }
+}
 
-    Ok(())
-}
\ No newline at end of file
+/// Creates the token callback that prints the model's answer to stdout as it is generated,
+/// under the same header as a complete summary.
+///
+/// # Returns
+///
+/// The callback.
+fn print_token() -> impl Fn(&str) + Send + Sync {
+    let started = AtomicBool::new(false);
+    move |token| {
+        if !started.swap(true, Ordering::Relaxed) {
+            print!("\nSummary:\n");
+        }
+        print!("{}", token);
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Asks on the terminal whether a prompt of the given size should be sent to the model.
+///
+/// # Arguments
+///
+/// * `tokens` - The prompt's estimated token count.
+///
+/// # Returns
+///
+/// `true` if the user answered yes, `false` otherwise.
+fn confirm_prompt(tokens: usize) -> bool {
+    eprint!("Send a prompt of ~{} estimated tokens to the model? [y/N] ", tokens);
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Runs the `models` subcommand, printing the models available on the Ollama server.
+///
+/// # Arguments
+///
+/// * `config` - The scraper configuration, whose LLM endpoint identifies the server.
+///
+/// # Returns
+///
+/// The process exit code.
+async fn models(config: ScraperConfig) -> i32 {
+    match LLMProcessor::new(config.llm_config).list_models().await {
+        Ok(models) => {
+            for model in models {
+                println!("{}", model);
+            }
+            EXIT_SUCCESS
+        }
+        Err(e) => {
+            error!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The marker appended to text cut short by `truncate_words`.
+pub const TRUNCATION_MARKER: &str = "[...truncated]";
+
+/// The `Section` enum lists the parts of the final CLI output that can be toggled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    /// The list of source URLs.
+    Sources,
+    /// The search query and the question sent to the LLM.
+    Query,
+    /// The total processing time.
+    Timing,
+    /// The number of pages analyzed.
+    Pages,
+    /// The LLM summary.
+    Summary,
+}
+
+impl Section {
+    /// All sections, in display order.
+    pub const ALL: [Section; 5] = [
+        Section::Sources,
+        Section::Query,
+        Section::Timing,
+        Section::Pages,
+        Section::Summary,
+    ];
+
+    /// Returns the name used for the section on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Section::Sources => "sources",
+            Section::Query => "query",
+            Section::Timing => "timing",
+            Section::Pages => "pages",
+            Section::Summary => "summary",
+        }
+    }
+}
+
+impl FromStr for Section {
+    type Err = String;
+
+    /// Parses a section from its command line name.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        Section::ALL
+            .into_iter()
+            .find(|section| section.name() == name)
+            .ok_or_else(|| {
+                let valid = Section::ALL.map(|section| section.name()).join(", ");
+                format!("Unknown output section '{}' (expected one of: {})", s.trim(), valid)
+            })
+    }
+}
+
+/// Parses a comma-separated list of sections, e.g. `sources,timing,summary`.
+///
+/// # Arguments
+///
+/// * `list` - The comma-separated section names.
+///
+/// # Returns
+///
+/// A `Result` containing the sections, or a message describing the first unknown name.
+pub fn parse_sections(list: &str) -> std::result::Result<Vec<Section>, String> {
+    list.split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(Section::from_str)
+        .collect()
+}
+
+/// Truncates text to at most `max_words` words, appending `TRUNCATION_MARKER` when anything is cut.
+///
+/// The text is cut at a word boundary and its original whitespace, including line breaks, is kept.
+///
+/// # Arguments
+///
+/// * `text` - The text to truncate.
+/// * `max_words` - The maximum number of words to keep.
+///
+/// # Returns
+///
+/// The truncated text, or the original text if it has no more than `max_words` words.
+pub fn truncate_words(text: &str, max_words: usize) -> String {
+    let mut words = 0;
+    let mut in_word = false;
+
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            in_word = false;
+        } else if !in_word {
+            if words == max_words {
+                let kept = text[..i].trim_end();
+                return if kept.is_empty() {
+                    TRUNCATION_MARKER.to_string()
+                } else {
+                    format!("{} {}", kept, TRUNCATION_MARKER)
+                };
+            }
+            in_word = true;
+            words += 1;
+        }
+    }
+
+    text.to_string()
+}
+
+/// The `ResultView` struct holds everything the CLI can display about a completed run.
+/// It renders only the sections that were requested.
+#[derive(Debug, Clone)]
+pub struct ResultView<'a> {
+    /// The search query.
+    pub search_query: &'a str,
+    /// The question sent to the LLM.
+    pub query: &'a str,
+    /// The source URLs.
+    pub sources: &'a [String],
+    /// The total processing time.
+    pub processing_time: Duration,
+    /// The number of pages analyzed.
+    pub pages_analyzed: usize,
+    /// The LLM summary.
+    pub summary: &'a str,
+}
+
+impl ResultView<'_> {
+    /// Renders the requested sections, in display order.
+    ///
+    /// # Arguments
+    ///
+    /// * `sections` - The sections to include.
+    ///
+    /// # Returns
+    ///
+    /// The formatted output.
+    pub fn render(&self, sections: &[Section]) -> String {
+        let show = |section: Section| sections.contains(&section);
+        let mut out = String::new();
+
+        if show(Section::Sources) {
+            let _ = writeln!(out, "\n=== Sources ===");
+            for source in self.sources {
+                let _ = writeln!(out, "{}", source);
+            }
+        }
+
+        if show(Section::Query) || show(Section::Timing) || show(Section::Pages) {
+            let _ = writeln!(out, "\n=== Search Results Summary ===");
+        }
+        if show(Section::Query) {
+            let _ = writeln!(out, "Search Query: {}", self.search_query);
+            let _ = writeln!(out, "Query: {}", self.query);
+        }
+        if show(Section::Timing) {
+            let _ = writeln!(out, "Processing time: {:.2?}", self.processing_time);
+        }
+        if show(Section::Pages) {
+            let _ = writeln!(out, "Pages analyzed: {}", self.pages_analyzed);
+        }
+
+        if show(Section::Summary) {
+            let _ = writeln!(out, "\nSummary:\n{}", self.summary);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(sources: &[String]) -> ResultView<'_> {
+        ResultView {
+            search_query: "rust",
+            query: "what is rust",
+            sources,
+            processing_time: Duration::from_millis(1500),
+            pages_analyzed: 2,
+            summary: "Rust is a language.",
+        }
+    }
+
+    /// Tests parsing of comma-separated section lists.
+    #[test]
+    fn test_parse_sections() {
+        assert_eq!(
+            parse_sections("sources, Timing,summary").unwrap(),
+            vec![Section::Sources, Section::Timing, Section::Summary]
+        );
+        assert!(parse_sections("sources,bogus").unwrap_err().contains("bogus"));
+    }
+
+    /// Tests that all sections render with a single summary header.
+    #[test]
+    fn test_render_all_sections() {
+        let sources = vec!["https://a.example".to_string(), "https://b.example".to_string()];
+        let out = view(&sources).render(&Section::ALL);
+
+        assert_eq!(out.matches("=== Search Results Summary ===").count(), 1);
+        assert_eq!(out.matches("https://a.example").count(), 1);
+        assert!(out.contains("Pages analyzed: 2"));
+        assert!(out.contains("Rust is a language."));
+    }
+
+    /// Tests that only the requested sections are rendered.
+    #[test]
+    fn test_render_selected_sections() {
+        let sources = vec!["https://a.example".to_string()];
+        let out = view(&sources).render(&[Section::Summary]);
+
+        assert!(out.contains("Rust is a language."));
+        assert!(!out.contains("https://a.example"));
+        assert!(!out.contains("Processing time"));
+    }
+
+    /// Tests word-boundary truncation of long summaries.
+    #[test]
+    fn test_truncate_words() {
+        assert_eq!(truncate_words("one two three", 3), "one two three");
+        assert_eq!(truncate_words("one two three  ", 3), "one two three  ");
+        assert_eq!(truncate_words("one two\nthree four", 3), "one two\nthree [...truncated]");
+        assert_eq!(truncate_words("one,  two three", 1), "one, [...truncated]");
+        assert_eq!(truncate_words("one two", 0), TRUNCATION_MARKER);
+        assert_eq!(truncate_words("", 0), "");
+    }
+}
@@ -0,0 +1,521 @@
+use crate::{
+    llm::{estimate_tokens, LLMProcessor},
+    prompt::PromptBuilder,
+    quality,
+    search::{broaden_query, SearchEngine},
+    Result, ScrapedContent, ScraperConfig, ScraperError, SearchHit,
+};
+use futures::{future, StreamExt};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// The exit code for a run where every stage succeeded.
+pub const EXIT_SUCCESS: i32 = 0;
+/// The exit code for a run that produced no summary.
+pub const EXIT_FAILURE: i32 = 1;
+/// The exit code for a run that produced a summary even though some pages could not be fetched.
+pub const EXIT_PARTIAL: i32 = 2;
+
+/// The `FetchStatus` enum describes what happened to a single search result URL.
+#[derive(Debug)]
+pub enum FetchStatus {
+    /// The page was fetched and passed on to the LLM.
+    Used,
+    /// The page was fetched but rejected by the quality filter.
+    Filtered,
+    /// The page could not be fetched.
+    Failed(ScraperError),
+}
+
+/// The `FetchOutcome` struct pairs a URL with the status of its fetch.
+#[derive(Debug)]
+pub struct FetchOutcome {
+    /// The URL that was fetched.
+    pub url: String,
+    /// What happened to the page.
+    pub status: FetchStatus,
+}
+
+/// The `PipelineOutcome` struct captures the result of each stage of a search-and-summarize run,
+/// so callers can tell a partial success apart from a complete one.
+#[derive(Debug)]
+pub struct PipelineOutcome {
+    /// The URLs returned by the search, or the error if the search failed.
+    pub search: Result<Vec<String>>,
+    /// The broadened query the URLs were found with, if the original query found nothing.
+    pub broadened_query: Option<String>,
+    /// The outcome of fetching each URL, in completion order.
+    pub fetches: Vec<FetchOutcome>,
+    /// The LLM summary, or `None` if the run stopped before reaching the LLM.
+    pub summary: Option<Result<String>>,
+    /// The estimated token count of the prompt sent to the LLM, or `None` if no single prompt
+    /// was built (the run stopped early or used map-reduce summarization).
+    pub prompt_tokens: Option<usize>,
+    /// The prompt that would have been sent to the LLM; only kept by dry runs.
+    pub prompt: Option<String>,
+    /// The total time the run took.
+    pub elapsed: Duration,
+}
+
+impl PipelineOutcome {
+    /// Creates the outcome of a run that failed before it could search.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error that stopped the run.
+    ///
+    /// # Returns
+    ///
+    /// A `PipelineOutcome` with the error as its search result.
+    pub(crate) fn failed(error: ScraperError) -> Self {
+        Self {
+            search: Err(error),
+            ..Self::new()
+        }
+    }
+
+    /// Creates the empty outcome of a run that is about to start.
+    fn new() -> Self {
+        Self {
+            search: Ok(Vec::new()),
+            broadened_query: None,
+            fetches: Vec::new(),
+            summary: None,
+            prompt_tokens: None,
+            prompt: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Returns the URLs found by the search, or an empty slice if the search failed.
+    pub fn urls(&self) -> &[String] {
+        self.search.as_deref().unwrap_or(&[])
+    }
+
+    /// Returns the number of pages that were sent to the LLM.
+    pub fn pages_analyzed(&self) -> usize {
+        self.fetches
+            .iter()
+            .filter(|fetch| matches!(fetch.status, FetchStatus::Used))
+            .count()
+    }
+
+    /// Returns the fetches that failed, with their errors.
+    pub fn failed_fetches(&self) -> impl Iterator<Item = (&str, &ScraperError)> {
+        self.fetches.iter().filter_map(|fetch| match &fetch.status {
+            FetchStatus::Failed(e) => Some((fetch.url.as_str(), e)),
+            _ => None,
+        })
+    }
+
+    /// Returns the summary if the LLM stage succeeded.
+    pub fn summary_text(&self) -> Option<&str> {
+        match &self.summary {
+            Some(Ok(summary)) => Some(summary),
+            _ => None,
+        }
+    }
+
+    /// Returns the first error that stopped the run from producing a summary, if any.
+    pub fn error(&self) -> Option<&ScraperError> {
+        match (&self.search, &self.summary) {
+            (Err(e), _) => Some(e),
+            (_, Some(Err(e))) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Returns the process exit code describing the outcome.
+    ///
+    /// # Returns
+    ///
+    /// `EXIT_SUCCESS` if every stage succeeded, `EXIT_PARTIAL` if a summary was produced despite
+    /// failed fetches, or `EXIT_FAILURE` if no summary was produced.
+    pub fn exit_code(&self) -> i32 {
+        if self.summary_text().is_none() {
+            EXIT_FAILURE
+        } else if self.failed_fetches().next().is_some() {
+            EXIT_PARTIAL
+        } else {
+            EXIT_SUCCESS
+        }
+    }
+}
+
+/// Runs the full pipeline: search, fetch, quality filtering and LLM summarization.
+///
+/// With `broaden_on_empty`, a search that finds no URLs is retried once with a broadened query,
+/// which is recorded in `PipelineOutcome::broadened_query`. When `urls` are given the search is
+/// skipped and they are fetched instead, after checking them with `SearchEngine::validate_urls`.
+///
+/// Every stage's result is recorded instead of aborting on the first error, so a run where
+/// some pages failed still returns its summary together with the failures.
+///
+/// # Arguments
+///
+/// * `config` - The configuration settings for the scraper.
+/// * `search_query` - The search query.
+/// * `query` - The question to answer from the fetched pages.
+/// * `results_count` - The number of search results to fetch.
+/// * `urls` - The URLs to fetch instead of searching, or an empty slice to search.
+/// * `model` - The name of the model to use.
+///
+/// # Returns
+///
+/// A `PipelineOutcome` describing what each stage produced.
+pub async fn run(
+    config: &ScraperConfig,
+    search_query: &str,
+    query: &str,
+    results_count: usize,
+    urls: &[String],
+    model: &str,
+) -> PipelineOutcome {
+    let search_engine = match SearchEngine::new(config.clone()) {
+        Ok(search_engine) => search_engine,
+        Err(e) => return PipelineOutcome::failed(e),
+    };
+
+    run_with(&search_engine, &llm_processor(config), search_query, query, results_count, urls, model).await
+}
+
+/// Creates the LLM processor for a configuration, applying its `llm_timeout`.
+///
+/// # Arguments
+///
+/// * `config` - The configuration settings for the scraper.
+///
+/// # Returns
+///
+/// A new `LLMProcessor`.
+pub(crate) fn llm_processor(config: &ScraperConfig) -> LLMProcessor {
+    let llm_processor = LLMProcessor::new(config.llm_config.clone());
+    match config.llm_timeout {
+        Some(timeout) => llm_processor.with_timeout(timeout),
+        None => llm_processor,
+    }
+}
+
+/// Runs the full pipeline like `run`, using an existing search engine and LLM processor.
+///
+/// Runs sharing an engine share its HTTP connection pool, rate limiter, per-host pacing and
+/// download budget, which is how several queries can be researched side by side.
+///
+/// # Arguments
+///
+/// * `search_engine` - The search engine used to search and fetch; its configuration drives the run.
+/// * `llm_processor` - The LLM processor used to answer.
+/// * `search_query` - The search query.
+/// * `query` - The question to answer from the fetched pages.
+/// * `results_count` - The number of search results to fetch.
+/// * `urls` - The URLs to fetch instead of searching, or an empty slice to search.
+/// * `model` - The name of the model to use.
+///
+/// # Returns
+///
+/// A `PipelineOutcome` describing what each stage produced.
+pub async fn run_with(
+    search_engine: &SearchEngine,
+    llm_processor: &LLMProcessor,
+    search_query: &str,
+    query: &str,
+    results_count: usize,
+    urls: &[String],
+    model: &str,
+) -> PipelineOutcome {
+    let start_time = Instant::now();
+    let mut outcome = PipelineOutcome::new();
+
+    let Some(urls) = search_stage(search_engine, search_query, results_count, urls, &mut outcome).await else {
+        outcome.elapsed = start_time.elapsed();
+        return outcome;
+    };
+
+    let config = search_engine.config();
+    let summary = if config.llm_config.map_reduce {
+        let quality_filter = &config.quality_filter;
+        let relevance_query = outcome.broadened_query.clone().unwrap_or_else(|| search_query.to_string());
+        let keep = |content: &ScrapedContent| {
+            quality_filter.passes(content) && quality_filter.is_relevant(content, &relevance_query)
+        };
+        let fetches = &mut outcome.fetches;
+
+        // Summarize each page as soon as it is fetched, then combine the summaries
+        let pages = search_engine.fetch_results(urls).filter_map(|(url, result)| {
+            let page = match result {
+                Ok(content) if keep(&content) => {
+                    fetches.push(FetchOutcome { url, status: FetchStatus::Used });
+                    Some(content)
+                }
+                Ok(_) => {
+                    fetches.push(FetchOutcome { url, status: FetchStatus::Filtered });
+                    None
+                }
+                Err(e) => {
+                    fetches.push(FetchOutcome { url, status: FetchStatus::Failed(e) });
+                    None
+                }
+            };
+            future::ready(page)
+        });
+
+        let summaries = llm_processor.map_stream(pages, query, model).await;
+        llm_processor.reduce(query, &summaries, model).await
+    } else {
+        match fetch_stage(search_engine, search_query, urls, &mut outcome).await {
+            Ok(contents) => {
+                let prompt = PromptBuilder::new(query.to_string())
+                    .with_contents(contents)
+                    .build();
+                outcome.prompt_tokens = Some(estimate_tokens(&prompt));
+
+                llm_processor.process(&prompt, model).await
+            }
+            Err(e) => Err(e),
+        }
+    };
+
+    debug!(
+        "Pipeline finished: {} fetched, {} used",
+        outcome.fetches.len(),
+        outcome.pages_analyzed()
+    );
+
+    outcome.summary = Some(summary);
+    outcome.elapsed = start_time.elapsed();
+    outcome
+}
+
+/// Runs the search and fetch stages like `run_with` and builds the prompt, but doesn't call the
+/// LLM. The prompt is kept in `PipelineOutcome::prompt` with its estimated token count, and
+/// `summary` is only set if the run failed before the prompt could be built.
+///
+/// Dry runs always build the single prompt, even when `map_reduce` is enabled, so selectors and
+/// the token budget can be tuned without a running model.
+///
+/// # Arguments
+///
+/// * `search_engine` - The search engine used to search and fetch; its configuration drives the run.
+/// * `search_query` - The search query.
+/// * `query` - The question to answer from the fetched pages.
+/// * `results_count` - The number of search results to fetch.
+/// * `urls` - The URLs to fetch instead of searching, or an empty slice to search.
+///
+/// # Returns
+///
+/// A `PipelineOutcome` describing what each stage produced.
+pub async fn dry_run_with(
+    search_engine: &SearchEngine,
+    search_query: &str,
+    query: &str,
+    results_count: usize,
+    urls: &[String],
+) -> PipelineOutcome {
+    let start_time = Instant::now();
+    let mut outcome = PipelineOutcome::new();
+
+    if let Some(urls) = search_stage(search_engine, search_query, results_count, urls, &mut outcome).await {
+        match fetch_stage(search_engine, search_query, urls, &mut outcome).await {
+            Ok(contents) => {
+                let prompt = PromptBuilder::new(query.to_string())
+                    .with_contents(contents)
+                    .build();
+                outcome.prompt_tokens = Some(estimate_tokens(&prompt));
+                outcome.prompt = Some(prompt);
+            }
+            Err(e) => outcome.summary = Some(Err(e)),
+        }
+    }
+
+    outcome.elapsed = start_time.elapsed();
+    outcome
+}
+
+/// Runs the search stage, retrying once with a broadened query if `broaden_on_empty` is set and
+/// nothing was found, or checks the given URLs instead. The URLs, or the error that stopped the
+/// run, are recorded in the outcome.
+///
+/// # Arguments
+///
+/// * `search_engine` - The search engine to search with.
+/// * `search_query` - The search query.
+/// * `results_count` - The number of search results to fetch.
+/// * `urls` - The URLs to fetch instead of searching, or an empty slice to search.
+/// * `outcome` - The outcome the search result is recorded in.
+///
+/// # Returns
+///
+/// An `Option` containing the URLs to fetch, or `None` if the run should stop.
+async fn search_stage(
+    search_engine: &SearchEngine,
+    search_query: &str,
+    results_count: usize,
+    urls: &[String],
+    outcome: &mut PipelineOutcome,
+) -> Option<Vec<String>> {
+    if !urls.is_empty() {
+        outcome.search = search_engine.validate_urls(urls).map(|()| urls.to_vec());
+        return outcome.search.as_ref().ok().cloned();
+    }
+
+    outcome.search = search_engine
+        .search(search_query, results_count)
+        .await
+        .map(|results| SearchHit::urls(&results));
+
+    if search_engine.config().broaden_on_empty && outcome.search.as_ref().is_ok_and(|urls| urls.is_empty()) {
+        if let Some(broadened) = broaden_query(search_query) {
+            warn!("No URLs found for '{}', retrying with the broadened query '{}'", search_query, broadened);
+            outcome.search = search_engine
+                .search(&broadened, results_count)
+                .await
+                .map(|results| SearchHit::urls(&results));
+            outcome.broadened_query = Some(broadened);
+        }
+    }
+
+    match &outcome.search {
+        Ok(urls) if !urls.is_empty() => Some(urls.clone()),
+        Ok(_) => {
+            outcome.search = Err(ScraperError::SearchError(format!(
+                "No URLs found for the query: {}",
+                search_query
+            )));
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Fetches every URL and keeps the pages that pass the quality and relevance filters, recording
+/// the status of each fetch in the outcome.
+///
+/// # Arguments
+///
+/// * `search_engine` - The search engine to fetch with.
+/// * `search_query` - The search query, used for the relevance filter unless it was broadened.
+/// * `urls` - The URLs to fetch.
+/// * `outcome` - The outcome the fetches are recorded in.
+///
+/// # Returns
+///
+/// A `Result` containing the kept pages, or an `ExtractionError` if none were kept.
+async fn fetch_stage(
+    search_engine: &SearchEngine,
+    search_query: &str,
+    urls: Vec<String>,
+    outcome: &mut PipelineOutcome,
+) -> Result<Vec<ScrapedContent>> {
+    let quality_filter = &search_engine.config().quality_filter;
+    let relevance_query = outcome.broadened_query.clone().unwrap_or_else(|| search_query.to_string());
+    let keep = |content: &ScrapedContent| {
+        quality_filter.passes(content) && quality_filter.is_relevant(content, &relevance_query)
+    };
+
+    let mut contents = Vec::new();
+    for (url, result) in search_engine.fetch_all_results(urls).await {
+        match result {
+            Ok(content) if keep(&content) => {
+                outcome.fetches.push(FetchOutcome { url, status: FetchStatus::Used });
+                contents.push(content);
+            }
+            Ok(_) => outcome.fetches.push(FetchOutcome { url, status: FetchStatus::Filtered }),
+            Err(e) => outcome.fetches.push(FetchOutcome { url, status: FetchStatus::Failed(e) }),
+        }
+    }
+
+    if contents.is_empty() {
+        let fetched = outcome
+            .fetches
+            .iter()
+            .filter(|fetch| !matches!(fetch.status, FetchStatus::Failed(_)))
+            .count();
+        return Err(quality::nothing_passed(fetched));
+    }
+    Ok(contents)
+}
+
+/// Builds a pipeline outcome for tests, with one `https://example.com/<i>` page per fetch status.
+///
+/// # Arguments
+///
+/// * `statuses` - The status of each fetched page.
+/// * `summary` - The result of the LLM stage, if it ran.
+///
+/// # Returns
+///
+/// The `PipelineOutcome`, whose search found exactly the fetched pages.
+#[cfg(test)]
+pub(crate) fn outcome_fixture(statuses: Vec<FetchStatus>, summary: Option<Result<String>>) -> PipelineOutcome {
+    let fetches = statuses
+        .into_iter()
+        .enumerate()
+        .map(|(i, status)| FetchOutcome {
+            url: format!("https://example.com/{}", i),
+            status,
+        })
+        .collect::<Vec<_>>();
+
+    PipelineOutcome {
+        search: Ok(fetches.iter().map(|fetch| fetch.url.clone()).collect()),
+        broadened_query: None,
+        fetches,
+        summary,
+        prompt_tokens: None,
+        prompt: None,
+        elapsed: Duration::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a run where every stage succeeded reports success.
+    #[test]
+    fn test_outcome_complete() {
+        let outcome = outcome_fixture(
+            vec![FetchStatus::Used, FetchStatus::Filtered],
+            Some(Ok("summary".to_string())),
+        );
+
+        assert_eq!(outcome.exit_code(), EXIT_SUCCESS);
+        assert_eq!(outcome.pages_analyzed(), 1);
+        assert_eq!(outcome.urls().len(), 2);
+        assert!(outcome.error().is_none());
+    }
+
+    /// Tests that failed fetches alongside a summary are reported as a partial success.
+    #[test]
+    fn test_outcome_partial() {
+        let outcome = outcome_fixture(
+            vec![
+                FetchStatus::Used,
+                FetchStatus::Failed(ScraperError::ExtractionError("blocked".to_string())),
+            ],
+            Some(Ok("summary".to_string())),
+        );
+
+        assert_eq!(outcome.exit_code(), EXIT_PARTIAL);
+        assert_eq!(outcome.summary_text(), Some("summary"));
+        let failed: Vec<_> = outcome.failed_fetches().map(|(url, _)| url).collect();
+        assert_eq!(failed, vec!["https://example.com/1"]);
+    }
+
+    /// Tests that runs without a summary are reported as failures with their error.
+    #[test]
+    fn test_outcome_failure() {
+        let llm_failed = outcome_fixture(
+            vec![FetchStatus::Used],
+            Some(Err(ScraperError::LLMError("model not found".to_string()))),
+        );
+        assert_eq!(llm_failed.exit_code(), EXIT_FAILURE);
+        assert!(matches!(llm_failed.error(), Some(ScraperError::LLMError(_))));
+
+        let mut search_failed = outcome_fixture(Vec::new(), None);
+        search_failed.search = Err(ScraperError::SearchError("rate limited".to_string()));
+        assert_eq!(search_failed.exit_code(), EXIT_FAILURE);
+        assert!(search_failed.urls().is_empty());
+        assert!(matches!(search_failed.error(), Some(ScraperError::SearchError(_))));
+    }
+}
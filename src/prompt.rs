@@ -1,4 +1,37 @@
-use crate::types::ScrapedContent;
+use crate::{
+    diff::sentences,
+    types::{FaqEntry, PageSummary, ScrapedContent},
+};
+use std::collections::BTreeMap;
+
+/// The phrase a grounded answer must use when the sources don't contain the answer.
+pub const NOT_FOUND_PHRASE: &str = "Not found in sources.";
+
+/// The instruction prepended to grounded prompts.
+pub const GROUNDING_INSTRUCTION: &str = "Answer strictly from the sources below and nothing else. \
+    Cite the source URL for every claim. If the sources don't contain the answer, reply exactly: ";
+
+/// The instruction asking the model to point out disagreements between sources and hedge.
+pub const UNCERTAINTY_GUIDANCE: &str = "If the sources disagree or the evidence is thin, say so instead of \
+    confidently picking one answer: state which source makes which claim and hedge your conclusion accordingly.";
+
+/// The addition to `UNCERTAINTY_GUIDANCE` for prompts with numbered sources.
+const UNCERTAINTY_CITATION_EXAMPLE: &str =
+    "Refer to the sources by their tags, e.g. \"[SOURCE 1] says X, but [SOURCE 3] says Y.\"";
+
+/// The instruction asking the model to cite sources by their bracketed numbers, followed by an
+/// example citation.
+pub const CITATION_INSTRUCTION: &str = "Cite the sources of every claim by their bracketed numbers from the \
+    source list at the end, e.g. ";
+
+/// The minimum number of words for a sentence to count as a claim in `uncited_claims`.
+const MIN_CLAIM_WORDS: usize = 4;
+
+/// The maximum number of characters of page content included in a classification prompt.
+const CLASSIFY_CONTENT_CHARS: usize = 2000;
+
+/// The marker appended to source content cut short by `PromptBuilder::with_max_tokens`.
+const TRUNCATED_MARKER: &str = " [truncated]";
 
 /// The `PromptBuilder` struct is responsible for constructing prompts from scraped content.
 /// It allows adding content and building a formatted prompt string.
@@ -7,6 +40,18 @@ pub struct PromptBuilder {
     query: String,
     /// The list of scraped content to be included in the prompt.
     contents: Vec<ScrapedContent>,
+    /// Whether the model is instructed to answer strictly from the sources.
+    grounded: bool,
+    /// Whether each source's breadcrumb trail is included.
+    breadcrumbs: bool,
+    /// Whether each source block is prefixed with a `[SOURCE n]` tag.
+    numbered_sources: bool,
+    /// Whether the model is asked to note disagreements between sources and hedge.
+    uncertainty_guidance: bool,
+    /// Whether the model is asked to cite sources by their bracketed numbers.
+    citation_instructions: bool,
+    /// The estimated token count the prompt is kept within by trimming source content.
+    max_tokens: Option<usize>,
 }
 
 impl PromptBuilder {
@@ -23,9 +68,124 @@ impl PromptBuilder {
         Self {
             query,
             contents: Vec::new(),
+            grounded: false,
+            breadcrumbs: false,
+            numbered_sources: false,
+            uncertainty_guidance: false,
+            citation_instructions: false,
+            max_tokens: None,
         }
     }
 
+    /// Sets whether the prompt instructs the model to answer strictly from the sources, citing
+    /// them, and to reply with `NOT_FOUND_PHRASE` when the answer isn't in them.
+    ///
+    /// # Arguments
+    ///
+    /// * `grounded` - Whether to add the grounding instruction.
+    ///
+    /// # Returns
+    ///
+    /// The updated `PromptBuilder` instance.
+    pub fn grounded(mut self, grounded: bool) -> Self {
+        self.grounded = grounded;
+        self
+    }
+
+    /// Sets whether each source's breadcrumb trail (its `breadcrumb` metadata) is included, to show
+    /// where the page sits in its site.
+    ///
+    /// # Arguments
+    ///
+    /// * `breadcrumbs` - Whether to include breadcrumb trails.
+    ///
+    /// # Returns
+    ///
+    /// The updated `PromptBuilder` instance.
+    pub fn breadcrumbs(mut self, breadcrumbs: bool) -> Self {
+        self.breadcrumbs = breadcrumbs;
+        self
+    }
+
+    /// Sets whether each source block is prefixed with a `[SOURCE n]` tag the model can reference.
+    /// Sources are numbered from 1 in the order they were added; see `source_map`.
+    ///
+    /// # Arguments
+    ///
+    /// * `numbered` - Whether to number the sources.
+    ///
+    /// # Returns
+    ///
+    /// The updated `PromptBuilder` instance.
+    pub fn with_numbered_sources(mut self, numbered: bool) -> Self {
+        self.numbered_sources = numbered;
+        self
+    }
+
+    /// Sets whether the prompt asks the model to point out where sources disagree and to hedge
+    /// instead of confidently picking one answer. With numbered sources, the model is also asked
+    /// to attribute conflicting claims by their `[SOURCE n]` tags.
+    ///
+    /// # Arguments
+    ///
+    /// * `guidance` - Whether to add the uncertainty guidance.
+    ///
+    /// # Returns
+    ///
+    /// The updated `PromptBuilder` instance.
+    pub fn with_uncertainty_guidance(mut self, guidance: bool) -> Self {
+        self.uncertainty_guidance = guidance;
+        self
+    }
+
+    /// Sets whether the prompt asks the model to cite the sources of its claims by their bracketed
+    /// numbers (`[1]`, or `[SOURCE 1]` with numbered sources), which the source list at the end of
+    /// the prompt maps to URLs. Citations can be checked with `uncited_claims`.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Whether to add the citation instruction.
+    ///
+    /// # Returns
+    ///
+    /// The updated `PromptBuilder` instance.
+    pub fn with_citation_instructions(mut self, instructions: bool) -> Self {
+        self.citation_instructions = instructions;
+        self
+    }
+
+    /// Sets the estimated token count (see `llm::estimate_tokens`) the built prompt must stay
+    /// within. Source content is trimmed to fit, sharing the room left by the query, instructions
+    /// and source headers evenly: short sources are kept whole and the rest is split between the
+    /// longer ones, so every source contributes something. Trimmed content ends with `[truncated]`.
+    /// The prompt can't be trimmed below its size without any content.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_tokens` - The maximum estimated token count of the prompt.
+    ///
+    /// # Returns
+    ///
+    /// The updated `PromptBuilder` instance.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Returns the mapping from each source number used in `[n]` labels and `[SOURCE n]` tags to
+    /// the source URL.
+    ///
+    /// # Returns
+    ///
+    /// A map from source number (starting at 1, in content order) to URL.
+    pub fn source_map(&self) -> BTreeMap<usize, String> {
+        self.contents
+            .iter()
+            .enumerate()
+            .map(|(index, content)| (index + 1, content.url.clone()))
+            .collect()
+    }
+
     /// Adds the given contents to the `PromptBuilder`.
     ///
     /// # Arguments
@@ -40,28 +200,120 @@ impl PromptBuilder {
         self
     }
 
-    /// Builds the prompt string by formatting the query and contents.
+    /// Builds the prompt string by formatting the query and contents, trimming the contents to
+    /// the `with_max_tokens` budget if one is set. Each source is labelled with its number, e.g.
+    /// `[1]`, and a `Sources:` list mapping the numbers to titles and URLs ends the prompt.
     ///
     /// # Returns
     ///
     /// A formatted prompt string.
     pub fn build(&self) -> String {
+        let mut texts: Vec<String> = self.contents.iter().map(|c| Self::clean_text(&c.content)).collect();
+
+        if let Some(max_tokens) = self.max_tokens {
+            // Every non-empty content also adds the line break that ends it
+            let overhead = self.assemble(&vec![String::new(); texts.len()]).chars().count() + texts.len();
+            let budget = (max_tokens * 4).saturating_sub(overhead);
+            let lengths: Vec<usize> = texts.iter().map(|text| text.chars().count()).collect();
+            for (text, share) in texts.iter_mut().zip(fair_shares(&lengths, budget)) {
+                truncate_to(text, share);
+            }
+        }
+
+        self.assemble(&texts)
+    }
+
+    /// Formats the query, instructions and sources into the prompt, with the given text standing
+    /// in for each source's content.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - The content of each source, in source order.
+    ///
+    /// # Returns
+    ///
+    /// A formatted prompt string.
+    fn assemble(&self, texts: &[String]) -> String {
         let formatted_contents = self.contents
             .iter()
-            .map(|c| {
+            .zip(texts)
+            .enumerate()
+            .map(|(index, (c, text))| {
+                let tag = if self.numbered_sources {
+                    format!("{}\n", self.source_label(index + 1))
+                } else {
+                    format!("{} ", self.source_label(index + 1))
+                };
+                let breadcrumb = match c.metadata.get("breadcrumb") {
+                    Some(trail) if self.breadcrumbs => format!("Breadcrumb: {}\n", trail),
+                    _ => String::new(),
+                };
                 Self::clean_text(
                     &format!(
-                        "Source: {}\nTimestamp: {}\nContent:\n{}\n---\n",
-                        c.url, c.timestamp, c.content
+                        "{}Source: {}\n{}Timestamp: {}\nContent:\n{}\n{}---\n",
+                        tag, c.url, breadcrumb, c.timestamp, text, format_faq(&c.faq)
                     )
                 )
             })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let source_list = self.contents
+            .iter()
+            .enumerate()
+            .map(|(index, c)| {
+                let label = self.source_label(index + 1);
+                match c.metadata.get("title") {
+                    Some(title) => format!("{} {} - {}\n", label, title.split_whitespace().collect::<Vec<_>>().join(" "), c.url),
+                    None => format!("{} {}\n", label, c.url),
+                }
+            })
             .collect::<String>();
+        let source_list = if source_list.is_empty() {
+            String::new()
+        } else {
+            format!("\nSources:\n{}", Self::clean_text(&source_list))
+        };
+
+        let mut instructions = String::new();
+        if self.grounded {
+            instructions.push_str(&format!("{}\"{}\"\n", GROUNDING_INSTRUCTION, NOT_FOUND_PHRASE));
+        }
+        if self.citation_instructions {
+            instructions.push_str(&format!(
+                "{}\"Water boils at 100 degrees Celsius at sea level {}.\"\n",
+                CITATION_INSTRUCTION,
+                self.source_label(1)
+            ));
+        }
+        if self.uncertainty_guidance {
+            instructions.push_str(UNCERTAINTY_GUIDANCE);
+            if self.numbered_sources {
+                instructions.push(' ');
+                instructions.push_str(UNCERTAINTY_CITATION_EXAMPLE);
+            }
+            instructions.push('\n');
+        }
 
-        format!(
-            "{} {}", self.query,
-            formatted_contents
-        )
+        format!("{}{} {}{}", instructions, self.query, formatted_contents, source_list)
+    }
+
+    /// Returns the label a source is referenced by: `[SOURCE n]` with numbered sources and `[n]`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The source number, starting at 1.
+    ///
+    /// # Returns
+    ///
+    /// The bracketed label.
+    fn source_label(&self, number: usize) -> String {
+        if self.numbered_sources {
+            format!("[SOURCE {}]", number)
+        } else {
+            format!("[{}]", number)
+        }
     }
 
     /// Cleans the given text by removing blank lines and normalizing whitespace.
@@ -86,6 +338,251 @@ impl PromptBuilder {
     }
 }
 
+/// Splits a character budget evenly between texts, as if handing out characters round-robin:
+/// texts shorter than their share keep their full length, and what they leave is split between
+/// the longer ones.
+///
+/// # Arguments
+///
+/// * `lengths` - The length of each text, in characters.
+/// * `budget` - The total number of characters to share out.
+///
+/// # Returns
+///
+/// The number of characters each text may keep, in the order of `lengths`.
+fn fair_shares(lengths: &[usize], budget: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by_key(|&index| lengths[index]);
+
+    let mut shares = vec![0; lengths.len()];
+    let mut remaining = budget;
+    for (position, index) in order.into_iter().enumerate() {
+        let share = (remaining / (lengths.len() - position)).min(lengths[index]);
+        shares[index] = share;
+        remaining -= share;
+    }
+    shares
+}
+
+/// Cuts a text down to a number of characters, ending it with `TRUNCATED_MARKER` if anything was
+/// cut. A text with no room for more than the marker is emptied.
+///
+/// # Arguments
+///
+/// * `text` - The text to cut.
+/// * `max_chars` - The maximum number of characters to keep, including the marker.
+fn truncate_to(text: &mut String, max_chars: usize) {
+    if text.chars().count() <= max_chars {
+        return;
+    }
+
+    let marker_chars = TRUNCATED_MARKER.chars().count();
+    if max_chars <= marker_chars {
+        text.clear();
+        return;
+    }
+
+    let end = text
+        .char_indices()
+        .nth(max_chars - marker_chars)
+        .map_or(text.len(), |(end, _)| end);
+    text.truncate(end);
+    text.truncate(text.trim_end().len());
+    text.push_str(TRUNCATED_MARKER);
+}
+
+/// Resolves the `[SOURCE n]` tags in a model's answer to the URLs they refer to.
+///
+/// Each known tag becomes `[SOURCE n: url]`; tags with numbers missing from the map are left as-is.
+///
+/// # Arguments
+///
+/// * `answer` - The model's answer.
+/// * `source_map` - The source numbers and URLs, as returned by `PromptBuilder::source_map`.
+///
+/// # Returns
+///
+/// The answer with its source tags resolved.
+pub fn resolve_source_tags(answer: &str, source_map: &BTreeMap<usize, String>) -> String {
+    let mut resolved = answer.to_string();
+    for (index, url) in source_map {
+        resolved = resolved.replace(
+            &format!("[SOURCE {}]", index),
+            &format!("[SOURCE {}: {}]", index, url),
+        );
+    }
+    resolved
+}
+
+/// Finds the sentences of an answer that make a claim without citing a source.
+///
+/// A sentence counts as cited when it contains a URL or a numbered reference such as `[2]` or
+/// `[SOURCE 2]`.
+/// Very short sentences and the `NOT_FOUND_PHRASE` are not treated as claims.
+///
+/// # Arguments
+///
+/// * `answer` - The model's answer.
+///
+/// # Returns
+///
+/// The uncited sentences, in order. An empty vector means every claim is cited.
+pub fn uncited_claims(answer: &str) -> Vec<&str> {
+    sentences(answer)
+        .into_iter()
+        .filter(|sentence| *sentence != NOT_FOUND_PHRASE)
+        .filter(|sentence| sentence.split_whitespace().count() >= MIN_CLAIM_WORDS)
+        .filter(|sentence| !has_citation(sentence))
+        .collect()
+}
+
+/// Checks whether a sentence contains a URL or a numbered reference such as `[2]` or `[SOURCE 2]`.
+///
+/// # Arguments
+///
+/// * `sentence` - The sentence to check.
+///
+/// # Returns
+///
+/// `true` if the sentence cites a source, `false` otherwise.
+fn has_citation(sentence: &str) -> bool {
+    if sentence.contains("http://") || sentence.contains("https://") {
+        return true;
+    }
+
+    sentence.split('[').skip(1).any(|rest| {
+        rest.split_once(']').is_some_and(|(reference, _)| {
+            let reference = reference.strip_prefix("SOURCE ").unwrap_or(reference);
+            !reference.is_empty() && reference.chars().all(|c| c.is_ascii_digit())
+        })
+    })
+}
+
+/// Formats FAQ entries as a `Q: ... A: ...` block for inclusion in a prompt.
+///
+/// # Arguments
+///
+/// * `faq` - The FAQ entries to format.
+///
+/// # Returns
+///
+/// The formatted block, or an empty string if there are no entries.
+fn format_faq(faq: &[FaqEntry]) -> String {
+    if faq.is_empty() {
+        return String::new();
+    }
+
+    let entries = faq
+        .iter()
+        .map(|entry| format!("Q: {}\nA: {}\n", entry.question, entry.answer))
+        .collect::<String>();
+    format!("FAQ:\n{}", entries)
+}
+
+/// Builds the prompt asking the model to pick the category that best fits a page.
+///
+/// Only the start of the page content is included, which is enough to tell its topic.
+///
+/// # Arguments
+///
+/// * `content` - The scraped page to classify.
+/// * `categories` - The categories to choose from.
+///
+/// # Returns
+///
+/// A formatted prompt string.
+pub fn build_classify_prompt(content: &ScrapedContent, categories: &[String]) -> String {
+    let excerpt: String = content.content.chars().take(CLASSIFY_CONTENT_CHARS).collect();
+    PromptBuilder::clean_text(&format!(
+        "Classify the following content into exactly one of these categories: {}\n\
+        Reply with the category name only.\n\
+        Source: {}\nContent:\n{}",
+        categories.join(", "), content.url, excerpt
+    ))
+}
+
+/// Builds the prompt used to summarize a single page in the map step of map-reduce summarization.
+///
+/// # Arguments
+///
+/// * `query` - The query or question the summary should focus on.
+/// * `content` - The scraped page to summarize.
+///
+/// # Returns
+///
+/// A formatted prompt string.
+pub fn build_map_prompt(query: &str, content: &ScrapedContent) -> String {
+    PromptBuilder::clean_text(&format!(
+        "Summarize the following content, keeping only information relevant to: {}\n\
+        Source: {}\nContent:\n{}\n{}",
+        query, content.url, content.content, format_faq(&content.faq)
+    ))
+}
+
+/// Builds the prompt asking the model to summarize the changes to a monitored page.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the page.
+/// * `diff` - The sentence-level diff, as returned by `SearchEngine::fetch_and_diff`.
+///
+/// # Returns
+///
+/// A formatted prompt string.
+pub fn build_diff_prompt(url: &str, diff: &str) -> String {
+    format!(
+        "Summarize what changed on {} since the last check. Lines starting with '-' were removed, \
+        lines starting with '+' were added and other lines are unchanged context.\n{}",
+        url, diff
+    )
+}
+
+/// Builds the prompt that merges a group of summaries into one summary when the reduce step is
+/// split into several levels.
+///
+/// # Arguments
+///
+/// * `query` - The query or question the summary should focus on.
+/// * `summaries` - The summaries to merge.
+///
+/// # Returns
+///
+/// A formatted prompt string.
+pub fn build_group_reduce_prompt(query: &str, summaries: &[PageSummary]) -> String {
+    build_reduce_prompt(
+        &format!(
+            "Combine the following summaries into one, keeping the source URLs and only information relevant to: {}\n",
+            query
+        ),
+        summaries,
+    )
+}
+
+/// Builds the prompt that combines per-page summaries into a final answer in the reduce step.
+///
+/// # Arguments
+///
+/// * `query` - The query or question to be answered.
+/// * `summaries` - The per-page summaries produced by the map step.
+///
+/// # Returns
+///
+/// A formatted prompt string.
+pub fn build_reduce_prompt(query: &str, summaries: &[PageSummary]) -> String {
+    let formatted_summaries = summaries
+        .iter()
+        .map(|s| {
+            PromptBuilder::clean_text(&format!(
+                "Source: {}\nSummary:\n{}\n---\n",
+                s.url, s.summary
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("{} {}", query, formatted_summaries)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,6 +597,10 @@ mod tests {
             content: "Test content".to_string(),
             metadata: HashMap::new(),
             timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
         };
 
         let prompt = PromptBuilder::new("What is Rust?".to_string())
@@ -110,4 +611,298 @@ mod tests {
         assert!(prompt.contains("https://example.com"));
         assert!(prompt.contains("Test content"));
     }
-}
\ No newline at end of file
+
+    /// Tests the map and reduce prompt builders.
+    #[test]
+    fn test_map_reduce_prompts() {
+        let content = ScrapedContent {
+            url: "https://example.com".to_string(),
+            content: "Rust is   a language".to_string(),
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+
+        let map_prompt = build_map_prompt("What is Rust?", &content);
+        assert!(map_prompt.contains("What is Rust?"));
+        assert!(map_prompt.contains("Rust is a language"));
+
+        let reduce_prompt = build_reduce_prompt(
+            "What is Rust?",
+            &[
+                PageSummary {
+                    url: "https://a.example".to_string(),
+                    summary: "Summary A".to_string(),
+                },
+                PageSummary {
+                    url: "https://b.example".to_string(),
+                    summary: "Summary B".to_string(),
+                },
+            ],
+        );
+        assert!(reduce_prompt.starts_with("What is Rust?"));
+        assert!(reduce_prompt.contains("https://a.example"));
+        assert!(reduce_prompt.contains("Summary B"));
+    }
+
+    /// Tests that FAQ entries are presented as question and answer pairs.
+    #[test]
+    fn test_prompt_includes_faq() {
+        let content = ScrapedContent {
+            url: "https://example.com".to_string(),
+            content: "Shipping information".to_string(),
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: vec![FaqEntry {
+                question: "How long does shipping take?".to_string(),
+                answer: "3-5 business days.".to_string(),
+            }],
+        };
+
+        let prompt = PromptBuilder::new("Shipping time?".to_string())
+            .with_contents(vec![content])
+            .build();
+
+        assert!(prompt.contains("FAQ:\nQ: How long does shipping take?\nA: 3-5 business days."));
+    }
+
+    /// Tests that grounded prompts carry the grounding instruction and fallback phrase.
+    #[test]
+    fn test_grounded_prompt() {
+        let content = ScrapedContent {
+            url: "https://example.com".to_string(),
+            content: "Test content".to_string(),
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+
+        let plain = PromptBuilder::new("What is Rust?".to_string())
+            .with_contents(vec![content.clone()])
+            .build();
+        assert!(!plain.contains(GROUNDING_INSTRUCTION));
+
+        let grounded = PromptBuilder::new("What is Rust?".to_string())
+            .with_contents(vec![content])
+            .grounded(true)
+            .build();
+        assert!(grounded.starts_with(GROUNDING_INSTRUCTION));
+        assert!(grounded.contains(NOT_FOUND_PHRASE));
+        assert!(grounded.contains("What is Rust?"));
+    }
+
+    /// Tests flagging of answer sentences that make claims without a citation.
+    #[test]
+    fn test_uncited_claims() {
+        let answer = "Rust is a systems programming language [1]. \
+            It was first released in 2015 (https://www.rust-lang.org). \
+            It guarantees memory safety without a garbage collector. \
+            See above.";
+        assert_eq!(
+            uncited_claims(answer),
+            vec!["It guarantees memory safety without a garbage collector."]
+        );
+
+        assert!(uncited_claims(NOT_FOUND_PHRASE).is_empty());
+        assert_eq!(uncited_claims("Cargo is the package manager [x].").len(), 1);
+    }
+
+    /// Tests that breadcrumb trails are only included when enabled.
+    #[test]
+    fn test_prompt_breadcrumbs() {
+        let content = ScrapedContent {
+            url: "https://example.com/docs/api/auth".to_string(),
+            content: "Use a bearer token.".to_string(),
+            metadata: HashMap::from([("breadcrumb".to_string(), "Docs > API > Authentication".to_string())]),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+
+        let plain = PromptBuilder::new("How do I authenticate?".to_string())
+            .with_contents(vec![content.clone()])
+            .build();
+        assert!(!plain.contains("Breadcrumb:"));
+
+        let with_trail = PromptBuilder::new("How do I authenticate?".to_string())
+            .with_contents(vec![content])
+            .breadcrumbs(true)
+            .build();
+        assert!(with_trail.contains("Breadcrumb: Docs > API > Authentication"));
+    }
+
+    /// Tests that source numbers follow the content order and resolve back to their URLs.
+    #[test]
+    fn test_numbered_sources() {
+        let page = |url: &str| ScrapedContent {
+            url: url.to_string(),
+            content: format!("Content of {}", url),
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+        let urls = ["https://a.example", "https://b.example", "https://c.example"];
+
+        let builder = PromptBuilder::new("Question?".to_string())
+            .with_contents(urls.iter().map(|url| page(url)).collect())
+            .with_numbered_sources(true);
+        let prompt = builder.build();
+
+        let positions: Vec<usize> = (1..=3)
+            .map(|n| prompt.find(&format!("[SOURCE {}]\nSource: {}", n, urls[n - 1])).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let source_map = builder.source_map();
+        assert_eq!(source_map.values().collect::<Vec<_>>(), urls.iter().collect::<Vec<_>>());
+        assert_eq!(
+            resolve_source_tags("A says so [SOURCE 1], C disagrees [SOURCE 3] [SOURCE 9].", &source_map),
+            "A says so [SOURCE 1: https://a.example], C disagrees [SOURCE 3: https://c.example] [SOURCE 9]."
+        );
+        assert!(uncited_claims("Rust is memory safe by default [SOURCE 2].").is_empty());
+
+        let plain = PromptBuilder::new("Question?".to_string())
+            .with_contents(vec![page(urls[0])])
+            .build();
+        assert!(!plain.contains("[SOURCE"));
+    }
+
+    /// Tests that the uncertainty guidance is only added when enabled, with a citation example
+    /// when sources are numbered.
+    #[test]
+    fn test_uncertainty_guidance() {
+        let content = ScrapedContent {
+            url: "https://example.com".to_string(),
+            content: "Test content".to_string(),
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+        let builder = || PromptBuilder::new("Is coffee healthy?".to_string()).with_contents(vec![content.clone()]);
+
+        assert!(!builder().build().contains(UNCERTAINTY_GUIDANCE));
+
+        let prompt = builder().with_uncertainty_guidance(true).build();
+        assert!(prompt.starts_with(UNCERTAINTY_GUIDANCE));
+        assert!(prompt.contains("Is coffee healthy?"));
+        assert!(!prompt.contains("[SOURCE 1] says"));
+
+        let prompt = builder()
+            .with_uncertainty_guidance(true)
+            .with_numbered_sources(true)
+            .grounded(true)
+            .build();
+        assert!(prompt.starts_with(GROUNDING_INSTRUCTION));
+        assert!(prompt.contains(UNCERTAINTY_GUIDANCE));
+        assert!(prompt.contains("[SOURCE 1] says X, but [SOURCE 3] says Y."));
+    }
+
+    /// Tests that `with_max_tokens` keeps the prompt within its budget while every source keeps
+    /// some content.
+    #[test]
+    fn test_max_tokens() {
+        let content = |url: &str, text: String| ScrapedContent {
+            url: url.to_string(),
+            content: text,
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+        let contents = vec![
+            content("https://one.example", "Rust compiles to native code. ".repeat(200)),
+            content("https://two.example", "Short but whole.".to_string()),
+            content("https://three.example", "Cargo builds all your crates. ".repeat(200)),
+        ];
+        let builder = || PromptBuilder::new("What is Rust?".to_string()).with_contents(contents.clone());
+
+        let prompt = builder().build();
+        assert!(!prompt.contains("[truncated]"));
+
+        let prompt = builder().with_max_tokens(300).build();
+        assert!(prompt.chars().count() <= 1200);
+        assert!(crate::llm::estimate_tokens(&prompt) <= 300);
+        assert!(prompt.contains("Short but whole.\n"));
+        assert_eq!(prompt.matches("[truncated]").count(), 2);
+        assert!(prompt.contains("Rust compiles to native code."));
+        assert!(prompt.contains("Cargo builds all your crates."));
+
+        // Both long sources get an equal share of the room left
+        let kept = |text: &str| prompt.matches(text).count();
+        assert!(kept("Rust compiles").abs_diff(kept("Cargo builds")) <= 1);
+
+        let prompt = builder().with_max_tokens(1_000_000).build();
+        assert!(!prompt.contains("[truncated]"));
+    }
+
+    /// Tests the source labels, the source list and the citation instruction.
+    #[test]
+    fn test_citations() {
+        let page = |url: &str, title: Option<&str>| ScrapedContent {
+            url: url.to_string(),
+            content: format!("Content of {}", url),
+            metadata: title
+                .map(|title| HashMap::from([("title".to_string(), title.to_string())]))
+                .unwrap_or_default(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        };
+        let builder = || {
+            PromptBuilder::new("Question?".to_string()).with_contents(vec![
+                page("https://a.example", Some("The  Rust\nBook")),
+                page("https://b.example", None),
+            ])
+        };
+
+        let prompt = builder().build();
+        assert!(!prompt.contains(CITATION_INSTRUCTION));
+        assert!(prompt.contains("[1] Source: https://a.example\n"));
+        assert!(prompt.contains("---\n[2] Source: https://b.example\n"));
+        assert!(prompt.ends_with("\nSources:\n[1] The Rust Book - https://a.example\n[2] https://b.example"));
+
+        let prompt = builder().with_citation_instructions(true).build();
+        assert!(prompt.starts_with(CITATION_INSTRUCTION));
+        assert!(prompt.contains("at sea level [1].\"\nQuestion?"));
+
+        let prompt = builder().with_citation_instructions(true).with_numbered_sources(true).build();
+        assert!(prompt.contains("at sea level [SOURCE 1].\""));
+        assert!(prompt.contains("[SOURCE 2]\nSource: https://b.example\n"));
+        assert!(prompt.ends_with("\nSources:\n[SOURCE 1] The Rust Book - https://a.example\n[SOURCE 2] https://b.example"));
+
+        let empty = PromptBuilder::new("Question?".to_string()).build();
+        assert!(!empty.contains("Sources:"));
+        assert!(uncited_claims("Water boils at 100 degrees Celsius [1].").is_empty());
+    }
+
+    /// Tests sharing a character budget evenly between texts.
+    #[test]
+    fn test_fair_shares() {
+        assert_eq!(fair_shares(&[100, 10, 100], 90), vec![40, 10, 40]);
+        assert_eq!(fair_shares(&[100, 10, 100], 1000), vec![100, 10, 100]);
+        assert_eq!(fair_shares(&[30, 30, 30], 10), vec![3, 3, 4]);
+        assert_eq!(fair_shares(&[5, 5], 0), vec![0, 0]);
+        assert!(fair_shares(&[], 10).is_empty());
+    }
+}
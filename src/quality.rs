@@ -0,0 +1,356 @@
+use crate::{
+    config::{QualityFilter, RelevanceMode},
+    Result, ScrapedContent, ScraperError,
+};
+use tracing::debug;
+
+/// Phrases that indicate the extracted content is a paywall prompt rather than the article.
+const PAYWALL_MARKERS: &[&str] = &[
+    "subscribe to continue reading",
+    "subscribe to read",
+    "subscribe to unlock",
+    "this content is for subscribers",
+    "already a subscriber",
+    "sign in to continue reading",
+    "create a free account to continue",
+    "you have reached your limit of free articles",
+];
+
+/// Phrases that indicate the page is a bot check, CAPTCHA or access-denied page.
+const BLOCKER_MARKERS: &[&str] = &[
+    "verify you are human",
+    "are you a robot",
+    "unusual traffic from your computer",
+    "checking your browser before accessing",
+    "enable javascript and cookies to continue",
+    "please complete the security check",
+    "access denied",
+    "captcha",
+];
+
+/// Query words shorter than this many characters (e.g. "a", "of") are ignored by the relevance check.
+const MIN_TERM_LENGTH: usize = 3;
+
+/// Pages longer than this many words are never treated as blocker pages, since real articles
+/// may legitimately mention CAPTCHAs or access control.
+const BLOCKER_MAX_WORDS: usize = 300;
+
+impl QualityFilter {
+    /// Checks whether a single piece of scraped content meets all configured thresholds.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The scraped content to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the content passes the filter, `false` otherwise.
+    pub fn passes(&self, content: &ScrapedContent) -> bool {
+        if content.content.chars().count() < self.min_length {
+            debug!("Dropping {}: content shorter than {} chars", content.url, self.min_length);
+            return false;
+        }
+
+        let score = confidence(content);
+        if score < self.min_confidence {
+            debug!("Dropping {}: confidence {:.2} below {:.2}", content.url, score, self.min_confidence);
+            return false;
+        }
+
+        if self.reject_paywalled && is_paywalled(content) {
+            debug!("Dropping {}: page appears to be paywalled", content.url);
+            return false;
+        }
+
+        if self.reject_blocker_pages && is_blocker_page(content) {
+            debug!("Dropping {}: page appears to be a blocker page", content.url);
+            return false;
+        }
+
+        true
+    }
+
+    /// Checks whether a page matches the search query under the configured relevance mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The scraped content to check.
+    /// * `query` - The search query the page was found with.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the page is relevant or no relevance mode is configured, `false` otherwise.
+    pub fn is_relevant(&self, content: &ScrapedContent, query: &str) -> bool {
+        match self.relevance {
+            Some(mode) if !matches_query(content, query, mode) => {
+                debug!("Dropping {}: query terms not found ({:?})", content.url, mode);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Filters scraped content, keeping only the pages that pass every configured threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - The scraped contents to filter.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the pages that passed, or an error if none of them did.
+    pub fn apply(&self, contents: Vec<ScrapedContent>) -> Result<Vec<ScrapedContent>> {
+        let total = contents.len();
+        let passed: Vec<ScrapedContent> = contents
+            .into_iter()
+            .filter(|content| self.passes(content))
+            .collect();
+
+        if passed.is_empty() {
+            return Err(nothing_passed(total));
+        }
+
+        debug!("{} of {} pages passed the quality filter", passed.len(), total);
+        Ok(passed)
+    }
+}
+
+/// Builds the error returned when none of the fetched pages passed the quality filter.
+///
+/// # Arguments
+///
+/// * `total` - The number of pages that were fetched.
+///
+/// # Returns
+///
+/// An `ExtractionError` reporting how many pages were rejected.
+pub(crate) fn nothing_passed(total: usize) -> ScraperError {
+    ScraperError::ExtractionError(format!(
+        "None of the {} fetched pages passed the quality filter",
+        total
+    ))
+}
+
+/// Estimates how confident we are that the content is genuine page text rather than boilerplate.
+///
+/// The score combines the amount of text, the share of alphabetic characters and whether the
+/// text reads like prose (reasonable sentence lengths).
+///
+/// # Arguments
+///
+/// * `content` - The scraped content to score.
+///
+/// # Returns
+///
+/// A score between 0.0 and 1.0.
+pub fn confidence(content: &ScrapedContent) -> f32 {
+    let text = &content.content;
+    let words = text.split_whitespace().count();
+    if words == 0 {
+        return 0.0;
+    }
+
+    let length_score = (words as f32 / 150.0).min(1.0);
+
+    let visible = text.chars().filter(|c| !c.is_whitespace()).count().max(1);
+    let alphabetic = text.chars().filter(|c| c.is_alphabetic()).count();
+    let alpha_score = alphabetic as f32 / visible as f32;
+
+    let sentences = text.matches(['.', '!', '?']).count().max(1);
+    let words_per_sentence = words as f32 / sentences as f32;
+    let prose_score = if (5.0..=40.0).contains(&words_per_sentence) {
+        1.0
+    } else {
+        0.5
+    };
+
+    (0.5 * length_score + 0.3 * alpha_score + 0.2 * prose_score).clamp(0.0, 1.0)
+}
+
+/// Checks whether the content looks like a paywall prompt.
+///
+/// # Arguments
+///
+/// * `content` - The scraped content to check.
+///
+/// # Returns
+///
+/// `true` if a paywall marker is present, `false` otherwise.
+pub fn is_paywalled(content: &ScrapedContent) -> bool {
+    let text = content.content.to_lowercase();
+    PAYWALL_MARKERS.iter().any(|marker| text.contains(marker))
+}
+
+/// Checks whether every term of a search query appears where the relevance mode requires.
+///
+/// Terms are matched case-insensitively as substrings. Quotes, excluded terms (`-word`),
+/// operators such as `site:` and words shorter than three characters are ignored, so a query
+/// with no remaining terms matches every page.
+///
+/// # Arguments
+///
+/// * `content` - The scraped content to check.
+/// * `query` - The search query.
+/// * `mode` - Where the terms must appear.
+///
+/// # Returns
+///
+/// `true` if the page contains every term, `false` otherwise.
+pub fn matches_query(content: &ScrapedContent, query: &str, mode: RelevanceMode) -> bool {
+    let title = content.metadata.get("title").map(|t| t.to_lowercase()).unwrap_or_default();
+    let body = content.content.to_lowercase();
+
+    let query = query.to_lowercase().replace(['"', '(', ')'], " ");
+    let terms: Vec<&str> = query
+        .split_whitespace()
+        .filter(|term| !term.starts_with('-') && !term.contains(':'))
+        .filter(|term| term.chars().count() >= MIN_TERM_LENGTH)
+        .collect();
+
+    let contains_all = |text: &str| terms.iter().all(|term| text.contains(term));
+    match mode {
+        RelevanceMode::TitleMatch => contains_all(&title),
+        RelevanceMode::BodyMatch => contains_all(&body),
+        RelevanceMode::Either => contains_all(&title) || contains_all(&body),
+    }
+}
+
+/// Checks whether the content looks like a bot check, CAPTCHA or access-denied page.
+///
+/// # Arguments
+///
+/// * `content` - The scraped content to check.
+///
+/// # Returns
+///
+/// `true` if the page is short and contains a blocker marker, `false` otherwise.
+pub fn is_blocker_page(content: &ScrapedContent) -> bool {
+    if content.content.split_whitespace().count() > BLOCKER_MAX_WORDS {
+        return false;
+    }
+
+    let text = content.content.to_lowercase();
+    BLOCKER_MARKERS.iter().any(|marker| text.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    fn page(url: &str, content: &str) -> ScrapedContent {
+        ScrapedContent {
+            url: url.to_string(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+            timestamp: Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: None,
+            faq: Vec::new(),
+        }
+    }
+
+    fn article() -> String {
+        "Rust is a systems programming language focused on safety and performance. ".repeat(20)
+    }
+
+    /// Tests that a genuine article passes the default filter.
+    #[test]
+    fn test_article_passes() {
+        let filter = QualityFilter::default();
+        assert!(filter.passes(&page("https://example.com/rust", &article())));
+    }
+
+    /// Tests that content below the minimum length is dropped.
+    #[test]
+    fn test_short_content_rejected() {
+        let filter = QualityFilter::default();
+        assert!(!filter.passes(&page("https://example.com/short", "Too short.")));
+
+        // The minimum counts characters, not the bytes of their UTF-8 encoding
+        let filter = QualityFilter {
+            min_length: 20,
+            min_confidence: 0.0,
+            ..QualityFilter::default()
+        };
+        assert!(!filter.passes(&page("https://example.com/ja", "錆は安全で高速な言語です。")));
+        assert!(filter.passes(&page("https://example.com/ja", &"錆は安全で高速な言語です。".repeat(2))));
+    }
+
+    /// Tests that low-confidence content such as symbol soup is dropped.
+    #[test]
+    fn test_low_confidence_rejected() {
+        let filter = QualityFilter {
+            min_length: 0,
+            min_confidence: 0.5,
+            ..QualityFilter::default()
+        };
+        let noise = "| | | -- >> << 123 456 ## ** |".repeat(5);
+        assert!(!filter.passes(&page("https://example.com/noise", &noise)));
+    }
+
+    /// Tests that paywalled and blocker pages are dropped.
+    #[test]
+    fn test_paywall_and_blocker_rejected() {
+        let filter = QualityFilter::default();
+        let paywalled = format!("{} Subscribe to continue reading this story.", article());
+        let blocker = format!(
+            "Our systems have detected unusual traffic from your computer network. {}",
+            "Please try your request again later. ".repeat(10)
+        );
+
+        assert!(!filter.passes(&page("https://example.com/paywall", &paywalled)));
+        assert!(!filter.passes(&page("https://example.com/blocked", &blocker)));
+    }
+
+    /// Tests that `apply` keeps passing pages and errors when none pass.
+    #[test]
+    fn test_apply() {
+        let filter = QualityFilter::default();
+
+        let kept = filter
+            .apply(vec![
+                page("https://example.com/good", &article()),
+                page("https://example.com/bad", "Nope."),
+            ])
+            .unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].url, "https://example.com/good");
+
+        let result = filter.apply(vec![page("https://example.com/bad", "Nope.")]);
+        assert!(matches!(result, Err(ScraperError::ExtractionError(_))));
+    }
+
+    /// Tests each relevance mode against pages matching in the title, the body or neither.
+    #[test]
+    fn test_relevance_modes() {
+        let mut in_title = page("https://example.com/a", "A guide to memory management.");
+        in_title.metadata.insert("title".to_string(), "Rust Ownership Explained".to_string());
+        let mut in_body = page("https://example.com/b", "Ownership is central to Rust.");
+        in_body.metadata.insert("title".to_string(), "Language notes".to_string());
+        let mut neither = page("https://example.com/c", "Garbage collection in Java.");
+        neither.metadata.insert("title".to_string(), "Rust news".to_string());
+
+        let query = r#""rust ownership" of -java site:example.com"#;
+        let check = |mode| {
+            [&in_title, &in_body, &neither]
+                .iter()
+                .map(|content| matches_query(content, query, mode))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(check(RelevanceMode::TitleMatch), vec![true, false, false]);
+        assert_eq!(check(RelevanceMode::BodyMatch), vec![false, true, false]);
+        assert_eq!(check(RelevanceMode::Either), vec![true, true, false]);
+
+        let filter = QualityFilter {
+            relevance: Some(RelevanceMode::TitleMatch),
+            ..QualityFilter::default()
+        };
+        assert!(filter.is_relevant(&in_title, query));
+        assert!(!filter.is_relevant(&in_body, query));
+        assert!(QualityFilter::default().is_relevant(&neither, query));
+    }
+}
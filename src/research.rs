@@ -0,0 +1,622 @@
+use crate::{
+    cache::ContentCache,
+    llm::{LLMProcessor, PromptConfirmation, TokenCallback},
+    pipeline::{self, FetchStatus, PipelineOutcome},
+    search::SearchEngine,
+    Result, ScraperConfig, ScraperError,
+};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// The model used when `ResearchOptions` doesn't name one.
+pub const DEFAULT_MODEL: &str = "llama3.2:latest";
+/// The number of search results fetched when `ResearchOptions` doesn't set one.
+pub const DEFAULT_RESULTS: usize = 5;
+/// The number of queries `Sollama::research_batch` runs at once when `ResearchOptions` doesn't set one.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 3;
+/// The subdirectory of `cache_dir` research results are cached in.
+const RESULT_CACHE_DIR: &str = "results";
+
+/// The `ResearchOptions` struct holds the per-call settings of a research run.
+#[derive(Debug, Clone)]
+pub struct ResearchOptions {
+    /// The question for the model, or `None` to ask what the search query is about.
+    pub question: Option<String>,
+    /// The number of search results to fetch.
+    pub results: usize,
+    /// The name of the model used to answer.
+    pub model: String,
+    /// The maximum number of queries `Sollama::research_batch` researches at the same time.
+    pub batch_concurrency: usize,
+    /// Whether to bypass the result cache and research the query again, refreshing the cached result.
+    pub refresh: bool,
+    /// The URLs to fetch instead of searching; when empty, the query is searched.
+    pub urls: Vec<String>,
+}
+
+impl Default for ResearchOptions {
+    /// Provides default values for the `ResearchOptions` struct.
+    ///
+    /// # Returns
+    ///
+    /// A `ResearchOptions` instance asking the default model about five search results.
+    fn default() -> Self {
+        Self {
+            question: None,
+            results: DEFAULT_RESULTS,
+            model: DEFAULT_MODEL.to_string(),
+            batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+            refresh: false,
+            urls: Vec::new(),
+        }
+    }
+}
+
+impl ResearchOptions {
+    /// Returns the question sent to the model for a search query.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    ///
+    /// # Returns
+    ///
+    /// The configured question, or a question asking what the search query is.
+    pub fn question_for(&self, query: &str) -> String {
+        self.question
+            .clone()
+            .unwrap_or_else(|| format!("based on the content provided what is : {}", query))
+    }
+}
+
+/// The `ResearchStats` struct summarizes what a research run did.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResearchStats {
+    /// The number of URLs the search returned.
+    pub urls_found: usize,
+    /// The number of pages sent to the model.
+    pub pages_used: usize,
+    /// The number of pages fetched but dropped by the quality or relevance filters.
+    pub pages_filtered: usize,
+    /// The number of pages that could not be fetched.
+    pub pages_failed: usize,
+    /// The broadened query the URLs were found with, if the original query found nothing.
+    pub broadened_query: Option<String>,
+    /// The estimated token count of the prompt sent to the model, if a single prompt was built.
+    pub prompt_tokens: Option<usize>,
+    /// The total time the run took.
+    pub elapsed: Duration,
+}
+
+/// The `ResearchResult` struct is the answer of a research run together with its sources.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResearchResult {
+    /// The URLs of the pages the summary is based on.
+    pub sources: Vec<String>,
+    /// The model's answer.
+    pub summary: String,
+    /// Statistics about the run.
+    pub stats: ResearchStats,
+}
+
+impl TryFrom<PipelineOutcome> for ResearchResult {
+    type Error = ScraperError;
+
+    /// Converts a pipeline outcome into a research result, or into the error that stopped the
+    /// run from producing a summary.
+    fn try_from(outcome: PipelineOutcome) -> Result<Self> {
+        let mut stats = ResearchStats {
+            urls_found: outcome.urls().len(),
+            broadened_query: outcome.broadened_query,
+            prompt_tokens: outcome.prompt_tokens,
+            elapsed: outcome.elapsed,
+            ..ResearchStats::default()
+        };
+
+        let mut sources = Vec::new();
+        for fetch in outcome.fetches {
+            match fetch.status {
+                FetchStatus::Used => {
+                    stats.pages_used += 1;
+                    sources.push(fetch.url);
+                }
+                FetchStatus::Filtered => stats.pages_filtered += 1,
+                FetchStatus::Failed(_) => stats.pages_failed += 1,
+            }
+        }
+
+        outcome.search?;
+        let summary = outcome.summary.ok_or_else(|| {
+            ScraperError::SearchError("The run stopped before reaching the LLM".to_string())
+        })??;
+
+        Ok(Self { sources, summary, stats })
+    }
+}
+
+/// The `Sollama` struct is the main entry point of the library: it runs the whole search, fetch,
+/// filter and summarize pipeline in one call, with every feature enabled in its configuration.
+///
+/// # Example
+///
+/// ```no_run
+/// use sollama::{ResearchOptions, ScraperConfig, Sollama};
+///
+/// # async fn run() -> sollama::Result<()> {
+/// let sollama = Sollama::new(ScraperConfig::default());
+/// let result = sollama.research("rust async runtimes", &ResearchOptions::default()).await?;
+///
+/// println!("{}", result.summary);
+/// for source in &result.sources {
+///     println!("- {}", source);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Sollama {
+    /// The configuration every run uses.
+    config: ScraperConfig,
+    /// The callback asked before each prompt is sent to the model, if any.
+    confirm: Option<PromptConfirmation>,
+    /// The callback the model's answer is streamed to, if any.
+    on_token: Option<TokenCallback>,
+}
+
+impl fmt::Debug for Sollama {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sollama")
+            .field("config", &self.config)
+            .field("confirm", &self.confirm.is_some())
+            .field("on_token", &self.on_token.is_some())
+            .finish()
+    }
+}
+
+impl Sollama {
+    /// Creates a new `Sollama` with the given configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration settings for the scraper and the LLM.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `Sollama`.
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            confirm: None,
+            on_token: None,
+        }
+    }
+
+    /// Sets a callback that is asked before each prompt is sent to the model, with the prompt's
+    /// estimated token count. See `LLMProcessor::with_confirmation`.
+    ///
+    /// # Arguments
+    ///
+    /// * `confirm` - The callback; returning `false` fails the run with an `LLMError`.
+    ///
+    /// # Returns
+    ///
+    /// The updated `Sollama` instance.
+    pub fn with_confirmation(mut self, confirm: PromptConfirmation) -> Self {
+        self.confirm = Some(confirm);
+        self
+    }
+
+    /// Sets a callback the model's answer is streamed to, token by token, as it is generated.
+    /// See `LLMProcessor::with_token_callback`.
+    ///
+    /// Batch runs share the callback, so their tokens interleave.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_token` - The callback invoked with each generated token.
+    ///
+    /// # Returns
+    ///
+    /// The updated `Sollama` instance.
+    pub fn with_token_callback(mut self, on_token: TokenCallback) -> Self {
+        self.on_token = Some(on_token);
+        self
+    }
+
+    /// Returns the configuration runs use.
+    pub fn config(&self) -> &ScraperConfig {
+        &self.config
+    }
+
+    /// Searches the web, fetches and filters the results and asks the model about them. With
+    /// `options.urls` set, those pages are fetched instead of searching.
+    ///
+    /// With `result_cache_ttl` set, a result cached for the same query, question, model, result
+    /// count and configuration is returned without any network requests, unless `options.refresh`
+    /// is set. Successful results are cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `options` - The question, result count and model of this run.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the summary with its sources and statistics, or the error that
+    /// stopped the run from producing a summary.
+    pub async fn research(&self, query: &str, options: &ResearchOptions) -> Result<ResearchResult> {
+        if let Some(result) = self.cached_result(query, options) {
+            return Ok(result);
+        }
+
+        ResearchResult::try_from(self.run(query, options).await)
+            .inspect(|result| self.cache_result(query, options, result))
+    }
+
+    /// Researches many independent queries, running up to `batch_concurrency` of them at a time.
+    ///
+    /// All queries share one search engine and LLM processor, and with them the connection pool,
+    /// rate limiter and download budget, so a large batch can't overwhelm the network or the model.
+    /// A failing query doesn't affect the others. Results are cached like those of `research`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - The search queries to research.
+    /// * `options` - The question, result count, model and concurrency used for every query.
+    ///
+    /// # Returns
+    ///
+    /// Each query paired with its result, in the order of `queries`.
+    pub async fn research_batch(
+        &self,
+        queries: Vec<String>,
+        options: &ResearchOptions,
+    ) -> Vec<(String, Result<ResearchResult>)> {
+        let search_engine = match SearchEngine::new(self.config.clone()) {
+            Ok(search_engine) => search_engine,
+            Err(e) => {
+                let message = format!("Could not create the search engine: {}", e);
+                return queries
+                    .into_iter()
+                    .map(|query| (query, Err(ScraperError::SearchError(message.clone()))))
+                    .collect();
+            }
+        };
+        let llm_processor = self.llm_processor();
+        let (search_engine, llm_processor) = (&search_engine, &llm_processor);
+
+        stream::iter(queries)
+            .map(|query| async move {
+                if let Some(result) = self.cached_result(&query, options) {
+                    return (query, Ok(result));
+                }
+
+                let outcome = pipeline::run_with(
+                    search_engine,
+                    llm_processor,
+                    &query,
+                    &options.question_for(&query),
+                    options.results,
+                    &options.urls,
+                    &options.model,
+                )
+                .await;
+                let result = ResearchResult::try_from(outcome)
+                    .inspect(|result| self.cache_result(&query, options, result));
+                (query, result)
+            })
+            .buffered(options.batch_concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Runs the same pipeline as `research` but returns the outcome of every stage, including
+    /// partial failures, instead of collapsing it into a single result. The result cache is not
+    /// consulted; see `cached_result` and `cache_result`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `options` - The question, result count and model of this run.
+    ///
+    /// # Returns
+    ///
+    /// A `PipelineOutcome` describing what each stage produced.
+    pub async fn run(&self, query: &str, options: &ResearchOptions) -> PipelineOutcome {
+        let search_engine = match SearchEngine::new(self.config.clone()) {
+            Ok(search_engine) => search_engine,
+            Err(e) => return PipelineOutcome::failed(e),
+        };
+
+        pipeline::run_with(
+            &search_engine,
+            &self.llm_processor(),
+            query,
+            &options.question_for(query),
+            options.results,
+            &options.urls,
+            &options.model,
+        )
+        .await
+    }
+
+    /// Runs the search and fetch stages of `run` and builds the prompt without calling the model,
+    /// so the prompt can be inspected. See `pipeline::dry_run_with`. The result cache is not used.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `options` - The question, result count and URLs of this run; the model is ignored.
+    ///
+    /// # Returns
+    ///
+    /// A `PipelineOutcome` with the prompt in `prompt`, or the error that stopped the run.
+    pub async fn dry_run(&self, query: &str, options: &ResearchOptions) -> PipelineOutcome {
+        let search_engine = match SearchEngine::new(self.config.clone()) {
+            Ok(search_engine) => search_engine,
+            Err(e) => return PipelineOutcome::failed(e),
+        };
+
+        pipeline::dry_run_with(
+            &search_engine,
+            query,
+            &options.question_for(query),
+            options.results,
+            &options.urls,
+        )
+        .await
+    }
+
+    /// Returns the cached result of a query, if the result cache is enabled and holds a valid entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `options` - The question, result count and model of the run.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the cached result, or `None` on a miss or when `options.refresh` is set.
+    pub fn cached_result(&self, query: &str, options: &ResearchOptions) -> Option<ResearchResult> {
+        if options.refresh {
+            return None;
+        }
+
+        let json = self.result_cache()?.get(&self.result_cache_key(query, options))?;
+        let result = serde_json::from_str(&json)
+            .inspect_err(|e| warn!("Ignoring corrupt cached result for '{}': {}", query, e))
+            .ok()?;
+        debug!("Using the cached result for '{}'", query);
+        Some(result)
+    }
+
+    /// Stores the result of a query in the result cache, if it is enabled. Errors are logged,
+    /// since a missing entry only means the query is researched again.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `options` - The question, result count and model of the run.
+    /// * `result` - The result to cache.
+    pub fn cache_result(&self, query: &str, options: &ResearchOptions, result: &ResearchResult) {
+        let Some(cache) = self.result_cache() else {
+            return;
+        };
+
+        let stored = serde_json::to_string(result)
+            .map_err(std::io::Error::other)
+            .and_then(|json| cache.put(&self.result_cache_key(query, options), &json));
+        if let Err(e) = stored {
+            warn!("Failed to cache the result for '{}': {}", query, e);
+        }
+    }
+
+    /// Returns the result cache, or `None` if `result_cache_ttl` or `cache_dir` isn't set.
+    fn result_cache(&self) -> Option<ContentCache> {
+        let ttl = self.config.result_cache_ttl?;
+        let dir = self.config.cache_dir.as_ref()?.join(RESULT_CACHE_DIR);
+        Some(ContentCache::new(dir, Some(ttl), self.config.cache_compression))
+    }
+
+    /// Builds the result cache key of a query. The whole configuration is part of the key, so
+    /// changing any setting starts from a fresh result.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `options` - The question, result count and model of the run.
+    ///
+    /// # Returns
+    ///
+    /// The key, which the cache hashes into the entry's file name.
+    fn result_cache_key(&self, query: &str, options: &ResearchOptions) -> String {
+        json!({
+            "query": query,
+            "question": options.question_for(query),
+            "model": options.model,
+            "results": options.results,
+            "urls": options.urls,
+            "config": self.config,
+        })
+        .to_string()
+    }
+
+    /// Creates the LLM processor runs use, with the confirmation and token callbacks if set.
+    fn llm_processor(&self) -> LLMProcessor {
+        let mut llm_processor = pipeline::llm_processor(&self.config);
+        if let Some(confirm) = &self.confirm {
+            llm_processor = llm_processor.with_confirmation(confirm.clone());
+        }
+        if let Some(on_token) = &self.on_token {
+            llm_processor = llm_processor.with_token_callback(on_token.clone());
+        }
+        llm_processor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::outcome_fixture;
+
+    /// Tests that a successful run keeps only the used pages as sources and counts the rest.
+    #[test]
+    fn test_result_from_outcome() {
+        let outcome = PipelineOutcome {
+            prompt_tokens: Some(1200),
+            elapsed: Duration::from_secs(3),
+            ..outcome_fixture(
+                vec![
+                    FetchStatus::Used,
+                    FetchStatus::Filtered,
+                    FetchStatus::Failed(ScraperError::ExtractionError("blocked".to_string())),
+                    FetchStatus::Used,
+                ],
+                Some(Ok("summary".to_string())),
+            )
+        };
+
+        let result = ResearchResult::try_from(outcome).unwrap();
+        assert_eq!(result.summary, "summary");
+        assert_eq!(result.sources, vec!["https://example.com/0", "https://example.com/3"]);
+        assert_eq!(result.stats.urls_found, 4);
+        assert_eq!(result.stats.pages_used, 2);
+        assert_eq!(result.stats.pages_filtered, 1);
+        assert_eq!(result.stats.pages_failed, 1);
+        assert_eq!(result.stats.prompt_tokens, Some(1200));
+        assert_eq!(result.stats.elapsed, Duration::from_secs(3));
+    }
+
+    /// Tests that search and LLM failures are returned as errors.
+    #[test]
+    fn test_result_from_failed_outcome() {
+        let llm_failed = outcome_fixture(
+            vec![FetchStatus::Used],
+            Some(Err(ScraperError::LLMError("model not found".to_string()))),
+        );
+        assert!(matches!(ResearchResult::try_from(llm_failed), Err(ScraperError::LLMError(_))));
+
+        let mut search_failed = outcome_fixture(Vec::new(), None);
+        search_failed.search = Err(ScraperError::SearchError("rate limited".to_string()));
+        assert!(matches!(ResearchResult::try_from(search_failed), Err(ScraperError::SearchError(_))));
+    }
+
+    /// Tests that the question defaults to asking about the search query.
+    #[test]
+    fn test_question_for() {
+        let options = ResearchOptions::default();
+        assert_eq!(options.question_for("rust"), "based on the content provided what is : rust");
+
+        let options = ResearchOptions {
+            question: Some("Why is it fast?".to_string()),
+            ..ResearchOptions::default()
+        };
+        assert_eq!(options.question_for("rust"), "Why is it fast?");
+    }
+
+    /// Tests that batch results keep the order of the input queries and that failing queries
+    /// are reported individually.
+    #[tokio::test]
+    async fn test_research_batch_order() {
+        let sollama = Sollama::default();
+        let queries = vec!["\"unbalanced".to_string(), "   ".to_string(), "site:".to_string()];
+        let options = ResearchOptions {
+            batch_concurrency: 2,
+            ..ResearchOptions::default()
+        };
+
+        let results = sollama.research_batch(queries.clone(), &options).await;
+
+        let returned: Vec<String> = results.iter().map(|(query, _)| query.clone()).collect();
+        assert_eq!(returned, queries);
+        assert!(results
+            .iter()
+            .all(|(_, result)| matches!(result, Err(ScraperError::SearchError(_)))));
+    }
+
+    /// Tests that a dry run never calls the model and reports why no prompt was built.
+    #[tokio::test]
+    async fn test_dry_run() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/api/generate").expect(0).create_async().await;
+
+        let mut config = ScraperConfig::default();
+        config.llm_config.endpoint = format!("{}/api/generate", server.url());
+        let sollama = Sollama::new(config);
+
+        let outcome = sollama.dry_run("site:", &ResearchOptions::default()).await;
+        assert!(outcome.prompt.is_none());
+        assert!(outcome.summary.is_none());
+        assert!(matches!(outcome.error(), Some(ScraperError::SearchError(_))));
+
+        mock.assert_async().await;
+    }
+
+    /// Tests that malformed or disallowed URLs given instead of a search are rejected up front.
+    #[tokio::test]
+    async fn test_invalid_urls() {
+        let sollama = Sollama::default();
+        let options = ResearchOptions {
+            urls: vec!["https://www.rust-lang.org/".to_string(), "rust-lang.org".to_string()],
+            ..ResearchOptions::default()
+        };
+
+        let outcome = sollama.dry_run("rust", &options).await;
+        assert!(outcome.fetches.is_empty());
+        match outcome.error() {
+            Some(ScraperError::SearchError(message)) => assert!(message.ends_with(": rust-lang.org"), "{}", message),
+            other => panic!("expected a search error, got {:?}", other),
+        }
+    }
+
+    /// Tests that a cached result is returned without searching or calling the model, and that
+    /// `refresh` bypasses it.
+    #[tokio::test]
+    async fn test_result_cache() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/api/generate").expect(0).create_async().await;
+
+        let dir = std::env::temp_dir().join(format!("sollama-results-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut config = ScraperConfig {
+            cache_dir: Some(dir.clone()),
+            result_cache_ttl: Some(Duration::from_secs(60)),
+            ..ScraperConfig::default()
+        };
+        config.llm_config.endpoint = format!("{}/api/generate", server.url());
+        let sollama = Sollama::new(config);
+
+        // An invalid query fails before any request, so a successful result must come from the cache
+        let query = "site:";
+        let options = ResearchOptions::default();
+        let cached = ResearchResult {
+            sources: vec!["https://example.com/rust".to_string()],
+            summary: "Rust is a systems programming language.".to_string(),
+            stats: ResearchStats::default(),
+        };
+        sollama.cache_result(query, &options, &cached);
+
+        let result = sollama.research(query, &options).await.unwrap();
+        assert_eq!(result.summary, cached.summary);
+        assert_eq!(result.sources, cached.sources);
+
+        let other_model = ResearchOptions {
+            model: "mistral:7b".to_string(),
+            ..ResearchOptions::default()
+        };
+        assert!(sollama.cached_result(query, &other_model).is_none());
+
+        let refresh = ResearchOptions {
+            refresh: true,
+            ..ResearchOptions::default()
+        };
+        assert!(matches!(sollama.research(query, &refresh).await, Err(ScraperError::SearchError(_))));
+
+        mock.assert_async().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
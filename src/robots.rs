@@ -0,0 +1,199 @@
+/// The `RobotsRules` struct holds the `Allow` and `Disallow` rules of a robots.txt file that apply
+/// to one user agent.
+///
+/// Rules are matched against the path and query of a URL. The longest matching rule wins, with
+/// `Allow` winning ties, and `*` and a trailing `$` are supported as in Google's robots.txt spec.
+/// A missing or empty robots.txt allows everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    /// The rules as `(allow, pattern)` pairs, in file order.
+    rules: Vec<(bool, String)>,
+}
+
+/// A group of a robots.txt file: consecutive `User-agent` lines and the rules that follow them.
+#[derive(Default)]
+struct Group {
+    /// The lowercased user agent tokens.
+    agents: Vec<String>,
+    /// The rules as `(allow, pattern)` pairs, in file order.
+    rules: Vec<(bool, String)>,
+}
+
+impl RobotsRules {
+    /// Parses a robots.txt file, keeping the rules of the group for `user_agent`.
+    ///
+    /// A group applies when its `User-agent` token appears in `user_agent`, case-insensitively.
+    /// The group with the longest matching token is used, falling back to the `*` group.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The contents of the robots.txt file.
+    /// * `user_agent` - The user agent string requests are sent with.
+    ///
+    /// # Returns
+    ///
+    /// The rules that apply to the user agent.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_lowercase();
+
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agents = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agents {
+                        groups.push(Group::default());
+                        in_agents = true;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_lowercase());
+                    }
+                }
+                directive @ ("allow" | "disallow") => {
+                    in_agents = false;
+                    // An empty Disallow allows everything, so it adds no rule
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.rules.push((directive == "allow", value.to_string()));
+                    }
+                }
+                _ => in_agents = false,
+            }
+        }
+
+        let specific = groups
+            .iter()
+            .flat_map(|group| group.agents.iter().map(move |agent| (agent, &group.rules)))
+            .filter(|(agent, _)| *agent != "*" && user_agent.contains(agent.as_str()))
+            .max_by_key(|(agent, _)| agent.len())
+            .map(|(_, rules)| rules);
+        let rules = specific.or_else(|| {
+            groups
+                .iter()
+                .find(|group| group.agents.iter().any(|agent| agent == "*"))
+                .map(|group| &group.rules)
+        });
+
+        Self {
+            rules: rules.cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Checks whether a path may be fetched.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path and query of the URL, e.g. `/docs/page?lang=en`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if no rule disallows the path, `false` otherwise.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map(|(allow, _)| *allow)
+            .unwrap_or(true)
+    }
+}
+
+/// Checks whether a robots.txt path pattern matches a path.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern, where `*` matches any run of characters and a trailing `$` anchors
+///   the end of the path.
+/// * `path` - The path to match.
+///
+/// # Returns
+///
+/// `true` if the pattern matches the start of the path (or all of it, when anchored).
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = parts.next().and_then(|first| path.strip_prefix(first)) else {
+        return false;
+    };
+
+    let mut parts = parts.peekable();
+    while let Some(part) = parts.next() {
+        // The last part of an anchored pattern must match the end of the path
+        if anchored && parts.peek().is_none() {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS_TXT: &str = "
+        # Everyone else
+        User-agent: *
+        Disallow: /private/
+        Disallow: /*.pdf$
+        Allow: /private/public-report
+
+        User-agent: RustBot
+        User-agent: OtherBot
+        Disallow: /
+        Allow: /docs/
+
+        User-agent: Nobody
+        Disallow:
+    ";
+
+    /// Tests that the group for the user agent is chosen, with `*` as the fallback.
+    #[test]
+    fn test_group_selection() {
+        let rules = RobotsRules::parse(ROBOTS_TXT, "Mozilla/5.0 (compatible; RustBot/1.0)");
+        assert!(!rules.is_allowed("/blog/post"));
+        assert!(rules.is_allowed("/docs/intro"));
+
+        let rules = RobotsRules::parse(ROBOTS_TXT, "Mozilla/5.0 (X11; Linux x86_64) Firefox/125.0");
+        assert!(rules.is_allowed("/blog/post"));
+        assert!(!rules.is_allowed("/private/notes"));
+
+        let rules = RobotsRules::parse(ROBOTS_TXT, "Nobody/1.0");
+        assert!(rules.is_allowed("/private/notes"));
+
+        assert!(RobotsRules::parse("", "RustBot").is_allowed("/anything"));
+    }
+
+    /// Tests longest-match precedence, wildcards and end anchors.
+    #[test]
+    fn test_rule_matching() {
+        let rules = RobotsRules::parse(ROBOTS_TXT, "AnyBot");
+
+        assert!(rules.is_allowed("/private/public-report?year=2024"));
+        assert!(!rules.is_allowed("/private/"));
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf?download=1"));
+        assert!(rules.is_allowed("/"));
+
+        assert!(pattern_matches("/a*b*c", "/a-x-b-y-c-z"));
+        assert!(!pattern_matches("/a*b*c$", "/a-x-b-y-c-z"));
+        assert!(pattern_matches("/*.php$", "/index.php"));
+    }
+}
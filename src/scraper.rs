@@ -1,8 +1,96 @@
-use crate::{Result, ScrapedContent, ScraperError};
-use scraper::{Html, Selector};
+use crate::{
+    config::SiteRules,
+    types::FaqEntry,
+    urls::{self, date_from_url},
+    Result, ScrapedContent, ScraperError,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use scraper::{node::Node, ElementRef, Html, Selector};
 use std::collections::HashMap;
 use tracing::instrument;
 
+/// The elements that start a new line of text when their content is extracted.
+const BLOCK_ELEMENTS: [&str; 30] = [
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption",
+    "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav",
+    "ol", "p", "pre", "section", "table", "tr", "ul",
+];
+
+/// The elements whose text is code or a fallback rather than page content.
+const NON_CONTENT_ELEMENTS: [&str; 3] = ["script", "style", "noscript"];
+
+/// The container selectors tried, in order, when extracting Markdown. Containers are converted
+/// whole, so headings and lists keep their place between the paragraphs.
+const MARKDOWN_CONTAINERS: [&str; 7] = [
+    "article",
+    "main",
+    "div.content",
+    ".documentation-content",
+    "div.markdown-body",
+    "div.mw-parser-output",
+    "body",
+];
+
+/// The elements left out of Markdown output, along with everything inside them.
+const MARKDOWN_SKIPPED: [&str; 9] = [
+    "script", "style", "noscript", "template", "nav", "footer", "aside", "form", "button",
+];
+
+/// The elements whose text is scored by the readability extractor.
+const READABLE_SCORED: &str = "p, pre, td, blockquote";
+/// The elements whose text is kept from the best-scoring subtree by the readability extractor.
+const READABLE_BLOCKS: &str = "p, li, dl, pre, blockquote, h2, h3, h4, h5, h6";
+/// The shortest paragraph, in characters, the readability extractor scores.
+const READABLE_MIN_CHARS: usize = 25;
+/// The elements that never hold the main content of a page.
+const UNLIKELY_ELEMENTS: [&str; 12] = [
+    "nav", "header", "footer", "aside", "form", "script", "style", "noscript", "template", "button",
+    "menu", "dialog",
+];
+/// The ARIA roles of landmarks that never hold the main content of a page.
+const UNLIKELY_ROLES: [&str; 6] = ["navigation", "banner", "contentinfo", "complementary", "dialog", "alert"];
+/// Class and id fragments marking boilerplate such as menus, banners and related links.
+const UNLIKELY_HINTS: [&str; 18] = [
+    "banner", "breadcrumb", "comment", "consent", "cookie", "footer", "menu", "modal", "nav",
+    "newsletter", "popup", "promo", "related", "share", "sidebar", "social", "sponsor", "subscribe",
+];
+/// Class and id fragments marking the main content.
+const LIKELY_HINTS: [&str; 7] = ["article", "body", "content", "entry", "main", "post", "story"];
+
+/// The Open Graph properties read into the metadata, with the key each is stored under.
+const OPEN_GRAPH_PROPERTIES: [(&str, &str); 4] = [
+    ("og:title", "title"),
+    ("og:description", "description"),
+    ("og:site_name", "site_name"),
+    ("og:type", "type"),
+];
+/// The schema.org types of the JSON-LD blocks whose fields are read into the metadata.
+const JSON_LD_ARTICLE_TYPES: [&str; 5] = ["Article", "NewsArticle", "BlogPosting", "TechArticle", "ScholarlyArticle"];
+
+/// The elements holding a page's publication date, most reliable first, with the attribute the
+/// date is read from.
+const PUBLISHED_DATE_SOURCES: [(&str, &str); 7] = [
+    ("meta[property='article:published_time']", "content"),
+    ("meta[name='article:published_time']", "content"),
+    ("meta[itemprop='datePublished']", "content"),
+    ("meta[name='date'], meta[name='pubdate'], meta[name='publish-date']", "content"),
+    ("meta[name='dc.date'], meta[name='DC.date.issued'], meta[name='dcterms.created']", "content"),
+    ("time[itemprop='datePublished'], time[pubdate]", "datetime"),
+    ("time[datetime]", "datetime"),
+];
+/// The formats of dates without a time zone, which are taken as UTC.
+const NAIVE_DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+/// The formats of dates without a time, which are taken as midnight UTC.
+const DATE_FORMATS: [&str; 5] = ["%Y-%m-%d", "%Y/%m/%d", "%B %d, %Y", "%b %d, %Y", "%d %B %Y"];
+
+/// The average reading speed, in words per minute, used to estimate reading time.
+const WORDS_PER_MINUTE: usize = 200;
+
 /// The `ContentScraper` struct is responsible for extracting content and metadata from HTML documents.
 /// It uses CSS selectors to identify the relevant parts of the document.
 pub struct ContentScraper {
@@ -10,6 +98,22 @@ pub struct ContentScraper {
     selectors: Vec<Selector>,
     /// A map of metadata keys to CSS selectors used to extract metadata from the HTML document.
     metadata_selectors: HashMap<String, Selector>,
+    /// Whether to append image alt text and figure captions to the extracted content.
+    include_image_captions: bool,
+    /// The minimum number of words a text block needs to be kept in the extracted content.
+    min_paragraph_words: usize,
+    /// The compiled per-host selector rules, checked in order.
+    site_rules: Vec<SiteSelectors>,
+}
+
+/// The `SiteSelectors` struct holds the compiled selectors of one site rule.
+struct SiteSelectors {
+    /// The host pattern the selectors apply to.
+    host: String,
+    /// The content selectors tried before the default ones.
+    selectors: Vec<Selector>,
+    /// The metadata selectors that replace the default ones.
+    metadata_selectors: HashMap<String, Selector>,
 }
 
 impl Default for ContentScraper {
@@ -20,13 +124,13 @@ impl Default for ContentScraper {
     /// A `ContentScraper` instance with default selectors.
     fn default() -> Self {
         let default_selectors = [
-            "article p, article li",
-            "div.content p, div.content li",
-            "main p, main li",
+            "article p, article li, article dl",
+            "div.content p, div.content li, div.content dl",
+            "main p, main li, main dl",
             ".documentation-content",
             "div.markdown-body",
             "div.mw-parser-output p",
-            "p, li",
+            "p, li, dl",
         ];
 
         let metadata_selectors = [
@@ -73,9 +177,73 @@ impl ContentScraper {
         Self {
             selectors,
             metadata_selectors,
+            include_image_captions: false,
+            min_paragraph_words: 0,
+            site_rules: Vec::new(),
         }
     }
 
+    /// Sets whether image alt text and `<figcaption>` content are appended to the extracted content.
+    ///
+    /// # Arguments
+    ///
+    /// * `include` - Whether to include image captions.
+    ///
+    /// # Returns
+    ///
+    /// The `ContentScraper` with the setting applied.
+    pub fn with_image_captions(mut self, include: bool) -> Self {
+        self.include_image_captions = include;
+        self
+    }
+
+    /// Sets the minimum number of words a text block (a paragraph, list item or other block
+    /// element) needs to be kept, dropping fragments such as "Read more" or "Share".
+    ///
+    /// # Arguments
+    ///
+    /// * `min_words` - The minimum word count, or 0 to keep every block.
+    ///
+    /// # Returns
+    ///
+    /// The `ContentScraper` with the setting applied.
+    pub fn with_min_paragraph_words(mut self, min_words: usize) -> Self {
+        self.min_paragraph_words = min_words;
+        self
+    }
+
+    /// Sets the per-host selector rules. Pages on a matching host try the rule's content
+    /// selectors before the default ones, and its metadata selectors replace the defaults.
+    /// Selectors that fail to parse are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - The site rules, typically loaded with `SiteRules::load`.
+    ///
+    /// # Returns
+    ///
+    /// The `ContentScraper` with the rules applied.
+    pub fn with_site_rules(mut self, rules: &SiteRules) -> Self {
+        self.site_rules = rules
+            .rules
+            .iter()
+            .map(|rule| SiteSelectors {
+                host: rule.host.clone(),
+                selectors: rule
+                    .content
+                    .iter()
+                    .filter_map(|s| Selector::parse(s).ok())
+                    .collect(),
+                metadata_selectors: rule
+                    .metadata
+                    .iter()
+                    .filter_map(|(key, sel)| Selector::parse(sel).ok().map(|selector| (key.clone(), selector)))
+                    .collect(),
+            })
+            .collect();
+        self
+    }
+
     /// Extracts the main content and metadata from the given HTML string.
     ///
     /// # Arguments
@@ -90,27 +258,220 @@ impl ContentScraper {
     pub fn extract(&self, html: &str, url: &str) -> Result<ScrapedContent> {
         let document = Html::parse_document(html);
 
-        let content = self.extract_content(&document)?;
-        let metadata = self.extract_metadata(&document);
+        let content = self.extract_content(&document, url)?;
+        Ok(self.scraped_content(&document, url, content))
+    }
+
+    /// Extracts the main content as Markdown, together with the metadata, from the given HTML string.
+    ///
+    /// Unlike `extract`, which flattens the content into lines of text, headings become `#`
+    /// lines, list items `-` lines (indented when nested) and links `[text](url)` with the URL
+    /// resolved against the page, so the model can see the structure of the page and refer to
+    /// its sections.
+    ///
+    /// # Arguments
+    ///
+    /// * `html` - The HTML string to be parsed.
+    /// * `url` - The URL of the HTML document.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `ScrapedContent` with the Markdown content and metadata, or an error if no content is found.
+    #[instrument(skip(self, html), fields(html_length = html.len()))]
+    pub fn extract_markdown(&self, html: &str, url: &str) -> Result<ScrapedContent> {
+        let document = Html::parse_document(html);
+
+        let content = self.extract_markdown_content(&document, url)?;
+        Ok(self.scraped_content(&document, url, content))
+    }
+
+    /// Extracts the main content, together with the metadata, from the given HTML string with a
+    /// Readability-style heuristic instead of the content selectors.
+    ///
+    /// Paragraphs are scored by their length and number of commas, and each score is added to
+    /// the paragraph's parent and, halved, to its grandparent. The element with the highest score,
+    /// discounted by the share of its text inside links, is taken as the main content. Menus,
+    /// banners, sidebars and footers, recognized by element, ARIA role, class and id, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `html` - The HTML string to be parsed.
+    /// * `url` - The URL of the HTML document.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `ScrapedContent` with the extracted content and metadata, or an error if no paragraph scores.
+    #[instrument(skip(self, html), fields(html_length = html.len()))]
+    pub fn extract_readable(&self, html: &str, url: &str) -> Result<ScrapedContent> {
+        let document = Html::parse_document(html);
+
+        let content = self.extract_readable_content(&document)?;
+        Ok(self.scraped_content(&document, url, content))
+    }
+
+    /// Builds the `ScrapedContent` of a page from its extracted content, appending image captions
+    /// when enabled and extracting the metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    /// * `url` - The URL of the page.
+    /// * `content` - The extracted content.
+    ///
+    /// # Returns
+    ///
+    /// The `ScrapedContent` of the page.
+    fn scraped_content(&self, document: &Html, url: &str, mut content: String) -> ScrapedContent {
+        if self.include_image_captions {
+            append_image_captions(&mut content, document);
+        }
+        let mut metadata = self.extract_metadata(document, url);
+        insert_reading_stats(&mut metadata, &content);
+
+        ScrapedContent {
+            url: url.to_string(),
+            content,
+            metadata,
+            timestamp: chrono::Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: published_date(document).or_else(|| date_from_url(url)),
+            faq: extract_faq(document),
+        }
+    }
+
+    /// Extracts only the content of a single section of the HTML document.
+    ///
+    /// The section starts at the heading matching `heading` and ends at the next heading of the
+    /// same or a higher level. Headings are matched case-insensitively and fuzzily: an anchor id
+    /// (`#installation`), exact text, partial text or a majority of the query words all match,
+    /// with closer matches preferred.
+    ///
+    /// # Arguments
+    ///
+    /// * `html` - The HTML string to be parsed.
+    /// * `url` - The URL of the HTML document.
+    /// * `heading` - The heading text or anchor of the section to extract.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `ScrapedContent` for the section, or an error if no matching heading or content is found.
+    #[instrument(skip(self, html), fields(html_length = html.len()))]
+    pub fn extract_section(&self, html: &str, url: &str, heading: &str) -> Result<ScrapedContent> {
+        let document = Html::parse_document(html);
+
+        let target = find_heading(&document, heading).ok_or_else(|| {
+            ScraperError::ExtractionError(format!("No section matching '{}' found", heading))
+        })?;
+        let level = heading_level(target.value().name()).unwrap_or(6);
+
+        let mut parts = Vec::new();
+        let mut in_section = false;
+
+        for node in document.root_element().descendants() {
+            if node.id() == target.id() {
+                in_section = true;
+                continue;
+            }
+            if !in_section {
+                continue;
+            }
+
+            if let Some(element) = ElementRef::wrap(node) {
+                // Stop at the next heading of the same or a higher level
+                if heading_level(element.value().name()).is_some_and(|l| l <= level) {
+                    break;
+                }
+            } else if let Some(text) = node.value().as_text() {
+                let skip = node.ancestors().any(|ancestor| {
+                    ancestor.id() == target.id()
+                        || matches!(
+                            ancestor.value(),
+                            Node::Element(e) if matches!(e.name(), "script" | "style" | "noscript")
+                        )
+                });
+
+                if !skip && !text.trim().is_empty() {
+                    parts.push(text.trim().to_string());
+                }
+            }
+        }
+
+        let content = self.clean_text(&parts.join(" "));
+        if content.is_empty() {
+            return Err(ScraperError::ExtractionError(format!(
+                "Section '{}' has no content",
+                heading
+            )));
+        }
+
+        let mut metadata = self.extract_metadata(&document, url);
+        metadata.insert(
+            "section".to_string(),
+            target.text().collect::<Vec<_>>().join(" ").trim().to_string(),
+        );
 
         Ok(ScrapedContent {
             url: url.to_string(),
             content,
             metadata,
             timestamp: chrono::Utc::now(),
+            raw_html: None,
+            redirect_chain: Vec::new(),
+            published: published_date(&document).or_else(|| date_from_url(url)),
+            faq: Vec::new(),
         })
     }
 
-    /// Extracts the main content from the HTML document using the configured selectors.
+    /// Finds the site rule that applies to a URL's host.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the first matching rule, or `None` if no rule applies.
+    fn site_rule(&self, url: &str) -> Option<&SiteSelectors> {
+        let host = urls::host(url)?;
+        self.site_rules.iter().find(|rule| urls::host_matches(&host, &rule.host))
+    }
+
+    /// Extracts the content of a page with the content selectors of the site rule for its host.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the cleaned content, or `None` if no rule applies or its selectors match nothing.
+    pub(crate) fn extract_site_content(&self, document: &Html, url: &str) -> Option<String> {
+        self.site_rule(url)?
+            .selectors
+            .iter()
+            .map(|selector| self.extract_text_by_selector(document, selector))
+            .find(|content| !content.is_empty())
+            .map(|content| self.clean_text(&content))
+    }
+
+    /// Extracts the main content from the HTML document using the site rule for the URL's host,
+    /// falling back to the configured selectors.
     ///
     /// # Arguments
     ///
     /// * `document` - The parsed HTML document.
+    /// * `url` - The URL of the page.
     ///
     /// # Returns
     ///
     /// A `Result` containing the extracted content as a string, or an error if no content is found.
-    fn extract_content(&self, document: &Html) -> Result<String> {
+    fn extract_content(&self, document: &Html, url: &str) -> Result<String> {
+        if let Some(content) = self.extract_site_content(document, url) {
+            return Ok(content);
+        }
+
         for selector in &self.selectors {
             let content = self.extract_text_by_selector(document, selector);
             if !content.is_empty() {
@@ -123,16 +484,137 @@ impl ContentScraper {
         ))
     }
 
-    /// Extracts metadata from the HTML document using the configured selectors.
+    /// Extracts the main content from the HTML document as Markdown. The content selectors of the
+    /// site rule for the URL's host are tried first, then the `MARKDOWN_CONTAINERS`; every
+    /// outermost element a selector matches is converted.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    /// * `url` - The URL of the page, which relative links are resolved against.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the Markdown content, or an error if no content is found.
+    pub(crate) fn extract_markdown_content(&self, document: &Html, url: &str) -> Result<String> {
+        let site_selectors = self.site_rule(url).map(|rule| rule.selectors.clone()).unwrap_or_default();
+        let containers = MARKDOWN_CONTAINERS.iter().filter_map(|s| Selector::parse(s).ok());
+
+        for selector in site_selectors.into_iter().chain(containers) {
+            let content = document
+                .select(&selector)
+                // Skip matches nested in another match, which were converted with it
+                .filter(|element| !element.ancestors().filter_map(ElementRef::wrap).any(|a| selector.matches(&a)))
+                .map(|element| markdown_text(element, url))
+                .collect::<Vec<_>>()
+                .join("\n");
+            // Headings are kept regardless of their length, and list markers aren't counted as words
+            let content = content
+                .lines()
+                .filter(|line| {
+                    let words = line.trim_start().trim_start_matches("- ").split_whitespace().count();
+                    line.starts_with('#') || words >= self.min_paragraph_words
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            if !content.trim().is_empty() {
+                return Ok(content);
+            }
+        }
+
+        Err(ScraperError::ExtractionError(
+            "No content found with available selectors".to_string(),
+        ))
+    }
+
+    /// Extracts the main content from the HTML document with the readability heuristic described
+    /// in `extract_readable`.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the cleaned content, or an error if no paragraph scores.
+    pub(crate) fn extract_readable_content(&self, document: &Html) -> Result<String> {
+        let not_found = || ScraperError::ExtractionError("No readable content found".to_string());
+        let (Ok(scored), Ok(blocks)) = (Selector::parse(READABLE_SCORED), Selector::parse(READABLE_BLOCKS)) else {
+            return Err(not_found());
+        };
+
+        let mut scores = HashMap::new();
+        for paragraph in document.select(&scored) {
+            let ancestors: Vec<ElementRef<'_>> = paragraph.ancestors().filter_map(ElementRef::wrap).collect();
+            if is_unlikely(paragraph) || ancestors.iter().any(|ancestor| is_unlikely(*ancestor)) {
+                continue;
+            }
+
+            let text = collapse_whitespace(&paragraph.text().collect::<String>());
+            let length = text.chars().count();
+            if length < READABLE_MIN_CHARS {
+                continue;
+            }
+            let score = 1.0 + text.matches(',').count() as f32 + (length as f32 / 100.0).min(3.0);
+
+            for (ancestor, share) in ancestors.iter().zip([1.0, 0.5]) {
+                *scores.entry(ancestor.id()).or_insert_with(|| class_weight(*ancestor)) += score * share;
+            }
+        }
+
+        let top = scores
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let element = document.tree.get(id).and_then(ElementRef::wrap)?;
+                Some((element, score * (1.0 - link_density(element))))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(element, _)| element)
+            .ok_or_else(not_found)?;
+
+        // Keep the outermost text blocks of the subtree, leaving out boilerplate and link lists
+        let content = top
+            .select(&blocks)
+            .filter(|block| {
+                !block
+                    .ancestors()
+                    .take_while(|node| node.id() != top.id())
+                    .filter_map(ElementRef::wrap)
+                    .any(|ancestor| is_unlikely(ancestor) || blocks.matches(&ancestor))
+            })
+            .filter(|block| !is_unlikely(*block) && link_density(*block) <= 0.5)
+            .map(|block| drop_short_blocks(&content_text(block), self.min_paragraph_words))
+            .filter(|text| !text.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Text directly inside the top element, e.g. separated by `<br>`s, has no blocks
+        let content = if content.is_empty() {
+            drop_short_blocks(&block_text(top), self.min_paragraph_words)
+        } else {
+            content
+        };
+
+        let content = self.clean_text(&content);
+        if content.is_empty() {
+            return Err(not_found());
+        }
+        Ok(content)
+    }
+
+    /// Extracts metadata from the HTML document using the configured selectors. Open Graph and
+    /// JSON-LD article metadata take precedence over them, and the selectors of the site rule for
+    /// the URL's host over both.
     ///
     /// # Arguments
     ///
     /// * `document` - The parsed HTML document.
+    /// * `url` - The URL of the page.
     ///
     /// # Returns
     ///
     /// A `HashMap` containing the extracted metadata.
-    fn extract_metadata(&self, document: &Html) -> HashMap<String, String> {
+    pub(crate) fn extract_metadata(&self, document: &Html, url: &str) -> HashMap<String, String> {
         let mut metadata = HashMap::new();
 
         for (key, selector) in &self.metadata_selectors {
@@ -140,6 +622,18 @@ impl ContentScraper {
                 metadata.insert(key.clone(), value);
             }
         }
+        metadata.extend(extract_structured_metadata(document));
+
+        let site_selectors = self.site_rule(url).map(|rule| &rule.metadata_selectors);
+        for (key, selector) in site_selectors.into_iter().flatten() {
+            if let Some(value) = self.extract_metadata_value(document, selector) {
+                metadata.insert(key.clone(), value);
+            }
+        }
+
+        if let Some(trail) = extract_breadcrumb(document) {
+            metadata.insert("breadcrumb".to_string(), trail);
+        }
 
         metadata
     }
@@ -188,9 +682,7 @@ impl ContentScraper {
     fn extract_text_by_selector(&self, document: &Html, selector: &Selector) -> String {
         document
             .select(selector)
-            .map(|element| {
-                element.text().collect::<Vec<_>>().join(" ")
-            })
+            .map(|element| drop_short_blocks(&content_text(element), self.min_paragraph_words))
             .filter(|s| !s.trim().is_empty())
             .collect::<Vec<_>>()
             .join("\n")
@@ -198,7 +690,9 @@ impl ContentScraper {
             .to_string()
     }
 
-    /// Cleans the extracted text by removing non-ASCII characters and normalizing whitespace.
+    /// Cleans the extracted text by removing control characters and normalizing whitespace.
+    /// Line breaks between blocks are kept so sentences from separate elements never merge, and
+    /// all other Unicode text (accents, CJK, emoji) is kept as-is.
     ///
     /// # Arguments
     ///
@@ -209,77 +703,1554 @@ impl ContentScraper {
     /// The cleaned text.
     fn clean_text(&self, text: &str) -> String {
         text.chars()
-            .filter(|&c| c.is_ascii() || c.is_whitespace())
+            .filter(|&c| !c.is_control() || c.is_whitespace())
             .collect::<String>()
-            .split_whitespace()
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
             .collect::<Vec<_>>()
-            .join(" ")
+            .join("\n")
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Extracts the text of an element, starting a new line at each block element boundary.
+///
+/// Text within inline elements is joined as-is, so `<b>Rust</b>acean` stays one word, while
+/// `<p>One.</p><p>Two.</p>` becomes two lines instead of `One.Two.`. The contents of `<script>`,
+/// `<style>` and `<noscript>` elements are left out.
+///
+/// # Arguments
+///
+/// * `element` - The element to extract text from.
+///
+/// # Returns
+///
+/// The text with whitespace collapsed within each line and empty lines removed.
+pub(crate) fn block_text(element: ElementRef<'_>) -> String {
+    let is_block = |node: &Node| matches!(node, Node::Element(e) if BLOCK_ELEMENTS.contains(&e.name()));
 
-    /// Tests the content extraction functionality of the `ContentScraper`.
-    #[test]
-    fn test_content_extraction() {
-        let html = r#"
-            <!DOCTYPE html>
-            <html>
-                <head>
-                    <title>Test Page</title>
-                    <meta name="description" content="Test description">
-                    <meta name="author" content="Test Author">
-                </head>
-                <body>
-                    <article>
-                        <h1>Test Article</h1>
-                        <p>This is a test paragraph.</p>
-                        <p>This is another paragraph.</p>
-                    </article>
-                </body>
-            </html>
-        "#;
+    let mut text = String::new();
+    for node in element.descendants() {
+        // Content following a block element starts on its own line
+        if node.prev_sibling().is_some_and(|sibling| is_block(sibling.value())) {
+            text.push('\n');
+        }
 
-        let scraper = ContentScraper::default();
-        let result = scraper.extract(html, "https://example.com").unwrap();
+        // Scripts, styles and noscript fallbacks inside the element aren't page content
+        let in_non_content = node
+            .ancestors()
+            .take_while(|ancestor| ancestor.id() != element.id())
+            .any(|ancestor| is_non_content(ancestor.value()));
 
-        assert!(result.content.contains("This is a test paragraph"));
-        assert!(result.content.contains("This is another paragraph"));
-        assert_eq!(result.metadata.get("title").unwrap(), "Test Page");
-        assert_eq!(result.metadata.get("description").unwrap(), "Test description");
-        assert_eq!(result.metadata.get("author").unwrap(), "Test Author");
+        match node.value() {
+            Node::Text(t) if !in_non_content => text.push_str(t),
+            value if is_block(value) => text.push('\n'),
+            _ => {}
+        }
     }
 
-    /// Tests the content extraction functionality with custom selectors.
-    #[test]
-    fn test_custom_selectors() {
-        let html = r#"
-            <div class="custom-content">
-                <span class="special">Special content</span>
-            </div>
-        "#;
-
-        let scraper = ContentScraper::new(
-            vec![".custom-content .special"],
-            vec![("custom", ".special")],
-        );
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-        let result = scraper.extract(html, "https://example.com").unwrap();
+/// Extracts the text of an element matched by a content selector.
+///
+/// Definition lists are rendered as `Term: definition` lines, and elements inside a definition
+/// list are skipped, since selectors like `p, li, dl` match them as part of the list as well.
+///
+/// # Arguments
+///
+/// * `element` - The matched element.
+///
+/// # Returns
+///
+/// The text of the element, or an empty string if it belongs to a definition list or sits inside
+/// a `<script>`, `<style>` or `<noscript>` element.
+pub(crate) fn content_text(element: ElementRef<'_>) -> String {
+    let is_dl = |node: &Node| matches!(node, Node::Element(e) if e.name() == "dl");
 
-        assert!(result.content.contains("Special content"));
-        assert_eq!(result.metadata.get("custom").unwrap(), "Special content");
+    if element.value().name() == "dl" {
+        definition_list_text(element)
+    } else if element.ancestors().any(|node| is_dl(node.value()) || is_non_content(node.value())) {
+        String::new()
+    } else {
+        block_text(element)
     }
+}
 
-    /// Tests the content extraction functionality when no content is found.
-    #[test]
-    fn test_empty_content() {
-        let html = "<html><body></body></html>";
-        let scraper = ContentScraper::default();
-        let result = scraper.extract(html, "https://example.com");
+/// Checks whether a node is a `<script>`, `<style>` or `<noscript>` element.
+///
+/// # Arguments
+///
+/// * `node` - The node to check.
+///
+/// # Returns
+///
+/// `true` if the node is one of the `NON_CONTENT_ELEMENTS`, `false` otherwise.
+fn is_non_content(node: &Node) -> bool {
+    matches!(node, Node::Element(e) if NON_CONTENT_ELEMENTS.contains(&e.name()))
+}
 
-        assert!(result.is_err());
-        matches!(result.unwrap_err(), ScraperError::ExtractionError(_));
+/// Renders a `<dl>` element as one `Term: definition` line per definition.
+///
+/// Consecutive `<dt>` elements share the definitions that follow them and are joined with `, `,
+/// and a term with several `<dd>` elements gets one line per definition. Groups wrapped in a
+/// `<div>`, as HTML allows, are read the same way.
+///
+/// # Arguments
+///
+/// * `element` - The `<dl>` element.
+///
+/// # Returns
+///
+/// The definition lines, one per line.
+fn definition_list_text(element: ElementRef<'_>) -> String {
+    let items = element.children().filter_map(ElementRef::wrap).flat_map(|child| {
+        if child.value().name() == "div" {
+            child.children().filter_map(ElementRef::wrap).collect::<Vec<_>>()
+        } else {
+            vec![child]
+        }
+    });
+
+    let mut lines = Vec::new();
+    let mut terms: Vec<String> = Vec::new();
+    let mut term_defined = false;
+    for item in items {
+        let text = block_text(item).lines().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            continue;
+        }
+
+        match item.value().name() {
+            "dt" => {
+                // A term after a definition starts a new group
+                if term_defined {
+                    terms.clear();
+                    term_defined = false;
+                }
+                terms.push(text);
+            }
+            "dd" => {
+                term_defined = true;
+                if terms.is_empty() {
+                    lines.push(text);
+                } else {
+                    lines.push(format!("{}: {}", terms.join(", "), text));
+                }
+            }
+            _ => {}
+        }
     }
-}
\ No newline at end of file
+
+    lines.join("\n")
+}
+
+/// Converts an element to Markdown: `#` headings, `-` list items indented by nesting level,
+/// `[text](url)` links, `` `code` `` spans and `Term: definition` lines for definition lists.
+/// Other block elements start a new line, and navigation, forms and scripts are left out.
+///
+/// # Arguments
+///
+/// * `element` - The element to convert.
+/// * `base_url` - The URL of the page, which relative links are resolved against.
+///
+/// # Returns
+///
+/// The Markdown, one block per line, with whitespace collapsed and empty lines removed.
+pub(crate) fn markdown_text(element: ElementRef<'_>, base_url: &str) -> String {
+    let mut markdown = String::new();
+    write_markdown(element, base_url, 0, &mut markdown);
+
+    markdown
+        .lines()
+        .map(|line| {
+            let text = collapse_whitespace(line);
+            // Only list items keep their indentation
+            let indent = line.len() - line.trim_start().len();
+            if text.starts_with("- ") && indent > 0 {
+                format!("{}{}", " ".repeat(indent), text)
+            } else {
+                text
+            }
+        })
+        .filter(|line| !line.trim().is_empty() && line.trim() != "-")
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Appends the Markdown of an element's children to `out`.
+///
+/// # Arguments
+///
+/// * `element` - The element whose children are converted.
+/// * `base_url` - The URL of the page, which relative links are resolved against.
+/// * `depth` - The number of lists the element is nested in.
+/// * `out` - The Markdown written so far.
+fn write_markdown(element: ElementRef<'_>, base_url: &str, depth: usize, out: &mut String) {
+    for node in element.children() {
+        if let Node::Text(text) = node.value() {
+            // Keep a single space where the text had surrounding whitespace, so words around
+            // inline elements don't merge, but never indent a line
+            let words = collapse_whitespace(text);
+            let at_line_start = out.is_empty() || out.ends_with('\n');
+            if text.starts_with(char::is_whitespace) && !at_line_start && !out.ends_with(' ') {
+                out.push(' ');
+            }
+            out.push_str(&words);
+            if !words.is_empty() && text.ends_with(char::is_whitespace) {
+                out.push(' ');
+            }
+            continue;
+        }
+        let Some(child) = ElementRef::wrap(node) else {
+            continue;
+        };
+
+        let name = child.value().name();
+        match name {
+            _ if MARKDOWN_SKIPPED.contains(&name) => {}
+            "ul" | "ol" => {
+                start_line(out);
+                write_markdown(child, base_url, depth + 1, out);
+                start_line(out);
+            }
+            "li" => {
+                start_line(out);
+                out.push_str(&format!("{}- ", "  ".repeat(depth.saturating_sub(1))));
+                write_markdown(child, base_url, depth, out);
+                start_line(out);
+            }
+            "dl" => {
+                start_line(out);
+                out.push_str(&definition_list_text(child));
+                start_line(out);
+            }
+            "a" => {
+                let text = inline_text(child);
+                let href = child.value().attr("href").filter(|href| !href.trim_start().starts_with('#'));
+                match href.and_then(|href| urls::resolve_url(base_url, href)) {
+                    Some(target) if !text.is_empty() => out.push_str(&format!("[{}]({})", text, target)),
+                    _ => out.push_str(&text),
+                }
+            }
+            "code" => {
+                let text = inline_text(child);
+                if !text.is_empty() {
+                    out.push_str(&format!("`{}`", text));
+                }
+            }
+            "br" => out.push('\n'),
+            _ => match heading_level(name) {
+                Some(level) => {
+                    let text = inline_text(child);
+                    if !text.is_empty() {
+                        start_line(out);
+                        out.push_str(&format!("{} {}\n", "#".repeat(level), text));
+                    }
+                }
+                None if BLOCK_ELEMENTS.contains(&name) => {
+                    start_line(out);
+                    write_markdown(child, base_url, depth, out);
+                    start_line(out);
+                }
+                None => write_markdown(child, base_url, depth, out),
+            },
+        }
+    }
+}
+
+/// Returns the text of an element on a single line, with whitespace collapsed.
+///
+/// # Arguments
+///
+/// * `element` - The element.
+///
+/// # Returns
+///
+/// The text of the element.
+fn inline_text(element: ElementRef<'_>) -> String {
+    collapse_whitespace(&element.text().collect::<String>())
+}
+
+/// Starts a new line of Markdown, unless the output is already at the start of a line or of a
+/// list item, so a paragraph inside a list item stays on the item's line.
+///
+/// # Arguments
+///
+/// * `out` - The Markdown written so far.
+fn start_line(out: &mut String) {
+    let line = out.rsplit('\n').next().unwrap_or_default();
+    if !line.trim().is_empty() && !line.trim_end().ends_with(" -") && line.trim() != "-" {
+        out.push('\n');
+    }
+}
+
+/// Checks whether an element is boilerplate that never holds the main content of a page: a
+/// navigation or form element, an ARIA landmark such as a banner, or an element whose class or id
+/// marks it as a menu, banner, sidebar and the like without also marking it as content.
+///
+/// # Arguments
+///
+/// * `element` - The element to check.
+///
+/// # Returns
+///
+/// `true` if the element is boilerplate, `false` otherwise.
+fn is_unlikely(element: ElementRef<'_>) -> bool {
+    let value = element.value();
+    if matches!(value.name(), "html" | "body" | "article" | "main") {
+        return false;
+    }
+    if UNLIKELY_ELEMENTS.contains(&value.name()) {
+        return true;
+    }
+    if value.attr("role").is_some_and(|role| UNLIKELY_ROLES.contains(&role.to_lowercase().as_str())) {
+        return true;
+    }
+
+    let hints = class_and_id(element);
+    UNLIKELY_HINTS.iter().any(|hint| hints.contains(hint)) && !LIKELY_HINTS.iter().any(|hint| hints.contains(hint))
+}
+
+/// Returns the starting score of a readability candidate from its class and id: positive for
+/// content hints such as `article` or `story`, negative for boilerplate hints such as `sidebar`.
+///
+/// # Arguments
+///
+/// * `element` - The candidate element.
+///
+/// # Returns
+///
+/// The starting score.
+fn class_weight(element: ElementRef<'_>) -> f32 {
+    let hints = class_and_id(element);
+    let mut weight = 0.0;
+    if LIKELY_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight += 25.0;
+    }
+    if UNLIKELY_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+/// Returns the lowercased class and id of an element, separated by a space.
+///
+/// # Arguments
+///
+/// * `element` - The element.
+///
+/// # Returns
+///
+/// The class and id, or an empty string if the element has neither.
+fn class_and_id(element: ElementRef<'_>) -> String {
+    let value = element.value();
+    format!("{} {}", value.attr("class").unwrap_or_default(), value.id().unwrap_or_default()).to_lowercase()
+}
+
+/// Returns the share of an element's text that is inside links, from 0 (no links) to 1 (only
+/// links). Whitespace isn't counted.
+///
+/// # Arguments
+///
+/// * `element` - The element.
+///
+/// # Returns
+///
+/// The link density, or 0 if the element has no text.
+fn link_density(element: ElementRef<'_>) -> f32 {
+    let text_length = |element: ElementRef<'_>| {
+        element.text().flat_map(str::chars).filter(|c| !c.is_whitespace()).count()
+    };
+
+    let total = text_length(element);
+    if total == 0 {
+        return 0.0;
+    }
+    let linked: usize = element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|descendant| descendant.value().name() == "a")
+        .map(text_length)
+        .sum();
+    linked as f32 / total as f32
+}
+
+/// Drops the lines of block text with fewer than `min_words` words.
+///
+/// Each line of `block_text` output is a separate block, so this removes short fragments such as
+/// navigation links while keeping the surrounding paragraphs.
+///
+/// # Arguments
+///
+/// * `text` - The block text, with one block per line.
+/// * `min_words` - The minimum word count, or 0 to keep every line.
+///
+/// # Returns
+///
+/// The text with the short lines removed.
+pub(crate) fn drop_short_blocks(text: &str, min_words: usize) -> String {
+    if min_words == 0 {
+        return text.to_string();
+    }
+
+    text.lines()
+        .filter(|line| line.split_whitespace().count() >= min_words)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extracts question and answer pairs from a document's FAQ sections.
+///
+/// Both schema.org `FAQPage` JSON-LD data and `<details>`/`<summary>` disclosure widgets are
+/// recognized. Questions found in both are only included once.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+///
+/// # Returns
+///
+/// A vector of the FAQ entries found, in document order.
+pub(crate) fn extract_faq(document: &Html) -> Vec<FaqEntry> {
+    let mut entries: Vec<FaqEntry> = Vec::new();
+    let mut push = |question: String, answer: String| {
+        if !question.is_empty() && !answer.is_empty() && !entries.iter().any(|e| e.question == question) {
+            entries.push(FaqEntry { question, answer });
+        }
+    };
+
+    if let Ok(selector) = Selector::parse("script[type='application/ld+json']") {
+        for script in document.select(&selector) {
+            let json = script.text().collect::<String>();
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&json) {
+                for (question, answer) in json_ld_faq(&value) {
+                    push(question, answer);
+                }
+            }
+        }
+    }
+
+    if let (Ok(details), Ok(summary)) = (Selector::parse("details"), Selector::parse("summary")) {
+        for element in document.select(&details) {
+            let Some(question) = element.select(&summary).next() else {
+                continue;
+            };
+            let answer = element
+                .descendants()
+                .filter(|node| !node.ancestors().any(|ancestor| ancestor.id() == question.id()))
+                .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            push(collapse_whitespace(&question.text().collect::<String>()), collapse_whitespace(&answer));
+        }
+    }
+
+    entries
+}
+
+/// Collects the question and answer pairs of every `FAQPage` in a JSON-LD value.
+///
+/// # Arguments
+///
+/// * `value` - The parsed JSON-LD value, which may be a single object, an array or an `@graph`.
+///
+/// # Returns
+///
+/// A vector of question and answer pairs, with HTML stripped from the answers.
+fn json_ld_faq(value: &serde_json::Value) -> Vec<(String, String)> {
+    let has_type = |value: &serde_json::Value, name: &str| json_ld_has_type(value, &[name]);
+    let as_list = |value: &serde_json::Value| match value {
+        serde_json::Value::Array(items) => items.clone(),
+        serde_json::Value::Null => Vec::new(),
+        item => vec![item.clone()],
+    };
+
+    if let Some(items) = value.as_array().or_else(|| value["@graph"].as_array()) {
+        return items.iter().flat_map(json_ld_faq).collect();
+    }
+    if !has_type(value, "FAQPage") {
+        return Vec::new();
+    }
+
+    as_list(&value["mainEntity"])
+        .iter()
+        .filter(|question| has_type(question, "Question"))
+        .filter_map(|question| {
+            let name = question["name"].as_str()?;
+            let answer = as_list(&question["acceptedAnswer"])
+                .first()
+                .and_then(|answer| answer["text"].as_str().map(str::to_string))?;
+            let answer = Html::parse_fragment(&answer).root_element().text().collect::<String>();
+            Some((collapse_whitespace(name), collapse_whitespace(&answer)))
+        })
+        .collect()
+}
+
+/// Checks whether a JSON-LD object has one of the given schema.org types.
+///
+/// # Arguments
+///
+/// * `value` - The JSON-LD object, whose `@type` may be a single type or a list of them.
+/// * `names` - The types to look for.
+///
+/// # Returns
+///
+/// `true` if any of the object's types is one of `names`.
+fn json_ld_has_type(value: &serde_json::Value, names: &[&str]) -> bool {
+    match &value["@type"] {
+        serde_json::Value::String(t) => names.contains(&t.as_str()),
+        serde_json::Value::Array(types) => types.iter().filter_map(|t| t.as_str()).any(|t| names.contains(&t)),
+        _ => false,
+    }
+}
+
+/// Extracts the structured metadata a page declares for sharing and search engines: the Open
+/// Graph `og:title`, `og:description`, `og:site_name` and `og:type` properties, and the
+/// `headline`, `author` and `datePublished` of the first JSON-LD article, stored as `title`,
+/// `author` and `date`. The JSON-LD fields take precedence over the Open Graph ones.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+///
+/// # Returns
+///
+/// A `HashMap` containing the metadata found, keyed like the selector-based metadata.
+pub(crate) fn extract_structured_metadata(document: &Html) -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+
+    for (property, key) in OPEN_GRAPH_PROPERTIES {
+        let Ok(selector) = Selector::parse(&format!("meta[property='{property}']")) else {
+            continue;
+        };
+        let value = document
+            .select(&selector)
+            .filter_map(|element| element.value().attr("content"))
+            .map(collapse_whitespace)
+            .find(|value| !value.is_empty());
+        if let Some(value) = value {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+
+    if let Ok(selector) = Selector::parse("script[type='application/ld+json']") {
+        let article = document.select(&selector).find_map(|script| {
+            let json = script.text().collect::<String>();
+            serde_json::from_str::<serde_json::Value>(&json)
+                .ok()
+                .and_then(|value| json_ld_article(&value))
+        });
+        metadata.extend(article.into_iter().flatten());
+    }
+
+    metadata
+}
+
+/// Collects the headline, author and publication date of the first article in a JSON-LD value.
+///
+/// # Arguments
+///
+/// * `value` - The parsed JSON-LD value, which may be a single object, an array or an `@graph`.
+///
+/// # Returns
+///
+/// An `Option` containing the metadata keys and values the article has, or `None` if the value
+/// has no article.
+fn json_ld_article(value: &serde_json::Value) -> Option<Vec<(String, String)>> {
+    if let Some(items) = value.as_array().or_else(|| value["@graph"].as_array()) {
+        return items.iter().find_map(json_ld_article);
+    }
+    if !json_ld_has_type(value, &JSON_LD_ARTICLE_TYPES) {
+        return None;
+    }
+
+    let name = |value: &serde_json::Value| value.as_str().or_else(|| value["name"].as_str()).map(collapse_whitespace);
+    let author = match &value["author"] {
+        serde_json::Value::Array(authors) => {
+            let names: Vec<String> = authors.iter().filter_map(name).filter(|name| !name.is_empty()).collect();
+            (!names.is_empty()).then(|| names.join(", "))
+        }
+        author => name(author),
+    };
+
+    let fields = [
+        ("title", value["headline"].as_str().map(collapse_whitespace)),
+        ("author", author),
+        ("date", value["datePublished"].as_str().map(collapse_whitespace)),
+    ];
+    Some(
+        fields
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value.filter(|value| !value.is_empty())?)))
+            .collect(),
+    )
+}
+
+/// Extracts the breadcrumb trail showing where a page sits in its site's hierarchy.
+///
+/// A schema.org `BreadcrumbList` in JSON-LD is preferred, ordered by item position. Otherwise the
+/// items of a `<nav>` whose `aria-label` mentions breadcrumbs are used.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+///
+/// # Returns
+///
+/// An `Option` containing the trail joined with ` > ` (e.g. `Docs > API > Authentication`), or
+/// `None` if the page has no breadcrumbs.
+pub(crate) fn extract_breadcrumb(document: &Html) -> Option<String> {
+    let join = |names: Vec<String>| {
+        let names: Vec<String> = names.into_iter().filter(|name| !name.is_empty()).collect();
+        (!names.is_empty()).then(|| names.join(" > "))
+    };
+
+    if let Ok(selector) = Selector::parse("script[type='application/ld+json']") {
+        for script in document.select(&selector) {
+            let json = script.text().collect::<String>();
+            if let Some(trail) = serde_json::from_str::<serde_json::Value>(&json)
+                .ok()
+                .and_then(|value| json_ld_breadcrumb(&value))
+                .and_then(join)
+            {
+                return Some(trail);
+            }
+        }
+    }
+
+    let (Ok(nav), Ok(items), Ok(links)) = (Selector::parse("nav[aria-label]"), Selector::parse("li"), Selector::parse("a")) else {
+        return None;
+    };
+    document
+        .select(&nav)
+        .filter(|element| {
+            element
+                .value()
+                .attr("aria-label")
+                .is_some_and(|label| label.to_lowercase().contains("breadcrumb"))
+        })
+        .find_map(|element| {
+            let mut names: Vec<String> = element
+                .select(&items)
+                .map(|item| collapse_whitespace(&item.text().collect::<String>()))
+                .collect();
+            if names.is_empty() {
+                names = element
+                    .select(&links)
+                    .map(|link| collapse_whitespace(&link.text().collect::<String>()))
+                    .collect();
+            }
+            join(names)
+        })
+}
+
+/// Collects the item names of the first `BreadcrumbList` in a JSON-LD value, ordered by position.
+///
+/// # Arguments
+///
+/// * `value` - The parsed JSON-LD value, which may be a single object, an array or an `@graph`.
+///
+/// # Returns
+///
+/// An `Option` containing the item names, or `None` if the value has no breadcrumb list.
+fn json_ld_breadcrumb(value: &serde_json::Value) -> Option<Vec<String>> {
+    if let Some(items) = value.as_array().or_else(|| value["@graph"].as_array()) {
+        return items.iter().find_map(json_ld_breadcrumb);
+    }
+    if value["@type"] != "BreadcrumbList" {
+        return None;
+    }
+
+    let mut items: Vec<(u64, String)> = value["itemListElement"]
+        .as_array()?
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let name = item["name"].as_str().or_else(|| item["item"]["name"].as_str())?;
+            let position = item["position"].as_u64().unwrap_or(index as u64 + 1);
+            Some((position, collapse_whitespace(name)))
+        })
+        .collect();
+    items.sort_by_key(|(position, _)| *position);
+
+    Some(items.into_iter().map(|(_, name)| name).collect())
+}
+
+/// Finds the publication date of a page in its metadata: the Open Graph `article:published_time`
+/// tag, other common `<meta>` date tags, a `<time datetime>` element, or the `datePublished` of a
+/// JSON-LD article, in that order.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+///
+/// # Returns
+///
+/// An `Option` containing the first date that parses, or `None` if none does.
+pub(crate) fn published_date(document: &Html) -> Option<DateTime<Utc>> {
+    PUBLISHED_DATE_SOURCES.iter().find_map(|(selector, attribute)| {
+        let selector = Selector::parse(selector).ok()?;
+        document
+            .select(&selector)
+            .filter_map(|element| element.value().attr(attribute))
+            .find_map(parse_date)
+    })
+    .or_else(|| extract_structured_metadata(document).get("date").map(String::as_str).and_then(parse_date))
+}
+
+/// Parses a date in one of the formats found in page metadata: RFC 3339 / ISO 8601 with or
+/// without a time zone, RFC 2822, or a plain date such as `2024-03-15` or `March 15, 2024`.
+/// Dates without a time zone are taken as UTC, and dates without a time as midnight.
+///
+/// # Arguments
+///
+/// * `value` - The date text.
+///
+/// # Returns
+///
+/// An `Option` containing the date in UTC, or `None` if it doesn't parse.
+pub(crate) fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    let with_zone = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z"));
+    if let Ok(date) = with_zone {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    NAIVE_DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|date| date.and_utc())
+}
+
+/// Collapses all runs of whitespace into single spaces and trims the result.
+///
+/// # Arguments
+///
+/// * `text` - The text to collapse.
+///
+/// # Returns
+///
+/// The collapsed text.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Records the word count and estimated reading time of the content in its metadata, as
+/// `word_count` and `reading_time_minutes` (rounded up, at least one minute).
+///
+/// # Arguments
+///
+/// * `metadata` - The metadata map to add the entries to.
+/// * `content` - The cleaned content text.
+pub(crate) fn insert_reading_stats(metadata: &mut HashMap<String, String>, content: &str) {
+    let words = content.split_whitespace().count();
+    let minutes = words.div_ceil(WORDS_PER_MINUTE).max(1);
+    metadata.insert("word_count".to_string(), words.to_string());
+    metadata.insert("reading_time_minutes".to_string(), minutes.to_string());
+}
+
+/// Appends the image captions of a document to its extracted content, one `[image: ...]` line
+/// per caption.
+///
+/// # Arguments
+///
+/// * `content` - The extracted content to append to.
+/// * `document` - The parsed HTML document.
+pub(crate) fn append_image_captions(content: &mut String, document: &Html) {
+    for caption in image_captions(document) {
+        content.push_str(&format!("\n[image: {}]", caption));
+    }
+}
+
+/// Collects the alt text of images and the text of figure captions in document order.
+///
+/// An image's alt text is skipped when it repeats the caption of its figure, so each piece of
+/// text appears once.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+///
+/// # Returns
+///
+/// A vector of the non-empty captions found.
+fn image_captions(document: &Html) -> Vec<String> {
+    let Ok(selector) = Selector::parse("img[alt], figcaption") else {
+        return Vec::new();
+    };
+
+    let mut captions: Vec<String> = Vec::new();
+    for element in document.select(&selector) {
+        let text = match element.value().attr("alt") {
+            Some(alt) if element.value().name() == "img" => alt.to_string(),
+            _ => element.text().collect::<Vec<_>>().join(" "),
+        };
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        if !text.is_empty() && !captions.contains(&text) {
+            captions.push(text);
+        }
+    }
+
+    captions
+}
+
+/// Returns the level of a heading element (1 for `h1` through 6 for `h6`).
+///
+/// # Arguments
+///
+/// * `name` - The element name.
+///
+/// # Returns
+///
+/// An `Option` containing the heading level, or `None` if the element is not a heading.
+fn heading_level(name: &str) -> Option<usize> {
+    match name {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// Lowercases text and reduces it to space-separated alphanumeric words for fuzzy comparison.
+///
+/// # Arguments
+///
+/// * `text` - The text to normalize.
+///
+/// # Returns
+///
+/// The normalized text.
+fn normalize_heading(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Finds the heading that best matches the given text or anchor.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+/// * `query` - The heading text or anchor to look for.
+///
+/// # Returns
+///
+/// An `Option` containing the best matching heading, or `None` if nothing matches.
+fn find_heading<'a>(document: &'a Html, query: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse("h1, h2, h3, h4, h5, h6").ok()?;
+    let anchor = query.trim().trim_start_matches('#');
+    let wanted = normalize_heading(query);
+    if wanted.is_empty() {
+        return None;
+    }
+    let wanted_words: Vec<&str> = wanted.split(' ').collect();
+
+    let mut best: Option<(u8, ElementRef<'a>)> = None;
+
+    for heading in document.select(&selector) {
+        let text = normalize_heading(&heading.text().collect::<Vec<_>>().join(" "));
+
+        let score = if heading.value().id() == Some(anchor) || text == wanted {
+            4
+        } else if text.contains(&wanted) {
+            3
+        } else if wanted.contains(&text) && !text.is_empty() {
+            2
+        } else {
+            let text_words: Vec<&str> = text.split(' ').collect();
+            let matched = wanted_words.iter().filter(|w| text_words.contains(w)).count();
+            if matched * 2 >= wanted_words.len() && matched > 0 {
+                1
+            } else {
+                0
+            }
+        };
+
+        if score > 0 && best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, heading));
+        }
+    }
+
+    best.map(|(_, heading)| heading)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the content extraction functionality of the `ContentScraper`.
+    #[test]
+    fn test_content_extraction() {
+        let html = r#"
+            <!DOCTYPE html>
+            <html>
+                <head>
+                    <title>Test Page</title>
+                    <meta name="description" content="Test description">
+                    <meta name="author" content="Test Author">
+                </head>
+                <body>
+                    <article>
+                        <h1>Test Article</h1>
+                        <p>This is a test paragraph.</p>
+                        <p>This is another paragraph.</p>
+                    </article>
+                </body>
+            </html>
+        "#;
+
+        let scraper = ContentScraper::default();
+        let result = scraper.extract(html, "https://example.com").unwrap();
+
+        assert!(result.content.contains("This is a test paragraph"));
+        assert!(result.content.contains("This is another paragraph"));
+        assert_eq!(result.metadata.get("title").unwrap(), "Test Page");
+        assert_eq!(result.metadata.get("description").unwrap(), "Test description");
+        assert_eq!(result.metadata.get("author").unwrap(), "Test Author");
+    }
+
+    /// Tests the content extraction functionality with custom selectors.
+    #[test]
+    fn test_custom_selectors() {
+        let html = r#"
+            <div class="custom-content">
+                <span class="special">Special content</span>
+            </div>
+        "#;
+
+        let scraper = ContentScraper::new(
+            vec![".custom-content .special"],
+            vec![("custom", ".special")],
+        );
+
+        let result = scraper.extract(html, "https://example.com").unwrap();
+
+        assert!(result.content.contains("Special content"));
+        assert_eq!(result.metadata.get("custom").unwrap(), "Special content");
+    }
+
+    const SECTIONED_HTML: &str = r#"
+        <html>
+            <body>
+                <h1>Tool Documentation</h1>
+                <p>Welcome to the docs.</p>
+                <h2 id="installation">Installation</h2>
+                <p>Run cargo install tool to install it.</p>
+                <h3>From source</h3>
+                <p>Clone the repository and build it.</p>
+                <h2 id="usage">Usage</h2>
+                <p>Run tool with a query.</p>
+            </body>
+        </html>
+    "#;
+
+    /// Tests extracting a section by heading text, including its subsections.
+    #[test]
+    fn test_extract_section() {
+        let scraper = ContentScraper::default();
+        let result = scraper
+            .extract_section(SECTIONED_HTML, "https://example.com", "INSTALLATION")
+            .unwrap();
+
+        assert!(result.content.contains("Run cargo install tool"));
+        assert!(result.content.contains("Clone the repository"));
+        assert!(!result.content.contains("Welcome to the docs"));
+        assert!(!result.content.contains("Run tool with a query"));
+        assert_eq!(result.metadata.get("section").unwrap(), "Installation");
+    }
+
+    /// Tests fuzzy and anchor-based section matching.
+    #[test]
+    fn test_extract_section_fuzzy_and_anchor() {
+        let scraper = ContentScraper::default();
+
+        let fuzzy = scraper
+            .extract_section(SECTIONED_HTML, "https://example.com", "how to install from source")
+            .unwrap();
+        assert!(fuzzy.content.contains("Clone the repository"));
+
+        let anchor = scraper
+            .extract_section(SECTIONED_HTML, "https://example.com", "#usage")
+            .unwrap();
+        assert!(anchor.content.contains("Run tool with a query"));
+
+        let missing = scraper.extract_section(SECTIONED_HTML, "https://example.com", "changelog");
+        assert!(matches!(missing, Err(ScraperError::ExtractionError(_))));
+    }
+
+    /// Tests the content extraction functionality when no content is found.
+    #[test]
+    fn test_empty_content() {
+        let html = "<html><body></body></html>";
+        let scraper = ContentScraper::default();
+        let result = scraper.extract(html, "https://example.com");
+
+        assert!(result.is_err());
+        matches!(result.unwrap_err(), ScraperError::ExtractionError(_));
+    }
+
+    /// Tests that image alt text and figure captions are appended only when enabled.
+    #[test]
+    fn test_image_captions() {
+        let html = r#"
+            <html>
+                <body>
+                    <article>
+                        <p>Whisk the eggs and sugar together.</p>
+                        <figure>
+                            <img src="batter.jpg" alt="Pale, fluffy cake batter">
+                            <figcaption>The batter should fall in ribbons.</figcaption>
+                        </figure>
+                        <img src="spacer.gif" alt="">
+                        <figure>
+                            <img src="oven.jpg" alt="Oven at 180C">
+                            <figcaption>Oven at 180C</figcaption>
+                        </figure>
+                    </article>
+                </body>
+            </html>
+        "#;
+
+        let plain = ContentScraper::default()
+            .extract(html, "https://example.com")
+            .unwrap();
+        assert!(!plain.content.contains("[image:"));
+
+        let result = ContentScraper::default()
+            .with_image_captions(true)
+            .extract(html, "https://example.com")
+            .unwrap();
+
+        assert!(result.content.starts_with("Whisk the eggs and sugar together."));
+        assert!(result.content.contains("\n[image: Pale, fluffy cake batter]\n"));
+        assert!(result.content.contains("[image: The batter should fall in ribbons.]"));
+        assert_eq!(result.content.matches("[image: Oven at 180C]").count(), 1);
+        assert!(!result.content.contains("[image: ]"));
+    }
+
+    /// Tests that text from adjacent block elements is separated instead of merged.
+    #[test]
+    fn test_block_elements_separated() {
+        let html = r#"<html><body><div class="documentation-content"><p>First sentence.</p><p>Second sentence</p><div>Third</div>trailing <b>bold</b>ness<br>After break</div></body></html>"#;
+
+        let scraper = ContentScraper::default();
+        let result = scraper.extract(html, "https://example.com").unwrap();
+
+        assert_eq!(
+            result.content,
+            "First sentence.\nSecond sentence\nThird\ntrailing boldness\nAfter break"
+        );
+        assert!(!result.content.contains("sentence.Second"));
+    }
+
+    /// Tests that non-ASCII text survives cleaning while control characters are stripped.
+    #[test]
+    fn test_unicode_preserved() {
+        let scraper = ContentScraper::default();
+
+        let cleaned = scraper.clean_text("The  café serves naïve\u{0007} travellers —\n\n日本語のテキスト 🦀\u{0000}");
+        assert_eq!(cleaned, "The café serves naïve travellers —\n日本語のテキスト 🦀");
+
+        let html = "<html><body><article><p>Crème brûlée at the café, naïve but good.</p><p>東京の天気は晴れです。</p></article></body></html>";
+        let result = scraper.extract(html, "https://example.com").unwrap();
+        assert!(result.content.contains("café"));
+        assert!(result.content.contains("naïve"));
+        assert!(result.content.contains("東京の天気は晴れです。"));
+    }
+
+    /// Tests extracting FAQ entries from `FAQPage` JSON-LD and `<details>` elements.
+    #[test]
+    fn test_faq_extraction() {
+        let html = r#"
+            <html>
+                <head>
+                    <script type="application/ld+json">
+                    {
+                        "@context": "https://schema.org",
+                        "@type": "FAQPage",
+                        "mainEntity": [
+                            {
+                                "@type": "Question",
+                                "name": "How long does shipping take?",
+                                "acceptedAnswer": { "@type": "Answer", "text": "<p>Usually <b>3-5</b> business days.</p>" }
+                            },
+                            {
+                                "@type": "Question",
+                                "name": "Can I return items?",
+                                "acceptedAnswer": { "@type": "Answer", "text": "Yes, within 30 days." }
+                            }
+                        ]
+                    }
+                    </script>
+                </head>
+                <body>
+                    <p>Our store ships worldwide.</p>
+                    <details>
+                        <summary>Do you ship abroad?</summary>
+                        <p>Yes, to over 40 countries.</p>
+                    </details>
+                    <details>
+                        <summary>Can I return items?</summary>
+                        <p>Duplicate of the JSON-LD question.</p>
+                    </details>
+                </body>
+            </html>
+        "#;
+
+        let result = ContentScraper::default().extract(html, "https://example.com").unwrap();
+
+        assert_eq!(
+            result.faq,
+            vec![
+                FaqEntry {
+                    question: "How long does shipping take?".to_string(),
+                    answer: "Usually 3-5 business days.".to_string(),
+                },
+                FaqEntry {
+                    question: "Can I return items?".to_string(),
+                    answer: "Yes, within 30 days.".to_string(),
+                },
+                FaqEntry {
+                    question: "Do you ship abroad?".to_string(),
+                    answer: "Yes, to over 40 countries.".to_string(),
+                },
+            ]
+        );
+    }
+
+    /// Tests extracting the breadcrumb trail from `BreadcrumbList` JSON-LD and breadcrumb navs.
+    #[test]
+    fn test_breadcrumb_extraction() {
+        let html = r#"
+            <html>
+                <head>
+                    <script type="application/ld+json">
+                    {
+                        "@context": "https://schema.org",
+                        "@graph": [
+                            { "@type": "WebPage", "name": "Authentication" },
+                            {
+                                "@type": "BreadcrumbList",
+                                "itemListElement": [
+                                    { "@type": "ListItem", "position": 3, "name": "Authentication" },
+                                    { "@type": "ListItem", "position": 1, "name": "Docs", "item": "https://example.com/docs" },
+                                    { "@type": "ListItem", "position": 2, "item": { "@id": "https://example.com/docs/api", "name": "API" } }
+                                ]
+                            }
+                        ]
+                    }
+                    </script>
+                </head>
+                <body><article><p>Use a bearer token to authenticate requests.</p></article></body>
+            </html>
+        "#;
+
+        let result = ContentScraper::default().extract(html, "https://example.com/docs/api/auth").unwrap();
+        assert_eq!(
+            result.metadata.get("breadcrumb").map(String::as_str),
+            Some("Docs > API > Authentication")
+        );
+
+        let html = r#"
+            <html><body>
+                <nav aria-label="Breadcrumb"><ol><li><a href="/">Home</a></li><li><a href="/blog">Blog</a></li><li>Post</li></ol></nav>
+                <article><p>Post content.</p></article>
+            </body></html>
+        "#;
+        let result = ContentScraper::default().extract(html, "https://example.com/blog/post").unwrap();
+        assert_eq!(result.metadata.get("breadcrumb").map(String::as_str), Some("Home > Blog > Post"));
+
+        let html = "<html><body><article><p>No trail here.</p></article></body></html>";
+        let result = ContentScraper::default().extract(html, "https://example.com").unwrap();
+        assert!(!result.metadata.contains_key("breadcrumb"));
+    }
+
+    /// Tests that definition lists are extracted as `Term: definition` pairs.
+    #[test]
+    fn test_definition_lists() {
+        let html = r#"
+            <html>
+                <body>
+                    <article>
+                        <p>A glossary of Rust terms.</p>
+                        <dl>
+                            <dt>Crate</dt>
+                            <dd>A compilation unit in Rust.</dd>
+                            <dt>Trait</dt>
+                            <dt>Interface</dt>
+                            <dd><p>A set of methods a type <em>can</em> implement.</p></dd>
+                            <div>
+                                <dt>Lifetime</dt>
+                                <dd>How long a reference is valid.</dd>
+                                <dd>Written as <code>'a</code>.</dd>
+                            </div>
+                        </dl>
+                    </article>
+                </body>
+            </html>
+        "#;
+
+        let result = ContentScraper::default().extract(html, "https://example.com").unwrap();
+        assert_eq!(
+            result.content,
+            "A glossary of Rust terms.\n\
+             Crate: A compilation unit in Rust.\n\
+             Trait, Interface: A set of methods a type can implement.\n\
+             Lifetime: How long a reference is valid.\n\
+             Lifetime: Written as 'a."
+        );
+    }
+
+    /// Tests that headings, nested lists, links and code survive Markdown extraction.
+    #[test]
+    fn test_extract_markdown() {
+        let html = r##"
+            <html>
+                <head><title>Async Rust</title></head>
+                <body>
+                    <nav><a href="/">Home</a></nav>
+                    <article>
+                        <header><h1>Async  Rust</h1></header>
+                        <p>Futures are <em>lazy</em>; see the <a href="/book/async.html">async book</a>
+                           and <a href="#runtimes">below</a>.</p>
+                        <h2 id="runtimes">Runtimes</h2>
+                        <ul>
+                            <li>Tokio
+                                <ul>
+                                    <li><p>Multi-threaded by default</p></li>
+                                    <li>Call <code>tokio::spawn</code> to start a task</li>
+                                </ul>
+                            </li>
+                            <li><a href="https://async.rs/">async-std</a></li>
+                        </ul>
+                        <dl><dt>Waker</dt><dd>Wakes a pending task.</dd></dl>
+                        <script>trackPageView();</script>
+                    </article>
+                </body>
+            </html>
+        "##;
+
+        let result = ContentScraper::default()
+            .extract_markdown(html, "https://rust-lang.org/learn/async")
+            .unwrap();
+        assert_eq!(
+            result.content,
+            "# Async Rust\n\
+             Futures are lazy; see the [async book](https://rust-lang.org/book/async.html) and below.\n\
+             ## Runtimes\n\
+             - Tokio\n\
+             \x20 - Multi-threaded by default\n\
+             \x20 - Call `tokio::spawn` to start a task\n\
+             - [async-std](https://async.rs/)\n\
+             Waker: Wakes a pending task."
+        );
+        assert_eq!(result.metadata.get("title").unwrap(), "Async Rust");
+
+        let plain = ContentScraper::default().extract(html, "https://rust-lang.org/learn/async").unwrap();
+        assert!(!plain.content.contains('#'));
+        assert!(ContentScraper::default().extract_markdown("<html><body></body></html>", "https://example.com").is_err());
+    }
+
+    /// Tests that the readability extractor keeps the story of a news page without an `<article>`
+    /// and leaves out its cookie banner, menu, sidebar and footer.
+    #[test]
+    fn test_extract_readable() {
+        let html = r#"
+            <html>
+                <head><title>City council approves new bike lanes</title></head>
+                <body>
+                    <div class="cookie-banner">
+                        <p>We use cookies to improve your experience. Accept cookies to continue.</p>
+                        <button>Accept</button>
+                    </div>
+                    <div id="top-nav">
+                        <ul>
+                            <li><a href="/">Home</a></li>
+                            <li><a href="/sports">Sports</a></li>
+                            <li><a href="/weather">Weather</a></li>
+                        </ul>
+                    </div>
+                    <div class="layout">
+                        <div class="story-body">
+                            <h1>City council approves new bike lanes</h1>
+                            <p>The city council voted 7 to 2 on Tuesday to approve twelve miles of protected
+                               bike lanes, the largest expansion in the city's history.</p>
+                            <p>Construction will begin in the spring, starting downtown, and is expected to
+                               take two years, according to the transportation department.</p>
+                            <p>Supporters, including local businesses and cycling groups, packed the meeting;
+                               opponents raised concerns about parking.</p>
+                            <p>Read more: <a href="/news/transit">Transit plan</a></p>
+                        </div>
+                        <div class="sidebar">
+                            <h3>Related stories</h3>
+                            <ul>
+                                <li><a href="/a">Council debates the budget for the next fiscal year</a></li>
+                                <li><a href="/b">A new park opens on the waterfront this weekend</a></li>
+                            </ul>
+                        </div>
+                    </div>
+                    <footer>
+                        <p>Copyright 2024 Daily Planet. All rights reserved. Contact us, advertise, or subscribe.</p>
+                    </footer>
+                </body>
+            </html>
+        "#;
+
+        let result = ContentScraper::default().extract_readable(html, "https://example.com/news").unwrap();
+        assert!(result.content.starts_with("The city council voted 7 to 2"), "{}", result.content);
+        assert!(result.content.contains("according to the transportation department."));
+        assert!(result.content.ends_with("opponents raised concerns about parking."));
+        for boilerplate in ["cookies", "Sports", "Related stories", "Council debates", "Transit plan", "Copyright"] {
+            assert!(!result.content.contains(boilerplate), "kept {}", boilerplate);
+        }
+        assert_eq!(result.metadata.get("title").unwrap(), "City council approves new bike lanes");
+
+        // The selector cascade falls back to every paragraph and list item on the page
+        let selected = ContentScraper::default().extract(html, "https://example.com/news").unwrap();
+        assert!(selected.content.contains("Accept cookies") && selected.content.contains("Copyright"));
+
+        let menu_only = "<html><body><nav><p>Only a menu here, with nothing else to read.</p></nav></body></html>";
+        assert!(ContentScraper::default().extract_readable(menu_only, "https://example.com").is_err());
+    }
+
+    /// Tests that the text of scripts, styles and noscript fallbacks never reaches the content.
+    #[test]
+    fn test_script_text_stripped() {
+        let html = r#"
+            <html>
+                <body>
+                    <article>
+                        <p>Rust 1.80 was released today. <script>document.write("Subscribe for Rust news");</script>It stabilizes lazy cells.</p>
+                        <script type="application/json">{"headline": "Tracking payload with plenty of text"}</script>
+                        <style>.ad { content: "Advertisement styles"; }</style>
+                    </article>
+                    <div class="markdown-body">
+                        <p>Install it with rustup.</p>
+                        <script>window.analytics = "Analytics code that should never be summarized";</script>
+                        <noscript>Please enable JavaScript to view the comments.</noscript>
+                    </div>
+                </body>
+            </html>
+        "#;
+
+        let result = ContentScraper::default().extract(html, "https://example.com").unwrap();
+        assert_eq!(result.content, "Rust 1.80 was released today. It stabilizes lazy cells.");
+
+        let scraper = ContentScraper::new(["div.markdown-body"], Vec::<(String, String)>::new());
+        let result = scraper.extract(html, "https://example.com").unwrap();
+        assert_eq!(result.content, "Install it with rustup.");
+    }
+
+    /// Tests parsing the date formats found in page metadata.
+    #[test]
+    fn test_parse_date() {
+        let expected = |value: &str| DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc);
+
+        assert_eq!(parse_date("2024-03-15T10:30:00+02:00"), Some(expected("2024-03-15T08:30:00Z")));
+        assert_eq!(parse_date("2024-03-15T10:30:00.250Z"), Some(expected("2024-03-15T10:30:00.250Z")));
+        assert_eq!(parse_date("2024-03-15T10:30:00+0200"), Some(expected("2024-03-15T08:30:00Z")));
+        assert_eq!(parse_date("Fri, 15 Mar 2024 10:30:00 GMT"), Some(expected("2024-03-15T10:30:00Z")));
+        assert_eq!(parse_date("2024-03-15T10:30"), Some(expected("2024-03-15T10:30:00Z")));
+        assert_eq!(parse_date("2024-03-15 10:30:00"), Some(expected("2024-03-15T10:30:00Z")));
+        assert_eq!(parse_date(" 2024-03-15 "), Some(expected("2024-03-15T00:00:00Z")));
+        assert_eq!(parse_date("2024/03/15"), Some(expected("2024-03-15T00:00:00Z")));
+        assert_eq!(parse_date("March 15, 2024"), Some(expected("2024-03-15T00:00:00Z")));
+        assert_eq!(parse_date("15 March 2024"), Some(expected("2024-03-15T00:00:00Z")));
+        assert_eq!(parse_date("last Tuesday"), None);
+        assert_eq!(parse_date(""), None);
+    }
+
+    /// Tests that the publication date comes from the page metadata before the URL.
+    #[test]
+    fn test_published_date() {
+        let html = |head: &str, body: &str| {
+            format!("<html><head>{head}</head><body><article><p>Rust 1.80 was released with lazy cells.</p>{body}</article></body></html>")
+        };
+        let scraper = ContentScraper::default();
+        let url = "https://example.com/2020/01/02/rust-release";
+        let published = |html: &str| scraper.extract(html, url).unwrap().published.map(|date| date.to_rfc3339());
+
+        let open_graph = html(
+            r#"<meta property="article:published_time" content="2024-07-25T14:00:00+00:00">"#,
+            r#"<time datetime="2024-07-20">July 20</time>"#,
+        );
+        assert_eq!(published(&open_graph).as_deref(), Some("2024-07-25T14:00:00+00:00"));
+
+        let invalid_meta = html(
+            r#"<meta name="date" content="sometime in July">"#,
+            r#"<time datetime="2024-07-20">July 20</time>"#,
+        );
+        assert_eq!(published(&invalid_meta).as_deref(), Some("2024-07-20T00:00:00+00:00"));
+
+        let no_metadata = html("", "");
+        assert_eq!(published(&no_metadata).as_deref(), Some("2020-01-02T00:00:00+00:00"));
+
+        assert!(scraper.extract(&no_metadata, "https://example.com/rust-release").unwrap().published.is_none());
+    }
+
+    /// Tests that Open Graph and JSON-LD metadata take precedence over the metadata selectors.
+    #[test]
+    fn test_structured_metadata() {
+        let html = r#"
+            <html>
+                <head>
+                    <title>Rust 1.80 | Example News</title>
+                    <meta name="description" content="Plain description">
+                    <meta name="author" content="Meta Author">
+                    <meta property="og:title" content="Open Graph title">
+                    <meta property="og:description" content="Rust 1.80 stabilizes   lazy cells.">
+                    <meta property="og:site_name" content="Example News">
+                    <meta property="og:type" content="article">
+                    <script type="application/ld+json">
+                        {
+                            "@context": "https://schema.org",
+                            "@graph": [
+                                { "@type": "WebSite", "name": "Example News", "headline": "Not an article" },
+                                {
+                                    "@type": ["NewsArticle"],
+                                    "headline": "Announcing Rust 1.80",
+                                    "author": [{ "@type": "Person", "name": "Jane Doe" }, "John Roe"],
+                                    "datePublished": "2024-07-25T14:00:00Z"
+                                },
+                                { "@type": "BlogPosting", "headline": "A later article" }
+                            ]
+                        }
+                    </script>
+                </head>
+                <body><article><p>Rust 1.80 was released with lazy cells.</p></article></body>
+            </html>
+        "#;
+
+        let result = ContentScraper::default().extract(html, "https://example.com/rust-release").unwrap();
+        assert_eq!(result.metadata.get("title").map(String::as_str), Some("Announcing Rust 1.80"));
+        assert_eq!(result.metadata.get("description").map(String::as_str), Some("Rust 1.80 stabilizes lazy cells."));
+        assert_eq!(result.metadata.get("site_name").map(String::as_str), Some("Example News"));
+        assert_eq!(result.metadata.get("type").map(String::as_str), Some("article"));
+        assert_eq!(result.metadata.get("author").map(String::as_str), Some("Jane Doe, John Roe"));
+        assert_eq!(result.metadata.get("date").map(String::as_str), Some("2024-07-25T14:00:00Z"));
+        assert_eq!(result.published.map(|date| date.to_rfc3339()).as_deref(), Some("2024-07-25T14:00:00+00:00"));
+
+        let open_graph_only = r#"
+            <html>
+                <head>
+                    <title>Fallback title</title>
+                    <meta name="author" content="Meta Author">
+                    <meta property="og:title" content="Open Graph title">
+                    <script type="application/ld+json">{ "@type": "Organization", "name": "Example" }</script>
+                </head>
+                <body><article><p>Rust 1.80 was released with lazy cells.</p></article></body>
+            </html>
+        "#;
+
+        let result = ContentScraper::default().extract(open_graph_only, "https://example.com/rust-release").unwrap();
+        assert_eq!(result.metadata.get("title").map(String::as_str), Some("Open Graph title"));
+        assert_eq!(result.metadata.get("author").map(String::as_str), Some("Meta Author"));
+        assert!(!result.metadata.contains_key("site_name"));
+        assert!(result.published.is_none());
+    }
+
+    /// Tests that text blocks below the minimum word count are dropped.
+    #[test]
+    fn test_min_paragraph_words() {
+        let html = r#"
+            <html>
+                <body>
+                    <article>
+                        <p>Rust is a systems programming language focused on safety.</p>
+                        <p>Share</p>
+                        <p>It guarantees memory safety without a garbage collector.</p>
+                        <p>Read more</p>
+                        <li>Tweet</li>
+                    </article>
+                </body>
+            </html>
+        "#;
+
+        let scraper = ContentScraper::default().with_min_paragraph_words(3);
+        let result = scraper.extract(html, "https://example.com").unwrap();
+        assert_eq!(
+            result.content,
+            "Rust is a systems programming language focused on safety.\n\
+             It guarantees memory safety without a garbage collector."
+        );
+
+        // Without a minimum every block is kept
+        let result = ContentScraper::default().extract(html, "https://example.com").unwrap();
+        assert!(result.content.contains("Share"));
+        assert!(result.content.contains("Read more"));
+    }
+
+    /// Tests that the word count and reading time are recorded for a known-length article.
+    #[test]
+    fn test_reading_stats() {
+        let paragraph = "Rust is a systems programming language focused on safety and speed. ";
+        let html = format!("<html><body><article><p>{}</p></article></body></html>", paragraph.repeat(50));
+
+        let result = ContentScraper::default().extract(&html, "https://example.com").unwrap();
+        assert_eq!(result.metadata.get("word_count").map(String::as_str), Some("550"));
+        assert_eq!(result.metadata.get("reading_time_minutes").map(String::as_str), Some("3"));
+
+        let html = "<html><body><article><p>Just a few words.</p></article></body></html>";
+        let result = ContentScraper::default().extract(html, "https://example.com").unwrap();
+        assert_eq!(result.metadata.get("word_count").map(String::as_str), Some("4"));
+        assert_eq!(result.metadata.get("reading_time_minutes").map(String::as_str), Some("1"));
+    }
+
+    /// Tests that pages on a host with a site rule use its selectors, and other hosts use the defaults.
+    #[test]
+    fn test_site_rules() {
+        use crate::config::SiteRule;
+
+        let html = r#"
+            <html>
+                <head><title>serde - Rust</title></head>
+                <body>
+                    <nav><p>Crates navigation</p></nav>
+                    <div class="docblock"><p>Serde is a framework for serializing data structures.</p></div>
+                    <h1 class="fqn">Crate serde</h1>
+                </body>
+            </html>
+        "#;
+
+        let rules = SiteRules {
+            rules: vec![SiteRule {
+                host: "docs.rs".to_string(),
+                content: vec![".docblock".to_string()],
+                metadata: HashMap::from([("title".to_string(), "h1.fqn".to_string())]),
+            }],
+        };
+        let scraper = ContentScraper::default().with_site_rules(&rules);
+
+        let result = scraper.extract(html, "https://docs.rs/serde").unwrap();
+        assert_eq!(result.content, "Serde is a framework for serializing data structures.");
+        assert_eq!(result.metadata.get("title").map(String::as_str), Some("Crate serde"));
+
+        let result = scraper.extract(html, "https://example.com/serde").unwrap();
+        assert!(result.content.contains("Crates navigation"));
+        assert_eq!(result.metadata.get("title").map(String::as_str), Some("serde - Rust"));
+    }
+}
@@ -1,25 +1,140 @@
-use std::sync::Arc;
-use crate::{Result, ScraperError, ScraperConfig, ScrapedContent};
-use reqwest::Client;
-use scraper::{Html, Selector};
+use std::error::Error as StdError;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use crate::{blacklist::{FailureBlacklist, BLACKLIST_FILE}, cache::ContentCache, robots::RobotsRules, config::{ExtractionStrategy, FetchStrategy, SearchProvider, SelectionStrategy, SiteRules, UserAgentMode}, diff::diff_summaries, scraper::{append_image_captions, content_text, drop_short_blocks, extract_faq, insert_reading_stats, published_date, ContentScraper}, urls, Result, ScraperError, ScraperConfig, ScrapedContent, SearchHit};
+use reqwest::{redirect::Policy, Client, StatusCode};
+use scraper::{ElementRef, Html, Selector};
+use serde::Deserialize;
 use std::time::Duration;
-use tokio::time::sleep;
-use futures::{stream, StreamExt};
+use tokio::time::{sleep, sleep_until, Instant};
+use futures::{future, stream, Stream, StreamExt};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use tokio::sync::Semaphore;
-use tracing::{debug, error};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, error, warn};
+
+/// The error message reported when a redirect to a different host is blocked.
+const CROSS_DOMAIN_REDIRECT_BLOCKED: &str = "cross-domain redirect blocked";
+/// The Google search endpoint.
+const SEARCH_BASE_URL: &str = "https://www.google.com/search";
+/// The DuckDuckGo HTML search endpoint.
+const DUCKDUCKGO_BASE_URL: &str = "https://html.duckduckgo.com/html/";
+/// The Wayback Machine prefix that redirects to the most recent archived copy of a URL.
+const ARCHIVE_BASE_URL: &str = "https://web.archive.org/web/2/";
+/// The longest `Retry-After` delay honored before retrying a rate-limited request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+/// The maximum number of redirects followed for a single request.
+const MAX_REDIRECTS: usize = 10;
+/// The maximum number of pages of a paginated article combined into one result.
+const MAX_ARTICLE_PAGES: usize = 5;
+/// The link texts (lowercased, without arrows) that mark a link to the next page of an article.
+const NEXT_PAGE_TEXTS: [&str; 3] = ["next", "next page", "continue reading"];
+/// Current desktop browser user agents sent with `UserAgentMode::RealisticRandom`.
+const REALISTIC_USER_AGENTS: [&str; 8] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14.4; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4_1) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Safari/605.1.15",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0",
+];
+/// The `SearxngResponse` struct is the part of a SearXNG JSON response the search uses.
+#[derive(Debug, Deserialize)]
+struct SearxngResponse {
+    /// The results, in ranked order.
+    results: Vec<SearxngResult>,
+}
+
+/// The `SearxngResult` struct is a single result of a SearXNG JSON response.
+#[derive(Debug, Deserialize)]
+struct SearxngResult {
+    /// The URL of the result.
+    url: String,
+    /// The title of the result.
+    #[serde(default)]
+    title: Option<String>,
+    /// The content snippet of the result.
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// The `HostSchedule` struct is the pacing state of a single host.
+#[derive(Debug, Clone, Copy)]
+struct HostSchedule {
+    /// The earliest time the next request to the host may be sent.
+    ready_at: Instant,
+    /// The gap the host gets between requests: `politeness_factor` times its last response time.
+    politeness_delay: Duration,
+}
+
+/// The `Download` struct is the body of a downloaded page, with the response details the fetch uses.
+struct Download {
+    /// The HTML of the page, or the text extracted from it when it is a PDF.
+    body: String,
+    /// Whether the page is a PDF, whose `body` is plain text rather than HTML.
+    is_pdf: bool,
+    /// Whether the body was cut off at `ScraperConfig::max_content_bytes`.
+    truncated: bool,
+    /// The `X-Robots-Tag` header of the response, if any.
+    robots_tag: Option<String>,
+}
+
+/// The cache entry field holding a recorded redirect hop, one field per hop.
+const CACHED_REDIRECT_FIELD: &str = "redirect";
+/// The cache entry field holding the `X-Robots-Tag` header of the cached page.
+const CACHED_ROBOTS_TAG_FIELD: &str = "x-robots-tag";
+/// The largest number of results a single search may ask for.
+pub const MAX_RESULT_COUNT: usize = 50;
+/// How many times the requested number of results are collected as candidates for
+/// `SelectionStrategy::DomainRoundRobin`, so results from other domains can be swapped in.
+const ROUND_ROBIN_CANDIDATE_FACTOR: usize = 3;
+/// The maximum length of the URL-encoded query, keeping the search URL under Google's 2048 character limit.
+const MAX_ENCODED_QUERY_LENGTH: usize = 2000;
+/// The Google search operators that take a value directly after the colon.
+const SEARCH_OPERATORS: [&str; 10] = [
+    "site", "inurl", "allinurl", "intitle", "allintitle", "intext", "allintext", "filetype", "ext", "related",
+];
+/// The paths of Google's own pages (on `google.*` hosts) that are never search results.
+const GOOGLE_INTERNAL_PATHS: [&str; 3] = ["/search", "/url", "/imgres"];
+/// The paths of preference and settings pages that are never search results, on any host.
+const INVALID_PATHS: [&str; 4] = ["/preferences", "/settings", "/advanced_search", "/setprefs"];
 
 /// The `SearchEngine` struct is responsible for performing search operations and fetching content from URLs.
 /// It uses the `reqwest` library for HTTP requests and the `scraper` library for parsing HTML.
 pub struct SearchEngine {
     /// The HTTP client used for making requests.
     client: Client,
+    /// A client that doesn't follow redirects, so `download` can follow and record them itself,
+    /// when `record_redirects` is set.
+    redirect_client: Option<Client>,
     /// The configuration settings for the scraper.
     config: ScraperConfig,
     /// The rate limiter used to control the rate of requests.
     rate_limiter: Arc<Semaphore>,
     /// The progress bar used to display progress information.
     progress: MultiProgress,
+    /// The prefix used to build archive URLs for the archive fallback.
+    archive_base_url: String,
+    /// The search endpoint queries are sent to.
+    search_base_url: String,
+    /// The scraper used to extract metadata from fetched pages.
+    scraper: ContentScraper,
+    /// The on-disk cache of fetched pages, if `cache_dir` is set.
+    cache: Option<ContentCache>,
+    /// The failure counts of URLs, if `failure_blacklist_threshold` is set.
+    blacklist: Option<FailureBlacklist>,
+    /// The total number of bytes downloaded by all fetches.
+    bytes_downloaded: AtomicUsize,
+    /// When each host may be requested again, set by `politeness_factor` and `Retry-After`.
+    host_schedules: Mutex<HashMap<String, HostSchedule>>,
+    /// The robots.txt of each origin, fetched on first use when `respect_robots_txt` is set. It is
+    /// empty when the origin has none.
+    robots: Mutex<HashMap<String, Arc<tokio::sync::OnceCell<String>>>>,
+    /// The random number generator behind user agent picks and pacing jitter, seeded from `seed`.
+    rng: Mutex<StdRng>,
 }
 
 impl SearchEngine {
@@ -33,35 +148,325 @@ impl SearchEngine {
     ///
     /// A `Result` containing the `SearchEngine` instance, or an error if the client could not be created.
     pub fn new(config: ScraperConfig) -> Result<Self> {
-        let client = Client::builder()
-            .user_agent(&config.user_agent)
-            .timeout(config.timeout)
-            .gzip(true)
-            .build()
-            .map_err(|e| ScraperError::RequestError(e))?;
+        let client = build_client(&config, redirect_policy(&config))?;
+        let redirect_client = if config.record_redirects {
+            Some(build_client(&config, Policy::none())?)
+        } else {
+            None
+        };
 
         // Initialize rate limiter
         let rate_limiter = Arc::new(Semaphore::new(config.rate_limit.burst_size));
 
+        let cache = config
+            .cache_dir
+            .as_ref()
+            .map(|dir| ContentCache::new(dir, config.cache_ttl, config.cache_compression));
+        let blacklist = config.failure_blacklist_threshold.map(|threshold| {
+            FailureBlacklist::new(config.cache_dir.as_ref().map(|dir| dir.join(BLACKLIST_FILE)), threshold)
+        });
+
+        let search_base_url = match config.search_provider {
+            SearchProvider::Google => SEARCH_BASE_URL.to_string(),
+            SearchProvider::DuckDuckGo => DUCKDUCKGO_BASE_URL.to_string(),
+            SearchProvider::SearXNG => match &config.searxng_endpoint {
+                Some(endpoint) => format!("{}/search", endpoint.trim_end_matches('/')),
+                None => {
+                    return Err(ScraperError::ConfigError(
+                        "search_provider SearXNG requires searxng_endpoint".to_string(),
+                    ))
+                }
+            },
+        };
+
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let mut scraper = ContentScraper::default()
+            .with_min_paragraph_words(config.min_paragraph_words)
+            .with_image_captions(config.include_image_captions);
+        if let Some(path) = &config.site_rules {
+            scraper = scraper.with_site_rules(&SiteRules::load(path)?);
+        }
+
         Ok(Self {
             client,
+            redirect_client,
             config,
             rate_limiter,
             progress: MultiProgress::new(),
+            archive_base_url: ARCHIVE_BASE_URL.to_string(),
+            search_base_url,
+            scraper,
+            cache,
+            blacklist,
+            bytes_downloaded: AtomicUsize::new(0),
+            host_schedules: Mutex::new(HashMap::new()),
+            robots: Mutex::new(HashMap::new()),
+            rng: Mutex::new(rng),
         })
     }
 
-    /// Performs a search operation and returns a list of URLs.
+    /// Performs a search operation and returns the results with their titles and snippets.
+    /// Use `SearchHit::urls` when only the URLs are needed.
+    ///
+    /// The count applies to usable URLs: when invalid and duplicate links leave fewer than
+    /// `result_count`, further result pages are requested, up to `max_search_pages`. Fewer URLs are
+    /// only returned when the search runs out of results. With `SelectionStrategy::DomainRoundRobin`,
+    /// more candidates are requested than returned so the selection can spread over more domains.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `result_count` - The number of search results to return, from 1 to `MAX_RESULT_COUNT`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a vector of `SearchHit`, or an error if the result count is out of
+    /// range or the search fails.
+    pub async fn search(&self, query: &str, result_count: usize) -> Result<Vec<SearchHit>> {
+        if !(1..=MAX_RESULT_COUNT).contains(&result_count) {
+            return Err(ScraperError::SearchError(format!(
+                "The result count must be between 1 and {}, got {}",
+                MAX_RESULT_COUNT, result_count
+            )));
+        }
+
+        let candidate_count = match self.config.selection_strategy {
+            SelectionStrategy::TopRanked => result_count,
+            SelectionStrategy::DomainRoundRobin => (result_count * ROUND_ROBIN_CANDIDATE_FACTOR).min(MAX_RESULT_COUNT),
+        };
+        let mut results: Vec<SearchHit> = Vec::new();
+        let mut per_domain = HashMap::new();
+
+        for page in 0..self.config.max_search_pages.max(1) {
+            let start = page.saturating_mul(candidate_count);
+            let page_results = self.search_page(query, candidate_count, start).await?;
+
+            let before = results.len();
+            for result in page_results {
+                if !results.iter().any(|r| r.url == result.url)
+                    && self.within_domain_cap(&mut per_domain, &result.url)
+                {
+                    results.push(result);
+                }
+            }
+
+            if results.len() >= result_count || results.len() == before {
+                break;
+            }
+            debug!("Only {} of {} usable URLs after page {}, requesting the next page", results.len(), result_count, page + 1);
+        }
+
+        Ok(self.select_urls(results, result_count))
+    }
+
+    /// Searches for several queries and merges their results, dropping duplicate URLs.
+    ///
+    /// With `sequential_search` (the default) the queries are searched one at a time, each
+    /// paced like a single search; otherwise all searches are sent at once, which is faster but
+    /// much more likely to trip the search provider's bot detection. A failed query is logged
+    /// and skipped as long as at least one query succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - The search queries.
+    /// * `result_count` - The number of search results to return per query.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the merged results in query order, or the first error if every query failed.
+    pub async fn search_all(&self, queries: &[String], result_count: usize) -> Result<Vec<SearchHit>> {
+        let results: Vec<Result<Vec<SearchHit>>> = if self.config.sequential_search {
+            stream::iter(queries)
+                .then(|query| self.search(query, result_count))
+                .collect()
+                .await
+        } else {
+            future::join_all(queries.iter().map(|query| self.search(query, result_count))).await
+        };
+
+        let mut merged: Vec<SearchHit> = Vec::new();
+        let mut first_error = None;
+        let mut succeeded = false;
+
+        for (query, result) in queries.iter().zip(results) {
+            match result {
+                Ok(found) => {
+                    succeeded = true;
+                    for result in found {
+                        if !merged.iter().any(|r| r.url == result.url) {
+                            merged.push(result);
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Search for '{}' failed: {}", query, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) if !succeeded => Err(e),
+            _ => Ok(merged),
+        }
+    }
+
+    /// Counts a search result towards the `max_per_domain` cap of its host.
+    ///
+    /// # Arguments
+    ///
+    /// * `counts` - The number of results kept so far per host.
+    /// * `url` - The URL of the result.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the result is kept, `false` if its host already reached the cap.
+    fn within_domain_cap(&self, counts: &mut HashMap<String, usize>, url: &str) -> bool {
+        if self.config.max_per_domain == 0 {
+            return true;
+        }
+
+        let count = counts.entry(urls::host(url).unwrap_or_default()).or_insert(0);
+        if *count >= self.config.max_per_domain {
+            debug!("Dropping {}: {} results from its host already", url, count);
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    /// Checks a URL's host against the configured `allowed_domains` and `blocked_domains`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the host isn't blocked and, when an allowlist is set, matches one of its domains.
+    fn domain_allowed(&self, url: &str) -> bool {
+        let Some(host) = urls::host(url) else {
+            return false;
+        };
+        let matches_any = |domains: &[String]| domains.iter().any(|domain| urls::host_matches(&host, domain));
+
+        (self.config.allowed_domains.is_empty() || matches_any(&self.config.allowed_domains))
+            && !matches_any(&self.config.blocked_domains)
+    }
+
+    /// Builds the search URL for a query on the configured provider, applying the configured
+    /// recency window.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query.
+    /// * `result_count` - The number of search results to request. DuckDuckGo and SearXNG ignore
+    ///   it, except to turn `start` into a SearXNG page number.
+    /// * `start` - The offset of the first result, for requesting later result pages.
+    ///
+    /// # Returns
+    ///
+    /// The search URL.
+    fn search_url(&self, query: &str, result_count: usize, start: usize) -> String {
+        match self.config.search_provider {
+            SearchProvider::Google => {
+                let mut url = format!(
+                    "{}?q={}&hl=en&num={}",
+                    self.search_base_url, urlencoding::encode(query), result_count
+                );
+                if start > 0 {
+                    url.push_str(&format!("&start={}", start));
+                }
+                if let Some(recency) = self.config.recency {
+                    url.push_str(&format!("&tbs=qdr:{}", recency.qdr_code()));
+                }
+                url
+            }
+            SearchProvider::DuckDuckGo => {
+                let mut url = format!("{}?q={}", self.search_base_url, urlencoding::encode(query));
+                if start > 0 {
+                    url.push_str(&format!("&s={}", start));
+                }
+                if let Some(recency) = self.config.recency {
+                    url.push_str(&format!("&df={}", recency.duckduckgo_code()));
+                }
+                url
+            }
+            SearchProvider::SearXNG => {
+                let per_page = result_count.max(1);
+                let mut url = format!(
+                    "{}?q={}&format=json&pageno={}",
+                    self.search_base_url,
+                    urlencoding::encode(query),
+                    start / per_page + 1
+                );
+                if let Some(recency) = self.config.recency {
+                    url.push_str(&format!("&time_range={}", recency.searxng_range()));
+                }
+                url
+            }
+        }
+    }
+
+    /// Picks up to `count` URLs from ranked candidates using the configured selection strategy.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - The candidate URLs (or search results) in ranked order.
+    /// * `count` - The maximum number of URLs to select.
+    ///
+    /// # Returns
+    ///
+    /// The selected URLs.
+    pub fn select_urls<T: AsRef<str>>(&self, candidates: Vec<T>, count: usize) -> Vec<T> {
+        match self.config.selection_strategy {
+            SelectionStrategy::TopRanked => candidates.into_iter().take(count).collect(),
+            SelectionStrategy::DomainRoundRobin => {
+                // Group by domain, keeping domains in order of their best-ranked URL
+                let mut groups: Vec<(String, std::collections::VecDeque<T>)> = Vec::new();
+                for url in candidates {
+                    let host = urls::host(url.as_ref()).unwrap_or_default();
+                    match groups.iter_mut().find(|(h, _)| *h == host) {
+                        Some((_, group)) => group.push_back(url),
+                        None => groups.push((host, std::collections::VecDeque::from([url]))),
+                    }
+                }
+
+                let mut selected = Vec::new();
+                while selected.len() < count && groups.iter().any(|(_, group)| !group.is_empty()) {
+                    for (_, group) in groups.iter_mut() {
+                        if selected.len() >= count {
+                            break;
+                        }
+                        if let Some(url) = group.pop_front() {
+                            selected.push(url);
+                        }
+                    }
+                }
+
+                selected
+            }
+        }
+    }
+
+    /// Requests a single page of search results.
     ///
     /// # Arguments
     ///
     /// * `query` - The search query.
-    /// * `result_count` - The number of search results to return.
+    /// * `result_count` - The number of search results to request.
+    /// * `start` - The offset of the first result.
     ///
     /// # Returns
     ///
-    /// A `Result` containing a vector of URLs, or an error if the search fails.
-    pub async fn search(&self, query: &str, result_count: &str) -> Result<Vec<String>> {
+    /// A `Result` containing the page's valid results, or an error if the search fails.
+    async fn search_page(&self, query: &str, result_count: usize, start: usize) -> Result<Vec<SearchHit>> {
+        validate_query(query)?;
+
         let search_pb = self.progress.add(ProgressBar::new_spinner());
         search_pb.set_style(
             ProgressStyle::default_spinner()
@@ -72,18 +477,19 @@ impl SearchEngine {
 
         sleep(Duration::from_secs(1)).await;
 
-        let url = format!(
-            "https://www.google.com/search?q={}&hl=en&num={}",
-            urlencoding::encode(query), result_count
-        );
+        let url = self.search_url(query, result_count, start);
 
         debug!("Search URL: {}", url);
 
-        let response = self.client
-            .get(&url)
+        let mut request = self.client.get(&url);
+        if let Some(user_agent) = self.pick_user_agent() {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+
+        let response = request
+            .timeout(self.config.search_timeout.unwrap_or(self.config.timeout))
             .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8")
             .header("Accept-Language", "en-US,en;q=0.5")
-            .header("Accept-Encoding", "gzip, deflate, br")
             .header("Connection", "keep-alive")
             .header("Upgrade-Insecure-Requests", "1")
             .header("Sec-Fetch-Dest", "document")
@@ -102,20 +508,102 @@ impl SearchEngine {
         search_pb.set_message("Processing search results...");
         let html = response.text().await?;
 
-        let document = Html::parse_document(&html);
-        self.extract_urls(&document)
+        match self.config.search_provider {
+            SearchProvider::Google => self.extract_results(&Html::parse_document(&html), result_count),
+            SearchProvider::DuckDuckGo => self.extract_duckduckgo_results(&Html::parse_document(&html), result_count),
+            SearchProvider::SearXNG => self.parse_searxng_results(status, &html, result_count),
+        }
+    }
+
+    /// Re-ranks URLs with a caller-supplied scoring function before fetching.
+    ///
+    /// URLs are sorted by descending score, with ties keeping their original order, and truncated
+    /// to `count`. The scorer can encode any signal, such as domain authority, URL structure or
+    /// keyword presence, and is layered on top of whatever order the search returned.
+    ///
+    /// This composes with relevance ranking by embeddings: embed the query and each candidate,
+    /// then return the cosine similarity from the scorer (or blend it with other signals as a
+    /// weighted sum).
+    ///
+    /// # Arguments
+    ///
+    /// * `urls` - The URLs to rank.
+    /// * `count` - The maximum number of URLs to keep.
+    /// * `scorer` - A function returning a score for each URL; higher is better.
+    ///
+    /// # Returns
+    ///
+    /// The top `count` URLs in descending score order.
+    pub fn rank_with(&self, urls: Vec<String>, count: usize, scorer: impl Fn(&str) -> f32) -> Vec<String> {
+        let mut scored: Vec<(f32, String)> = urls
+            .into_iter()
+            .map(|url| (scorer(&url), url))
+            .collect();
+
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        scored
+            .into_iter()
+            .take(count)
+            .map(|(_, url)| url)
+            .collect()
+    }
+
+    /// Checks URLs given by the user instead of search results with the same rules search results
+    /// are held to: only `https` URLs outside Google that pass the domain filters are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `urls` - The URLs to check.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that is `Ok` if every URL is valid, or a `SearchError` listing the invalid ones.
+    pub fn validate_urls(&self, urls: &[String]) -> Result<()> {
+        let invalid: Vec<&str> = urls
+            .iter()
+            .filter(|url| !self.is_valid_url(url))
+            .map(String::as_str)
+            .collect();
+
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(ScraperError::SearchError(format!(
+                "Invalid URLs (only https URLs outside Google that pass the domain filters are accepted): {}",
+                invalid.join(", ")
+            )))
+        }
     }
 
     /// Fetches content from all the given URLs.
     ///
     /// # Arguments
     ///
-    /// * `urls` - A vector of URLs to fetch content from.
+    /// * `urls` - The URLs to fetch content from, as strings or as `SearchHit`s.
     ///
     /// # Returns
     ///
     /// A `Result` containing a vector of `ScrapedContent`, or an error if the fetch fails.
-    pub async fn fetch_all(&self, urls: Vec<String>) -> Result<Vec<ScrapedContent>> {
+    pub async fn fetch_all(&self, urls: impl IntoIterator<Item = impl Into<String>>) -> Result<Vec<ScrapedContent>> {
+        let results = self.fetch_all_results(urls.into_iter().map(Into::into).collect()).await;
+
+        Ok(results
+            .into_iter()
+            .filter_map(|(_, result)| result.ok())
+            .collect())
+    }
+
+    /// Fetches content from all the given URLs, keeping the outcome of every fetch.
+    ///
+    /// # Arguments
+    ///
+    /// * `urls` - A vector of URLs to fetch content from.
+    ///
+    /// # Returns
+    ///
+    /// A vector of each URL paired with its fetch result, in completion order.
+    pub async fn fetch_all_results(&self, urls: Vec<String>) -> Vec<(String, Result<ScrapedContent>)> {
         let total_urls = urls.len();
 
         let fetch_pb = self.progress.add(ProgressBar::new_spinner());
@@ -128,287 +616,3951 @@ impl SearchEngine {
 
         fetch_pb.set_message(format!("Fetching pages...{}", urls.len()));
 
-        let fetches = stream::iter(urls)
-            .map(|url| {
-                let rate_limiter = self.rate_limiter.clone();
-                let fetch_pb = fetch_pb.clone();
-                async move {
-                    // Acquire rate limit permit
-                    let _permit = rate_limiter.acquire().await.expect("Rate limiter closed");
-                    let delay = Duration::from_secs_f32(1.0 / self.config.rate_limit.requests_per_second);
-                    sleep(delay).await;
-
-                    fetch_pb.set_message(format!("Fetching {}", url));
-
-                    match self.fetch_content(&url).await {
-                        Ok(content) => {
-                            Some(content)
-                        }
-                        Err(_e) => {
-                            None
-                        }
-                    }
-                }
-            })
-            .buffer_unordered(self.config.concurrent_requests)
-            .collect::<Vec<_>>()
+        let results: Vec<(String, Result<ScrapedContent>)> = self
+            .fetch_results(urls)
+            .inspect(|(url, _)| fetch_pb.set_message(format!("Fetched {}", url)))
+            .collect()
             .await;
 
-        let contents: Vec<ScrapedContent> = fetches.into_iter()
-            .filter_map(|x| x)
-            .collect();
-
-        let success_count = contents.len();
+        let success_count = results.iter().filter(|(_, result)| result.is_ok()).count();
+        if self.bandwidth_exceeded() {
+            warn!(
+                "Download limit reached after {} bytes, remaining pages were skipped",
+                self.bytes_downloaded()
+            );
+        }
         fetch_pb.finish_with_message(format!(
             "Completed: {} of {} pages scraped successfully",
             success_count,
             total_urls
         ));
-        Ok(contents)
+        results
     }
 
-    /// Fetches content from a single URL with retries.
+    /// Returns the configuration the engine was created with.
+    pub fn config(&self) -> &ScraperConfig {
+        &self.config
+    }
+
+    /// Returns the total number of bytes downloaded so far. Pages served from the cache are not counted.
+    pub fn bytes_downloaded(&self) -> usize {
+        self.bytes_downloaded.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the `max_total_bytes` download limit has been reached.
+    pub fn bandwidth_exceeded(&self) -> bool {
+        self.config
+            .max_total_bytes
+            .is_some_and(|limit| self.bytes_downloaded() >= limit)
+    }
+
+    /// Returns the URLs skipped because they failed `failure_blacklist_threshold` times in a row.
+    pub fn blacklisted_urls(&self) -> Vec<String> {
+        self.blacklist
+            .as_ref()
+            .map(FailureBlacklist::blacklisted)
+            .unwrap_or_default()
+    }
+
+    /// Resets the failure blacklist, so every URL is fetched again. Does nothing if
+    /// `failure_blacklist_threshold` isn't set.
+    pub fn reset_blacklist(&self) {
+        if let Some(blacklist) = &self.blacklist {
+            blacklist.clear();
+        }
+    }
+
+    /// Fetches content from the given URLs, yielding each page as soon as it has been scraped.
+    ///
+    /// Requests are rate limited and run with the configured concurrency, exactly like `fetch_all`,
+    /// but consumers can start processing pages (e.g. summarizing them) while the remaining fetches
+    /// are still in flight. Pages that fail to fetch are skipped.
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL to fetch content from.
+    /// * `urls` - A vector of URLs to fetch content from.
     ///
     /// # Returns
     ///
-    /// A `Result` containing the `ScrapedContent`, or an error if the fetch fails.
-    async fn fetch_content(&self, url: &str) -> Result<ScrapedContent> {
-        debug!("Fetching content from: {}", url);
-
-        let mut retries = 0;
-        let mut last_error = None;
-
-        while retries < self.config.max_retries {
-            match self.try_fetch_content(url).await {
-                Ok(content) => return Ok(content),
-                Err(e) => {
-                    retries += 1;
-                    last_error = Some(e);
-                    if retries < self.config.max_retries {
-                        let delay = Duration::from_secs(2u64.pow(retries));
-                        sleep(delay).await;
-                    }
-                }
-            }
-        }
-
-        Err(last_error.unwrap_or_else(|| {
-            ScraperError::ExtractionError("Max retries exceeded".to_string())
-        }))
+    /// A stream of `ScrapedContent` in completion order.
+    pub fn fetch_stream(&self, urls: Vec<String>) -> impl Stream<Item = ScrapedContent> + '_ {
+        self.fetch_results(urls)
+            .filter_map(|(_, result)| future::ready(result.ok()))
     }
 
-    /// Attempts to fetch content from a single URL.
+    /// Fetches content from the given URLs, yielding the outcome of each fetch as it completes.
+    ///
+    /// Unlike `fetch_stream`, failed fetches are yielded too, so callers can report exactly which
+    /// URLs could not be scraped and why.
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL to fetch content from.
+    /// * `urls` - A vector of URLs to fetch content from.
     ///
     /// # Returns
     ///
-    /// A `Result` containing the `ScrapedContent`, or an error if the fetch fails.
-    async fn try_fetch_content(&self, url: &str) -> Result<ScrapedContent> {
-        let response = self.client
-            .get(url)
-            .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8")
-            .header("Accept-Language", "en-US,en;q=0.5")
-            .header("Accept-Encoding", "gzip, deflate, br")
-            .header("Connection", "keep-alive")
-            .header("Upgrade-Insecure-Requests", "1")
-            .header("Sec-Fetch-Dest", "document")
-            .header("Sec-Fetch-Mode", "navigate")
-            .header("Sec-Fetch-Site", "none")
-            .header("Sec-Fetch-User", "?1")
-            .send()
-            .await?;
-
-        let html = response.text().await?;
-        let document = Html::parse_document(&html);
-
-        let content = self.extract_text(&document)?;
-
-        Ok(ScrapedContent {
-            url: url.to_string(),
-            content,
-            metadata: std::collections::HashMap::new(),
-            timestamp: chrono::Utc::now(),
-        })
+    /// A stream of each URL paired with its fetch result, in completion order.
+    pub fn fetch_results(&self, urls: Vec<String>) -> impl Stream<Item = (String, Result<ScrapedContent>)> + '_ {
+        stream::iter(urls)
+            .map(move |url| async move {
+                let result = self.fetch_paced(url.clone()).await;
+                (url, result)
+            })
+            .buffer_unordered(self.config.concurrent_requests)
     }
 
-    /// Extracts URLs from the HTML document.
+    /// Fetches pages and folds each one into an accumulator as soon as it arrives.
+    ///
+    /// Unlike `fetch_all`, pages are not retained: each `ScrapedContent` is handed to `f` and
+    /// dropped afterwards, so memory stays bounded by the accumulator (e.g. a bounded prompt or a
+    /// running summary) rather than by the size of the whole corpus.
     ///
     /// # Arguments
     ///
-    /// * `document` - The parsed HTML document.
+    /// * `urls` - A vector of URLs to fetch content from.
+    /// * `init` - The initial accumulator value.
+    /// * `f` - A function combining the accumulator with the next fetched page.
     ///
     /// # Returns
     ///
-    /// A `Result` containing a vector of URLs, or an error if no URLs are found.
-    fn extract_urls(&self, document: &Html) -> Result<Vec<String>> {
-        // Try multiple selector patterns that Google might use
-        let selector_patterns = [
-            "div.g div.yuRUbf > a",           // Common pattern
-            "div.tF2Cxc > div.yuRUbf > a",    // Alternative pattern
-            "div.g a[href]",                  // More general pattern
-            "div[class='g'] a[ping]",         // Another common pattern
-            "div.rc > a",                     // Legacy pattern
-            "div.r > a",                      // Legacy pattern
-            "a[data-ved]",                    // Links with data-ved attribute
-        ];
+    /// A `Result` containing the final accumulator value.
+    pub async fn fetch_and_fold<B, F>(&self, urls: Vec<String>, init: B, mut f: F) -> Result<B>
+    where
+        F: FnMut(B, ScrapedContent) -> B,
+    {
+        let pages = self.fetch_stream(urls);
+        futures::pin_mut!(pages);
 
-        let mut all_urls = Vec::new();
+        let mut acc = init;
+        while let Some(content) = pages.next().await {
+            acc = f(acc, content);
+        }
 
-        for pattern in selector_patterns {
-            debug!("Trying selector pattern: {}", pattern);
+        Ok(acc)
+    }
 
-            if let Ok(selector) = Selector::parse(pattern) {
-                let urls: Vec<String> = document
-                    .select(&selector)
-                    .filter_map(|link| {
-                        let href = link.value().attr("href")?;
-                        debug!("Found raw URL: {}", href);
-
-                        if let Some(clean_url) = self.clean_google_url(href) {
-                            if self.is_valid_url(&clean_url) {
-                                debug!("Valid URL found: {}", clean_url);
-                                Some(clean_url)
-                            } else {
-                                debug!("Invalid URL: {}", clean_url);
-                                None
-                            }
-                        } else {
-                            debug!("Could not clean URL: {}", href);
-                            None
-                        }
-                    })
-                    .collect();
+    /// Fetches the live version of a page and diffs its text against the cached version.
+    ///
+    /// The fresh HTML replaces the cache entry, so each call reports the changes since the previous
+    /// call. The first call for a URL only stores the baseline. Expired entries are still used as
+    /// the baseline.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page to monitor.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `None` if the page text is unchanged or has no baseline yet, or the
+    /// sentence-level diff (see `diff::diff_summaries`) if it changed. Fails with a `ConfigError`
+    /// if no `cache_dir` is configured.
+    pub async fn fetch_and_diff(&self, url: &str) -> Result<Option<String>> {
+        let cache = self.cache.as_ref().ok_or_else(|| {
+            ScraperError::ConfigError("fetch_and_diff requires a cache_dir".to_string())
+        })?;
 
-                all_urls.extend(urls);
-            }
+        let previous = cache.get_stale(url);
+        let mut hops = Vec::new();
+        let download = {
+            let _permit = self.wait_to_fetch(url).await?;
+            let result = self.download(url, None, &mut hops).await;
+            self.record_outcome(url, &result);
+            result?
+        };
+        if download.is_pdf {
+            return Err(ScraperError::ExtractionError(format!("Can't diff {}: PDFs aren't cached", url)));
+        }
+        let html = download.body;
+        if let Err(e) = cache.put_entry(url, &html, &cache_fields(&hops, download.robots_tag.as_deref())) {
+            warn!("Failed to cache {}: {}", url, e);
         }
 
-        // Remove duplicates
-        all_urls.sort();
-        all_urls.dedup();
+        let Some(previous) = previous else {
+            debug!("Stored the first version of {}", url);
+            return Ok(None);
+        };
 
-        if all_urls.is_empty() {
-            error!("No valid URLs found in the response");
-        } else {
-            for (i, url) in all_urls.iter().enumerate() {
-                debug!("URL {}: {}", i + 1, url);
-            }
-        }
+        let old_text = self.page_content(&Html::parse_document(&previous), url).unwrap_or_default();
+        let new_text = self.page_content(&Html::parse_document(&html), url)?;
+        let diff = diff_summaries(&old_text, &new_text);
 
-        Ok(all_urls)
+        Ok((!diff.is_empty()).then_some(diff))
     }
 
-    /// Cleans a Google redirect URL to extract the actual URL.
+    /// Waits for a rate limit permit and the configured request interval, then fetches a URL.
+    ///
+    /// Pages missing any of the configured `required_metadata` keys are rejected, and once the
+    /// `max_total_bytes` limit is reached no new fetches start. When `politeness_factor` is set,
+    /// the fetch also waits until its host has had time to recover from the previous request.
     ///
     /// # Arguments
     ///
-    /// * `url` - The Google redirect URL.
+    /// * `url` - The URL to fetch content from.
     ///
     /// # Returns
     ///
-    /// An `Option` containing the cleaned URL, or `None` if the URL could not be cleaned.
-    fn clean_google_url(&self, url: &str) -> Option<String> {
-        debug!("Cleaning URL: {}", url);
+    /// A `Result` containing the `ScrapedContent`, or an error if the fetch fails.
+    async fn fetch_paced(&self, url: String) -> Result<ScrapedContent> {
+        let url = if self.config.prefer_canonical_urls {
+            urls::canonical_url(&url)
+        } else {
+            url
+        };
 
-        // Handle Google redirect URLs
-        if url.starts_with("/url?") || url.contains("/url?") {
-            let url_str = url.replace("/url?", "");
-            if let Some(query) = url_str.split('&').find(|&q| q.starts_with("q=")) {
-                let clean = query.replace("q=", "");
-                let decoded = urlencoding::decode(&clean).ok()?.into_owned();
-                debug!("Cleaned redirect URL: {}", decoded);
-                return Some(decoded);
+        let _permit = self.wait_to_fetch(&url).await?;
+        let result = self.fetch_content(&url).await;
+        self.record_outcome(&url, &result);
+        let content = result?;
+
+        let missing: Vec<&str> = self
+            .config
+            .required_metadata
+            .iter()
+            .filter(|key| !content.metadata.contains_key(key.as_str()))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            debug!("Dropping {}: missing metadata {:?}", url, missing);
+            return Err(ScraperError::ExtractionError(format!(
+                "Missing required metadata: {}",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(content)
+    }
+
+    /// Waits until a URL may be fetched: first for the politeness slot of its host, so a slow host
+    /// doesn't hold a permit idle, then for a rate limit permit and the configured request interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL about to be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the permit, which the caller holds until its request is done, or an
+    /// error if the `max_total_bytes` limit is reached or the URL is blacklisted.
+    async fn wait_to_fetch(&self, url: &str) -> Result<SemaphorePermit<'_>> {
+        if let Some(ready_at) = self.reserve_request_time(url) {
+            debug!("Waiting {:?} before fetching {} again", ready_at.saturating_duration_since(Instant::now()), url);
+            sleep_until(ready_at).await;
+        }
+
+        let permit = self.acquire_paced().await;
+
+        if let Some(limit) = self.config.max_total_bytes.filter(|_| self.bandwidth_exceeded()) {
+            debug!("Skipping {}: download limit reached", url);
+            return Err(ScraperError::BandwidthExceeded(limit));
+        }
+
+        if self.blacklist.as_ref().is_some_and(|blacklist| blacklist.is_blacklisted(url)) {
+            debug!("Skipping {}: blacklisted after repeated failures", url);
+            return Err(ScraperError::Blacklisted(url.to_string()));
+        }
+
+        Ok(permit)
+    }
+
+    /// Records the outcome of a fetch in the blacklist, if one is configured. Pages skipped by
+    /// robots rules or the download limit count as neither success nor failure.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL that was fetched.
+    /// * `result` - The result of the fetch.
+    fn record_outcome<T>(&self, url: &str, result: &Result<T>) {
+        let Some(blacklist) = &self.blacklist else {
+            return;
+        };
+        match result {
+            Ok(_) => blacklist.record_success(url),
+            Err(
+                ScraperError::RobotsDisallowed(_)
+                | ScraperError::DisallowedByRobots(_)
+                | ScraperError::BandwidthExceeded(_),
+            ) => {}
+            Err(_) => {
+                blacklist.record_failure(url);
             }
         }
+    }
 
-        // Handle absolute URLs
-        if url.starts_with("http") {
-            debug!("Found absolute URL: {}", url);
-            return Some(url.to_string());
+    /// Waits for a rate limit permit, then for the configured request interval.
+    ///
+    /// # Returns
+    ///
+    /// The permit, which the caller holds until its request is done.
+    async fn acquire_paced(&self) -> SemaphorePermit<'_> {
+        let permit = self.rate_limiter.acquire().await.expect("Rate limiter closed");
+        let interval = Duration::from_secs_f32(1.0 / self.config.rate_limit.requests_per_second);
+        let delay = pacing_delay(interval, self.config.pacing_jitter, &mut *self.rng.lock().unwrap());
+        sleep(delay).await;
+        permit
+    }
+
+    /// Looks up the earliest time the host of a URL may be requested again.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL about to be fetched.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the time to wait until, or `None` if the host can be requested now.
+    fn next_request_time(&self, url: &str) -> Option<Instant> {
+        let host = urls::host(url)?;
+        let ready_at = self.host_schedules.lock().unwrap().get(&host)?.ready_at;
+        (ready_at > Instant::now()).then_some(ready_at)
+    }
+
+    /// Reserves the next request slot of the host of a URL. The host's next slot moves on by its
+    /// politeness delay in the same step, so concurrent fetches of one host each get their own slot
+    /// instead of all reading the same one and sending at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL about to be fetched.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the time to wait until, or `None` if the host can be requested now.
+    fn reserve_request_time(&self, url: &str) -> Option<Instant> {
+        let host = urls::host(url)?;
+        let now = Instant::now();
+        let mut schedules = self.host_schedules.lock().unwrap();
+        let schedule = schedules.get_mut(&host)?;
+
+        let slot = schedule.ready_at.max(now);
+        schedule.ready_at = slot + schedule.politeness_delay;
+        (slot > now).then_some(slot)
+    }
+
+    /// Records how long a host took to respond, delaying its next request by `politeness_factor`
+    /// times that duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL that was fetched.
+    /// * `response_time` - How long the request took, including reading the body.
+    fn record_response_time(&self, url: &str, response_time: Duration) {
+        let (Some(factor), Some(host)) = (self.config.politeness_factor, urls::host(url)) else {
+            return;
+        };
+
+        let delay = response_time.mul_f64(f64::from(factor.max(0.0)));
+        let ready_at = Instant::now() + delay;
+        let mut schedules = self.host_schedules.lock().unwrap();
+        let schedule = schedules
+            .entry(host)
+            .or_insert(HostSchedule { ready_at, politeness_delay: delay });
+        // Slots already reserved by concurrent fetches stay reserved
+        schedule.ready_at = schedule.ready_at.max(ready_at);
+        schedule.politeness_delay = delay;
+    }
+
+    /// Fetches content from a single URL with retries.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to fetch content from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `ScrapedContent`, or an error if the fetch fails.
+    async fn fetch_content(&self, url: &str) -> Result<ScrapedContent> {
+        debug!("Fetching content from: {}", url);
+
+        let mut retries = 0;
+        let mut last_error = None;
+
+        while retries < self.config.max_retries {
+            match self.try_fetch_hedged(url).await {
+                Ok(content) => return Ok(content),
+                // Neither asking again nor a fallback makes robots.txt allow the page
+                Err(e @ ScraperError::DisallowedByRobots(_)) => return Err(e),
+                Err(e) => {
+                    retries += 1;
+                    // Client errors such as 404 won't go away by asking again
+                    let retryable = !matches!(e, ScraperError::HttpStatus(status) if is_permanent_status(status));
+                    let rate_limited = matches!(e, ScraperError::HttpStatus(429));
+                    last_error = Some(e);
+                    if !retryable {
+                        break;
+                    }
+                    if retries < self.config.max_retries {
+                        // A 429 with `Retry-After` waits as long as the server asked instead of backing off
+                        match self.next_request_time(url).filter(|_| rate_limited) {
+                            Some(ready_at) => sleep_until(ready_at).await,
+                            None => sleep(Duration::from_secs(2u64.pow(retries))).await,
+                        }
+                    }
+                }
+            }
         }
 
-        debug!("URL could not be cleaned: {}", url);
-        None
+        // Give difficult pages one last chance with a different kind of request
+        if let Some(fallback) = &self.config.fetch_fallback {
+            debug!("Retrying {} with fallback strategy {:?}", url, fallback);
+            match self.try_fetch_with(url, Some(fallback)).await {
+                Ok(content) => return Ok(content),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        if self.config.archive_fallback {
+            let archive_url = format!("{}{}", self.archive_base_url, url);
+            debug!("Trying archived copy of {} at {}", url, archive_url);
+            match self.try_fetch_content(&archive_url).await {
+                Ok(mut content) => {
+                    content.url = url.to_string();
+                    content.metadata.insert("archive_url".to_string(), archive_url);
+                    return Ok(content);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            ScraperError::ExtractionError("Max retries exceeded".to_string())
+        }))
     }
 
-    /// Checks if a URL is valid.
+    /// Checks whether robots.txt allows fetching a URL with a user agent. The robots.txt of each
+    /// origin is fetched once and shared by all later fetches, including concurrent ones.
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL to check.
+    /// * `url` - The URL about to be fetched.
+    /// * `user_agent` - The user agent the request is sent with, which picks the robots.txt group.
     ///
     /// # Returns
     ///
-    /// `true` if the URL is valid, `false` otherwise.
-    fn is_valid_url(&self, url: &str) -> bool {
-        // Invalid patterns
-        let invalid_patterns = [
-            "google.com/search",
-            "google.com/url",
-            "google.com/imgres",
-            "accounts.google",
-            "webcache.googleusercontent",
-            "/preferences",
-            "/settings",
-            "/advanced_search",
-            "/setprefs",
-            "javascript:",
-        ];
+    /// `true` if the URL may be fetched, `false` if robots.txt disallows it.
+    async fn robots_allowed(&self, url: &str, user_agent: &str) -> bool {
+        let Ok(parsed) = url::Url::parse(url) else {
+            return true;
+        };
+        let origin = parsed.origin().ascii_serialization();
 
-        let is_valid = url.starts_with("https://") &&
-            !invalid_patterns.iter().any(|&pattern| url.contains(pattern)) &&
-            !url.contains("&");
+        let robots = self.robots.lock().unwrap().entry(origin.clone()).or_default().clone();
+        let text = robots.get_or_init(|| self.fetch_robots(&origin)).await;
+        let rules = RobotsRules::parse(text, user_agent);
 
-        if is_valid {
-            debug!("URL is valid: {}", url);
-        } else {
-            debug!("URL is invalid: {}", url);
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        rules.is_allowed(&path)
+    }
+
+    /// Fetches the robots.txt of an origin. A missing or unreachable robots.txt allows everything.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The scheme, host and port, e.g. `https://example.com`.
+    ///
+    /// # Returns
+    ///
+    /// The contents of robots.txt, or an empty string if there is none.
+    async fn fetch_robots(&self, origin: &str) -> String {
+        let robots_url = format!("{}/robots.txt", origin);
+        let response = self
+            .client
+            .get(&robots_url)
+            .timeout(self.config.fetch_timeout.unwrap_or(self.config.timeout))
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(text) => text,
+                Err(e) => {
+                    debug!("Ignoring unreadable {}: {}", robots_url, e);
+                    String::new()
+                }
+            },
+            Ok(response) => {
+                debug!("No robots.txt at {} (HTTP {})", robots_url, response.status());
+                String::new()
+            }
+            Err(e) => {
+                debug!("Could not fetch {}: {}", robots_url, e);
+                String::new()
+            }
         }
+    }
 
-        is_valid
+    /// Attempts to fetch content from a single URL, hedging slow requests when `hedge_after` is set.
+    ///
+    /// If the first request hasn't completed within `hedge_after`, a second identical request is
+    /// sent and whichever succeeds first wins. A failure of one request falls back to the other.
+    /// The second request is paced like any other: it takes its own rate limit permit and waits
+    /// for the request interval and the host's next politeness slot, while the first keeps running.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to fetch content from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `ScrapedContent`, or an error if the fetch fails.
+    async fn try_fetch_hedged(&self, url: &str) -> Result<ScrapedContent> {
+        let Some(hedge_after) = self.config.hedge_after else {
+            return self.try_fetch_content(url).await;
+        };
+
+        let first = self.try_fetch_content(url);
+        tokio::pin!(first);
+        tokio::select! {
+            result = &mut first => return result,
+            _ = sleep(hedge_after) => {}
+        }
+
+        let hedge_slot = async {
+            let permit = self.acquire_paced().await;
+            if let Some(ready_at) = self.reserve_request_time(url) {
+                sleep_until(ready_at).await;
+            }
+            permit
+        };
+        tokio::pin!(hedge_slot);
+        let _hedge_permit = tokio::select! {
+            result = &mut first => return result,
+            permit = &mut hedge_slot => permit,
+        };
+
+        debug!("{} hasn't responded within {:?}, sending a hedged request", url, hedge_after);
+        let hedge = self.try_fetch_content(url);
+        tokio::pin!(hedge);
+        tokio::select! {
+            result = &mut first => match result {
+                Ok(content) => Ok(content),
+                Err(_) => hedge.await,
+            },
+            result = &mut hedge => match result {
+                Ok(content) => Ok(content),
+                Err(_) => first.await,
+            },
+        }
     }
 
-    /// Extracts text content from the HTML document using predefined selectors.
+    /// Attempts to fetch content from a single URL.
     ///
     /// # Arguments
     ///
-    /// * `document` - The parsed HTML document.
+    /// * `url` - The URL to fetch content from.
     ///
     /// # Returns
     ///
-    /// A `Result` containing the extracted text content, or an error if no content is found.
-    fn extract_text(&self, document: &Html) -> Result<String> {
-        let selectors = [
-            "article p, article li",
-            "div.content p, div.content li",
-            "main p, main li",
-            ".documentation-content",
-            "div.markdown-body",
-            "div.mw-parser-output p",
-            "p, li",
-        ];
+    /// A `Result` containing the `ScrapedContent`, or an error if the fetch fails.
+    async fn try_fetch_content(&self, url: &str) -> Result<ScrapedContent> {
+        self.try_fetch_with(url, None).await
+    }
 
-        for selector_str in selectors {
-            if let Ok(selector) = Selector::parse(selector_str) {
-                let content: String = document
-                    .select(&selector)
-                    .map(|element| element.text().collect::<Vec<_>>().join(" "))
-                    .collect::<Vec<_>>()
-                    .join("\n");
+    /// Attempts to fetch content from a single URL using the given fetch strategy.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to fetch content from.
+    /// * `strategy` - The alternate strategy to use, or `None` for the default browser-like request.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `ScrapedContent`, or an error if the fetch fails.
+    async fn try_fetch_with(&self, url: &str, strategy: Option<&FetchStrategy>) -> Result<ScrapedContent> {
+        let mut current = url.to_string();
+        let mut refreshes = 0;
+        let mut followed_canonical = false;
+        let mut redirect_chain = Vec::new();
+        let mut robots_tag;
 
-                if !content.trim().is_empty() {
-                    return Ok(content.trim().to_string());
+        let (page, next_page) = loop {
+            if self.config.record_redirects {
+                redirect_chain.push(current.clone());
+            }
+
+            let (html, truncated) = match self.cache.as_ref().and_then(|cache| cache.get_entry(&current)) {
+                Some(entry) => {
+                    debug!("Using cached copy of {}", current);
+                    let field = |wanted: &'static str| {
+                        entry.fields.iter().filter(move |(name, _)| name == wanted).map(|(_, value)| value.clone())
+                    };
+                    if self.config.record_redirects {
+                        redirect_chain.extend(field(CACHED_REDIRECT_FIELD));
+                    }
+                    robots_tag = field(CACHED_ROBOTS_TAG_FIELD).next();
+                    if self.config.respect_robots && robots_tag.as_deref().is_some_and(robots_disallows) {
+                        debug!("Skipping cached {}: X-Robots-Tag {:?}", current, robots_tag);
+                        return Err(ScraperError::RobotsDisallowed(current));
+                    }
+                    (entry.body, false)
+                }
+                None => {
+                    let hops_start = redirect_chain.len();
+                    let download = self.download(&current, strategy, &mut redirect_chain).await?;
+                    robots_tag = download.robots_tag;
+                    if download.is_pdf {
+                        break (pdf_page(url, &download.body, robots_tag, redirect_chain)?, None);
+                    }
+
+                    // A truncated copy would later be served from the cache as if it were complete
+                    if let Some(cache) = self.cache.as_ref().filter(|_| !download.truncated) {
+                        let fields = cache_fields(&redirect_chain[hops_start..], robots_tag.as_deref());
+                        if let Err(e) = cache.put_entry(&current, &download.body, &fields) {
+                            warn!("Failed to cache {}: {}", current, e);
+                        }
+                    }
+                    (download.body, download.truncated)
+                }
+            };
+
+            let document = Html::parse_document(&html);
+
+            // Follow HTML meta-refresh redirects, which reqwest doesn't handle
+            if refreshes < self.config.max_meta_refreshes {
+                if let Some(target) = meta_refresh_target(&document, &current) {
+                    // The redirect policy only sees HTTP redirects, so refreshes are checked here
+                    if self.config.block_cross_domain_redirects && !same_host(url, &target) {
+                        debug!("Blocking cross-domain meta refresh to {}", target);
+                        return Err(ScraperError::ExtractionError(CROSS_DOMAIN_REDIRECT_BLOCKED.to_string()));
+                    }
+                    debug!("Following meta refresh from {} to {}", current, target);
+                    refreshes += 1;
+                    current = target;
+                    continue;
+                }
+            }
+
+            // AMP pages extract poorly, so fetch the page they declare as canonical instead
+            if self.config.prefer_canonical_urls && !followed_canonical {
+                if let Some(target) = amp_canonical_target(&document, &current) {
+                    debug!("Following AMP canonical link from {} to {}", current, target);
+                    followed_canonical = true;
+                    current = target;
+                    continue;
+                }
+            }
+
+            let mut content = self.page_content(&document, &current)?;
+            // The cut falls inside the last block, so only the blocks before it are complete
+            if truncated {
+                if let Some((complete, _)) = content.trim_end().rsplit_once('\n') {
+                    content.truncate(complete.trim_end().len());
                 }
             }
+            if self.config.include_image_captions {
+                append_image_captions(&mut content, &document);
+            }
+
+            let mut metadata = self.scraper.extract_metadata(&document, url);
+            insert_reading_stats(&mut metadata, &content);
+            if let Some(tag) = robots_tag {
+                metadata.insert("x-robots-tag".to_string(), tag);
+            }
+            if truncated {
+                metadata.insert("truncated".to_string(), "true".to_string());
+            }
+
+            let next_page = if self.config.follow_article_pagination {
+                next_page_target(&document, &current)
+            } else {
+                None
+            };
+
+            let page = ScrapedContent {
+                url: url.to_string(),
+                content,
+                metadata,
+                timestamp: chrono::Utc::now(),
+                raw_html: self.config.keep_raw_html.then_some(html),
+                redirect_chain,
+                published: published_date(&document).or_else(|| urls::date_from_url(url)),
+                faq: extract_faq(&document),
+            };
+            break (page, next_page);
+        };
+
+        // The parsed document isn't `Send`, so the following pages are fetched after it is dropped
+        match next_page {
+            Some(next_page) => Ok(self.append_article_pages(page, &current, next_page, strategy).await),
+            None => Ok(page),
         }
+    }
 
-        Err(ScraperError::ExtractionError("No content found".to_string()))
+    /// Fetches the following pages of a paginated article and appends their content.
+    ///
+    /// Pagination stops after `MAX_ARTICLE_PAGES` pages, at a page that was already visited, when
+    /// the download limit is reached, or at the first page that fails to download. The pages
+    /// fetched so far are kept in every case.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The content of the first page.
+    /// * `page_url` - The URL the first page was fetched from.
+    /// * `next_page` - The URL of the second page.
+    /// * `strategy` - The fetch strategy used for the first page.
+    ///
+    /// # Returns
+    ///
+    /// The `ScrapedContent` with the content of every fetched page, separated by line breaks.
+    async fn append_article_pages(
+        &self,
+        mut page: ScrapedContent,
+        page_url: &str,
+        next_page: String,
+        strategy: Option<&FetchStrategy>,
+    ) -> ScrapedContent {
+        let mut visited = vec![page_url.to_string()];
+        let mut next_page = Some(next_page);
+
+        while let Some(url) = next_page.take() {
+            if visited.len() >= MAX_ARTICLE_PAGES || visited.contains(&url) || self.bandwidth_exceeded() {
+                break;
+            }
+
+            if let Some(ready_at) = self.reserve_request_time(&url) {
+                sleep_until(ready_at).await;
+            }
+            let html = match self.download(&url, strategy, &mut Vec::new()).await {
+                Ok(download) if !download.is_pdf => download.body,
+                Ok(_) => {
+                    debug!("Stopping pagination of {} at {}: the page is a PDF", page.url, url);
+                    break;
+                }
+                Err(e) => {
+                    debug!("Stopping pagination of {} at {}: {}", page.url, url, e);
+                    break;
+                }
+            };
+
+            let document = Html::parse_document(&html);
+            if let Ok(content) = self.page_content(&document, &url) {
+                page.content.push('\n');
+                page.content.push_str(&content);
+            }
+            next_page = next_page_target(&document, &url);
+            visited.push(url);
+        }
+
+        if visited.len() > 1 {
+            debug!("Combined {} pages of {}", visited.len(), page.url);
+            page.metadata.insert("article_pages".to_string(), visited.len().to_string());
+            insert_reading_stats(&mut page.metadata, &page.content);
+        }
+        page
+    }
+
+    /// Downloads the HTML of a single URL, following HTTP redirects. The text of PDFs is extracted
+    /// instead, which requires the `pdf` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to download.
+    /// * `strategy` - The alternate strategy to use, or `None` for the default browser-like request.
+    /// * `redirect_chain` - The chain to append redirect hops to when `record_redirects` is set.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Download`, or an error if `respect_robots_txt` is set and
+    /// robots.txt disallows the page, the request fails, the response has a non-success status,
+    /// `respect_robots` is set and the `X-Robots-Tag` header disallows harvesting the page, or the
+    /// page is a PDF whose text can't be extracted.
+    async fn download(
+        &self,
+        url: &str,
+        strategy: Option<&FetchStrategy>,
+        redirect_chain: &mut Vec<String>,
+    ) -> Result<Download> {
+        let user_agent = match strategy {
+            Some(FetchStrategy::UserAgent(user_agent)) => Some(user_agent.clone()),
+            _ => self.pick_user_agent(),
+        };
+
+        // The robots.txt group is picked by the user agent the site actually sees
+        if self.config.respect_robots_txt {
+            let sent = user_agent.as_deref().unwrap_or(self.config.user_agent.as_str());
+            if !self.robots_allowed(url, sent).await {
+                debug!("Skipping {}: disallowed by robots.txt for {}", url, sent);
+                return Err(ScraperError::DisallowedByRobots(url.to_string()));
+            }
+        }
+
+        let request = |client: &Client, target: &str| {
+            let mut request = client
+                .get(target)
+                .timeout(self.config.fetch_timeout.unwrap_or(self.config.timeout));
+            if strategy != Some(&FetchStrategy::MinimalHeaders) {
+                request = request
+                    .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8")
+                    .header("Accept-Language", "en-US,en;q=0.5")
+                    .header("Connection", "keep-alive")
+                    .header("Upgrade-Insecure-Requests", "1")
+                    .header("Sec-Fetch-Dest", "document")
+                    .header("Sec-Fetch-Mode", "navigate")
+                    .header("Sec-Fetch-Site", "none")
+                    .header("Sec-Fetch-User", "?1");
+            }
+            if let Some(user_agent) = &user_agent {
+                request = request.header(reqwest::header::USER_AGENT, user_agent);
+            }
+            request
+        };
+
+        let started = Instant::now();
+        let response = match &self.redirect_client {
+            Some(client) => self.follow_redirects(client, url, request, redirect_chain).await?,
+            None => request(&self.client, url).send().await.map_err(|e| {
+                if is_cross_domain_redirect(&e) {
+                    ScraperError::ExtractionError(CROSS_DOMAIN_REDIRECT_BLOCKED.to_string())
+                } else {
+                    ScraperError::RequestError(e)
+                }
+            })?,
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                let delay = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(retry_after_delay);
+                if let (Some(delay), Some(host)) = (delay, urls::host(url)) {
+                    debug!("{} is rate limited, retrying after {:?}", url, delay);
+                    let ready_at = Instant::now() + delay.min(MAX_RETRY_AFTER);
+                    self.host_schedules
+                        .lock()
+                        .unwrap()
+                        .entry(host)
+                        .and_modify(|schedule| schedule.ready_at = ready_at)
+                        .or_insert(HostSchedule { ready_at, politeness_delay: Duration::ZERO });
+                }
+            }
+            debug!("Fetching {} failed with HTTP {}", url, status);
+            return Err(ScraperError::HttpStatus(status.as_u16()));
+        }
+
+        let robots_values: Vec<&str> = response
+            .headers()
+            .get_all("x-robots-tag")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        let robots_tag = (!robots_values.is_empty()).then(|| robots_values.join(", "));
+
+        if let Some(tag) = robots_tag.as_deref().filter(|tag| robots_disallows(tag)) {
+            if self.config.respect_robots {
+                debug!("Skipping {}: X-Robots-Tag {}", url, tag);
+                return Err(ScraperError::RobotsDisallowed(url.to_string()));
+            }
+        }
+
+        let is_pdf = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(is_pdf_content_type);
+        let (mut bytes, truncated) = self.read_body(response).await?;
+        self.bytes_downloaded.fetch_add(bytes.len(), Ordering::Relaxed);
+        self.record_response_time(url, started.elapsed());
+
+        let body = if is_pdf {
+            if truncated {
+                return Err(ScraperError::ExtractionError(format!(
+                    "The PDF {} is larger than max_content_bytes ({} bytes)",
+                    url, self.config.max_content_bytes
+                )));
+            }
+            pdf_text(url, &bytes)?
+        } else {
+            if truncated {
+                debug!("Truncated {} at {} bytes", url, bytes.len());
+                truncate_utf8(&mut bytes);
+            }
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+
+        Ok(Download { body, is_pdf, truncated, robots_tag })
     }
-}
\ No newline at end of file
+
+    /// Sends a request with a client that doesn't follow redirects and follows them here instead,
+    /// appending every hop to the redirect chain. Like the redirect policy of the shared client, up
+    /// to `MAX_REDIRECTS` redirects are followed and redirects to a different host are rejected
+    /// when `block_cross_domain_redirects` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client to send the requests with, which must not follow redirects.
+    /// * `url` - The URL to request first.
+    /// * `request` - Builds the request for a URL with the client.
+    /// * `redirect_chain` - The chain to append redirect hops to.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the first response that isn't a redirect, or an error if a request
+    /// fails, a redirect is blocked or there are too many redirects.
+    async fn follow_redirects(
+        &self,
+        client: &Client,
+        url: &str,
+        request: impl Fn(&Client, &str) -> reqwest::RequestBuilder,
+        redirect_chain: &mut Vec<String>,
+    ) -> Result<reqwest::Response> {
+        let mut current = url.to_string();
+
+        for _ in 0..=MAX_REDIRECTS {
+            let response = request(client, &current).send().await?;
+            let target = response
+                .status()
+                .is_redirection()
+                .then(|| response.headers().get(reqwest::header::LOCATION))
+                .flatten()
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok());
+            let Some(target) = target else {
+                return Ok(response);
+            };
+
+            if self.config.block_cross_domain_redirects && !same_host(url, target.as_str()) {
+                debug!("Blocking cross-domain redirect to {}", target);
+                return Err(ScraperError::ExtractionError(CROSS_DOMAIN_REDIRECT_BLOCKED.to_string()));
+            }
+            current = target.to_string();
+            redirect_chain.push(current.clone());
+        }
+
+        Err(ScraperError::ExtractionError(format!("Too many redirects fetching {}", url)))
+    }
+
+    /// Reads the body of a response as it streams in, stopping once `max_content_bytes` bytes have
+    /// been read so huge pages are never buffered whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The response to read.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the bytes read and whether the body was cut off, or an error if
+    /// reading the body fails.
+    async fn read_body(&self, response: reqwest::Response) -> Result<(Vec<u8>, bool)> {
+        let limit = self.config.max_content_bytes;
+        let mut body = Vec::new();
+        let mut chunks = response.bytes_stream();
+
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            if limit > 0 && body.len() + chunk.len() > limit {
+                body.extend_from_slice(&chunk[..limit - body.len()]);
+                return Ok((body, true));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok((body, false))
+    }
+
+    /// Extracts search results (URL, title and snippet) from the HTML document.
+    ///
+    /// Extraction stops as soon as `limit` distinct valid URLs have been found, so the remaining
+    /// selector patterns aren't run over large result pages. Duplicates don't count towards the
+    /// limit, and the results kept are the first ones found, in the page's ranking order.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    /// * `limit` - The number of distinct results after which extraction stops.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a vector of `SearchHit`, or an error if no results are found.
+    fn extract_results(&self, document: &Html, limit: usize) -> Result<Vec<SearchHit>> {
+        let selectors = &self.config.result_selectors;
+        let mut all_results = Vec::new();
+        let mut seen = HashSet::new();
+        let mut per_domain = HashMap::new();
+
+        'patterns: for pattern in &selectors.links {
+            debug!("Trying selector pattern: {}", pattern);
+
+            if let Ok(selector) = Selector::parse(pattern) {
+                for link in document.select(&selector) {
+                    let Some(href) = link.value().attr("href") else {
+                        continue;
+                    };
+                    debug!("Found raw URL: {}", href);
+
+                    let Some(clean_url) = self.clean_google_url(href) else {
+                        debug!("Could not clean URL: {}", href);
+                        continue;
+                    };
+                    if !self.is_valid_url(&clean_url) {
+                        debug!("Invalid URL: {}", clean_url);
+                        continue;
+                    }
+
+                    // Duplicates are skipped before the title and snippet are looked up
+                    if !seen.insert(clean_url.clone()) || !self.within_domain_cap(&mut per_domain, &clean_url) {
+                        continue;
+                    }
+                    debug!("Valid URL found: {}", clean_url);
+                    let container = result_container(link, &selectors.containers);
+                    all_results.push(SearchHit {
+                        url: clean_url,
+                        title: result_title(link, container),
+                        snippet: container.and_then(|container| result_snippet(container, &selectors.snippets)),
+                    });
+
+                    if all_results.len() >= limit {
+                        debug!("Found {} results, skipping the rest of the page", limit);
+                        break 'patterns;
+                    }
+                }
+            }
+        }
+
+        if all_results.is_empty() {
+            error!("No valid URLs found in the response");
+        } else {
+            for (i, result) in all_results.iter().enumerate() {
+                debug!("URL {}: {}", i + 1, result.url);
+            }
+        }
+
+        Ok(all_results)
+    }
+
+    /// Extracts search results (URL, title and snippet) from a DuckDuckGo HTML results page.
+    ///
+    /// Results are read from the `a.result__a` anchors, skipping ads. Like `extract_results`,
+    /// extraction stops once `limit` distinct valid URLs have been found.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    /// * `limit` - The number of distinct results after which extraction stops.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a vector of `SearchHit` in page order.
+    fn extract_duckduckgo_results(&self, document: &Html, limit: usize) -> Result<Vec<SearchHit>> {
+        let (Ok(link_selector), Ok(snippet_selector)) =
+            (Selector::parse("a.result__a"), Selector::parse(".result__snippet"))
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut results = Vec::new();
+        let mut seen = HashSet::new();
+        let mut per_domain = HashMap::new();
+
+        for link in document.select(&link_selector) {
+            let container = link.ancestors().filter_map(ElementRef::wrap).find(|element| {
+                element.value().classes().any(|class| class == "result")
+            });
+            if container.is_some_and(|c| c.value().classes().any(|class| class == "result--ad")) {
+                continue;
+            }
+
+            let Some(href) = link.value().attr("href") else {
+                continue;
+            };
+            let Some(clean_url) = self.clean_duckduckgo_url(href) else {
+                debug!("Could not clean URL: {}", href);
+                continue;
+            };
+            if !self.is_valid_url(&clean_url)
+                || !seen.insert(clean_url.clone())
+                || !self.within_domain_cap(&mut per_domain, &clean_url)
+            {
+                continue;
+            }
+
+            results.push(SearchHit {
+                url: clean_url,
+                title: Some(element_text(link)).filter(|title| !title.is_empty()),
+                snippet: container
+                    .and_then(|c| c.select(&snippet_selector).next())
+                    .map(element_text)
+                    .filter(|snippet| !snippet.is_empty()),
+            });
+            if results.len() >= limit {
+                break;
+            }
+        }
+
+        if results.is_empty() {
+            error!("No valid URLs found in the response");
+        }
+        Ok(results)
+    }
+
+    /// Parses the results of a SearXNG JSON response.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status of the response.
+    /// * `body` - The response body.
+    /// * `limit` - The maximum number of distinct results to return.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a vector of `SearchHit` in ranked order, or a `SearchError` if the
+    /// instance refused the request or has JSON output disabled.
+    fn parse_searxng_results(&self, status: StatusCode, body: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        // Instances without the json format enabled answer 403 with an HTML page
+        let json_disabled = || {
+            ScraperError::SearchError(format!(
+                "The SearXNG instance at {} has JSON output disabled; add `json` to `search.formats` in its settings",
+                self.search_base_url
+            ))
+        };
+        if status == StatusCode::FORBIDDEN {
+            return Err(json_disabled());
+        }
+        if !status.is_success() {
+            return Err(ScraperError::SearchError(format!("SearXNG returned HTTP {}", status)));
+        }
+        let response: SearxngResponse = serde_json::from_str(body).map_err(|_| json_disabled())?;
+
+        let mut seen = HashSet::new();
+        let mut per_domain = HashMap::new();
+        let non_empty = |text: Option<String>| text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        Ok(response
+            .results
+            .into_iter()
+            .filter(|result| {
+                self.is_valid_url(&result.url)
+                    && seen.insert(result.url.clone())
+                    && self.within_domain_cap(&mut per_domain, &result.url)
+            })
+            .take(limit)
+            .map(|result| SearchHit {
+                url: result.url,
+                title: non_empty(result.title),
+                snippet: non_empty(result.content),
+            })
+            .collect())
+    }
+
+    /// Cleans a DuckDuckGo redirect URL (`//duckduckgo.com/l/?uddg=...`) to extract the actual URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The DuckDuckGo redirect URL.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the cleaned URL, or `None` if the URL could not be cleaned.
+    fn clean_duckduckgo_url(&self, url: &str) -> Option<String> {
+        // Result links are protocol-relative
+        let absolute = if url.starts_with("//") { format!("https:{}", url) } else { url.to_string() };
+        let parsed = url::Url::parse(&absolute).ok()?;
+
+        if parsed.host_str().is_some_and(|host| host.ends_with("duckduckgo.com")) {
+            return parsed
+                .query_pairs()
+                .find(|(key, _)| key == "uddg")
+                .map(|(_, target)| target.into_owned());
+        }
+
+        absolute.starts_with("http").then_some(absolute)
+    }
+
+    /// Cleans a Google redirect URL to extract the actual URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The Google redirect URL.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the cleaned URL, or `None` if the URL could not be cleaned.
+    fn clean_google_url(&self, url: &str) -> Option<String> {
+        debug!("Cleaning URL: {}", url);
+
+        // Handle Google redirect URLs
+        if url.starts_with("/url?") || url.contains("/url?") {
+            let url_str = url.replace("/url?", "");
+            if let Some(query) = url_str.split('&').find(|&q| q.starts_with("q=")) {
+                let clean = query.replace("q=", "");
+                let decoded = urlencoding::decode(&clean).ok()?.into_owned();
+                debug!("Cleaned redirect URL: {}", decoded);
+                return Some(decoded);
+            }
+        }
+
+        // Handle absolute URLs
+        if url.starts_with("http") {
+            debug!("Found absolute URL: {}", url);
+            return Some(url.to_string());
+        }
+
+        debug!("URL could not be cleaned: {}", url);
+        None
+    }
+
+    /// Picks the user agent for a single request from the configured user agent mode.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the user agent to send, or `None` to keep the client's fixed user agent.
+    fn pick_user_agent(&self) -> Option<String> {
+        pick_user_agent(&self.config.user_agent_mode, &mut *self.rng.lock().unwrap())
+    }
+
+    /// Checks if a URL is valid.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the URL is valid, `false` otherwise.
+    fn is_valid_url(&self, url: &str) -> bool {
+        let is_valid = url::Url::parse(url)
+            .map(|parsed| {
+                parsed.scheme() == "https"
+                    && !is_google_internal(&parsed)
+                    && !INVALID_PATHS.iter().any(|&path| has_path_prefix(parsed.path(), path))
+            })
+            .unwrap_or(false)
+            && self.domain_allowed(url);
+
+        if is_valid {
+            debug!("URL is valid: {}", url);
+        } else {
+            debug!("URL is invalid: {}", url);
+        }
+
+        is_valid
+    }
+
+    /// Extracts the main content of a page as configured: as Markdown with `markdown_content`,
+    /// otherwise with the site rule for its host, falling back to the `extraction_strategy`.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    /// * `url` - The URL of the page.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the extracted content, or an error if no content is found.
+    fn page_content(&self, document: &Html, url: &str) -> Result<String> {
+        if self.config.markdown_content {
+            return self.scraper.extract_markdown_content(document, url);
+        }
+        if let Some(content) = self.scraper.extract_site_content(document, url) {
+            return Ok(content);
+        }
+
+        match self.config.extraction_strategy {
+            ExtractionStrategy::Selectors => self.extract_text(document),
+            ExtractionStrategy::Readability => self
+                .scraper
+                .extract_readable_content(document)
+                .or_else(|_| self.extract_text(document)),
+        }
+    }
+
+    /// Extracts text content from the HTML document using predefined selectors.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The parsed HTML document.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the extracted text content, or an error if no content is found.
+    fn extract_text(&self, document: &Html) -> Result<String> {
+        let selectors = [
+            "article p, article li, article dl",
+            "div.content p, div.content li, div.content dl",
+            "main p, main li, main dl",
+            ".documentation-content",
+            "div.markdown-body",
+            "div.mw-parser-output p",
+            "p, li, dl",
+        ];
+
+        for selector_str in selectors {
+            if let Ok(selector) = Selector::parse(selector_str) {
+                let content: String = document
+                    .select(&selector)
+                    .map(|element| drop_short_blocks(&content_text(element), self.config.min_paragraph_words))
+                    .filter(|text| !text.trim().is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                if !content.trim().is_empty() {
+                    return Ok(content.trim().to_string());
+                }
+            }
+        }
+
+        Err(ScraperError::ExtractionError("No content found".to_string()))
+    }
+}
+
+/// Picks the user agent for a single request.
+///
+/// # Arguments
+///
+/// * `mode` - The configured user agent mode.
+/// * `rng` - The random number generator used to pick from a list.
+///
+/// # Returns
+///
+/// An `Option` containing the user agent to send, or `None` to keep the client's fixed user agent.
+fn pick_user_agent(mode: &UserAgentMode, rng: &mut impl Rng) -> Option<String> {
+    match mode {
+        UserAgentMode::Fixed => None,
+        UserAgentMode::List(user_agents) => user_agents.choose(rng).cloned(),
+        UserAgentMode::RealisticRandom => REALISTIC_USER_AGENTS.choose(rng).map(|ua| ua.to_string()),
+    }
+}
+
+/// Checks a search query for problems that would produce a malformed search URL or no results.
+///
+/// Rejects empty queries, queries too long for a search URL, unbalanced quotes or parentheses,
+/// operators without a value (`site:`) and dangling `OR`/`AND`.
+///
+/// # Arguments
+///
+/// * `query` - The search query.
+///
+/// # Returns
+///
+/// A `Result` that is `Ok` if the query looks usable, or a `SearchError` describing the problem.
+pub fn validate_query(query: &str) -> Result<()> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(ScraperError::SearchError("Search query is empty".to_string()));
+    }
+
+    let encoded_length = urlencoding::encode(query).len();
+    if encoded_length > MAX_ENCODED_QUERY_LENGTH {
+        return Err(ScraperError::SearchError(format!(
+            "Search query is too long ({} encoded characters, maximum is {})",
+            encoded_length, MAX_ENCODED_QUERY_LENGTH
+        )));
+    }
+
+    if !query.matches('"').count().is_multiple_of(2) {
+        return Err(ScraperError::SearchError(
+            "Search query has an unbalanced quote".to_string(),
+        ));
+    }
+
+    let mut depth = 0i32;
+    for c in query.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            break;
+        }
+    }
+    if depth != 0 {
+        return Err(ScraperError::SearchError(
+            "Search query has unbalanced parentheses".to_string(),
+        ));
+    }
+
+    let words: Vec<&str> = query.split_whitespace().collect();
+    for word in &words {
+        let word = word.trim_start_matches('-');
+        if let Some(operator) = word.strip_suffix(':') {
+            if SEARCH_OPERATORS.contains(&operator.to_lowercase().as_str()) {
+                return Err(ScraperError::SearchError(format!(
+                    "Search operator '{}' is missing a value",
+                    word
+                )));
+            }
+        }
+    }
+
+    let is_boolean = |word: &&str| matches!(*word, "OR" | "AND" | "|");
+    if words.first().is_some_and(is_boolean) || words.last().is_some_and(is_boolean) {
+        return Err(ScraperError::SearchError(
+            "Search query starts or ends with a boolean operator".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Broadens a search query that found nothing by removing the syntax that narrows it.
+///
+/// Quotes, parentheses, `OR`/`AND`, excluded terms (`-word`) and operators such as `site:` or
+/// `intitle:` are removed. If the query has none of these, its last word is dropped instead, as
+/// long as at least two words remain.
+///
+/// # Arguments
+///
+/// * `query` - The search query to broaden.
+///
+/// # Returns
+///
+/// An `Option` containing the broadened query, or `None` if it can't be broadened any further.
+pub fn broaden_query(query: &str) -> Option<String> {
+    let original: Vec<&str> = query.split_whitespace().collect();
+
+    let stripped = query.replace(['"', '(', ')'], " ");
+    let words: Vec<&str> = stripped
+        .split_whitespace()
+        .filter(|word| !matches!(*word, "OR" | "AND" | "|"))
+        .filter(|word| !(word.starts_with('-') && word.len() > 1))
+        .filter(|word| {
+            !word.split_once(':').is_some_and(|(operator, _)| {
+                SEARCH_OPERATORS.contains(&operator.to_lowercase().as_str())
+                    || matches!(operator, "before" | "after")
+            })
+        })
+        .collect();
+
+    let broadened = if words.is_empty() {
+        return None;
+    } else if words != original {
+        words
+    } else if words.len() > 2 {
+        words[..words.len() - 1].to_vec()
+    } else {
+        return None;
+    };
+
+    Some(broadened.join(" "))
+}
+
+/// Computes the delay before the next request, applying random jitter when configured.
+///
+/// A perfectly regular interval is itself a bot fingerprint, so the delay is offset by a value
+/// drawn uniformly from `-jitter..=jitter`, clamped so it never goes below zero.
+///
+/// # Arguments
+///
+/// * `interval` - The base interval derived from the rate limit.
+/// * `jitter` - The maximum offset to apply, if any.
+/// * `rng` - The random number generator to draw the offset from.
+///
+/// # Returns
+///
+/// The delay to wait before the next request.
+fn pacing_delay(interval: Duration, jitter: Option<Duration>, rng: &mut impl Rng) -> Duration {
+    match jitter {
+        Some(jitter) if !jitter.is_zero() => {
+            let max_offset = jitter.as_secs_f64();
+            let offset = rng.gen_range(-max_offset..=max_offset);
+            Duration::from_secs_f64((interval.as_secs_f64() + offset).max(0.0))
+        }
+        _ => interval,
+    }
+}
+
+/// Finds the target of a `<meta http-equiv="refresh">` redirect in a document.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+/// * `page_url` - The URL the document was fetched from, used to resolve relative targets.
+///
+/// # Returns
+///
+/// An `Option` containing the absolute target URL, or `None` if the page has no refresh redirect.
+fn meta_refresh_target(document: &Html, page_url: &str) -> Option<String> {
+    let selector = Selector::parse("meta[http-equiv][content]").ok()?;
+
+    let content = document
+        .select(&selector)
+        .find(|meta| {
+            meta.value()
+                .attr("http-equiv")
+                .is_some_and(|value| value.eq_ignore_ascii_case("refresh"))
+        })?
+        .value()
+        .attr("content")?;
+
+    // The content looks like `0; url=https://example.com/` (the `url=` part may be quoted)
+    let (_, target) = content.split_once(';')?;
+    let target = target.trim();
+    let target = target
+        .get(..4)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("url="))
+        .map_or(target, |_| &target[4..]);
+    let target = target.trim().trim_matches(|c| c == '\'' || c == '"');
+
+    urls::resolve_url(&urls::base_url(document, page_url), target)
+}
+
+/// Finds the link to the next page of a paginated article.
+///
+/// A `rel="next"` link or anchor is preferred; otherwise an anchor whose text is "Next" (arrows
+/// such as `»` are ignored) is used. Links to other hosts are never followed.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+/// * `page_url` - The URL the document was fetched from.
+///
+/// # Returns
+///
+/// An `Option` containing the absolute URL of the next page, or `None` if the page has none.
+fn next_page_target(document: &Html, page_url: &str) -> Option<String> {
+    let rel_next = Selector::parse("link[rel~='next'][href], a[rel~='next'][href]").ok()?;
+    let anchors = Selector::parse("a[href]").ok()?;
+
+    let href = document
+        .select(&rel_next)
+        .next()
+        .or_else(|| {
+            document.select(&anchors).find(|anchor| {
+                let text = anchor.text().collect::<String>().to_lowercase();
+                let text = text.trim_matches(|c: char| c.is_whitespace() || matches!(c, '»' | '›' | '→' | '>'));
+                NEXT_PAGE_TEXTS.contains(&text)
+            })
+        })?
+        .value()
+        .attr("href")?;
+
+    urls::resolve_url(&urls::base_url(document, page_url), href)
+        .filter(|target| target != page_url && urls::host(target) == urls::host(page_url))
+}
+
+/// Checks whether an `X-Robots-Tag` header value asks for the page not to be indexed or archived.
+///
+/// Directives may be scoped to a crawler (`googlebot: noindex`); scoped directives are honoured
+/// too, since this scraper doesn't identify as any particular crawler.
+///
+/// # Arguments
+///
+/// * `value` - The header value, e.g. `noindex, nofollow`.
+///
+/// # Returns
+///
+/// `true` if the value contains `noindex`, `noarchive` or `none`, `false` otherwise.
+fn robots_disallows(value: &str) -> bool {
+    value.split(',').any(|directive| {
+        let directive = directive.trim();
+        let directive = match directive.split_once(':') {
+            Some((name, rest)) if !name.trim().eq_ignore_ascii_case("unavailable_after") => rest,
+            _ => directive,
+        };
+        matches!(
+            directive.trim().to_lowercase().as_str(),
+            "noindex" | "noarchive" | "none"
+        )
+    })
+}
+
+/// Drops the incomplete UTF-8 sequence, if any, left at the end of a body that was cut off.
+///
+/// # Arguments
+///
+/// * `bytes` - The body to trim.
+fn truncate_utf8(bytes: &mut Vec<u8>) {
+    let tail = bytes.len().saturating_sub(4)..bytes.len();
+    let Some(start) = tail.rev().find(|&i| bytes[i] & 0xC0 != 0x80) else {
+        return;
+    };
+    let width = match bytes[start] {
+        byte if byte >= 0xF0 => 4,
+        byte if byte >= 0xE0 => 3,
+        byte if byte >= 0xC0 => 2,
+        _ => 1,
+    };
+    if start + width > bytes.len() {
+        bytes.truncate(start);
+    }
+}
+
+/// Checks whether a `Content-Type` header value is that of a PDF document.
+///
+/// # Arguments
+///
+/// * `value` - The header value, e.g. `application/pdf; charset=binary`.
+///
+/// # Returns
+///
+/// `true` if the media type is `application/pdf`, `false` otherwise.
+fn is_pdf_content_type(value: &str) -> bool {
+    value
+        .split(';')
+        .next()
+        .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("application/pdf"))
+}
+
+/// Extracts the text of a PDF document.
+///
+/// # Arguments
+///
+/// * `url` - The URL the document was downloaded from.
+/// * `bytes` - The contents of the document.
+///
+/// # Returns
+///
+/// A `Result` containing the text of the document, or an `ExtractionError` if it can't be parsed.
+#[cfg(feature = "pdf")]
+fn pdf_text(url: &str, bytes: &[u8]) -> Result<String> {
+    pdf_extract::extract_text_from_mem(bytes)
+        .map_err(|e| ScraperError::ExtractionError(format!("Failed to extract the text of the PDF {}: {}", url, e)))
+}
+
+/// Rejects a PDF document, whose text can only be extracted with the `pdf` feature.
+///
+/// # Arguments
+///
+/// * `url` - The URL the document was downloaded from.
+/// * `_bytes` - The contents of the document.
+///
+/// # Returns
+///
+/// An `ExtractionError` naming the feature to enable.
+#[cfg(not(feature = "pdf"))]
+fn pdf_text(url: &str, _bytes: &[u8]) -> Result<String> {
+    Err(ScraperError::ExtractionError(format!(
+        "{} is a PDF, which requires building with the `pdf` feature",
+        url
+    )))
+}
+
+/// Builds the scraped content of a PDF document from its extracted text.
+///
+/// The lines of the text are trimmed, with the blank ones dropped. PDFs have no HTML, so
+/// `raw_html` is never kept and the publication date only comes from the URL.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the document.
+/// * `text` - The text extracted from the document.
+/// * `robots_tag` - The `X-Robots-Tag` header of the response, if any.
+/// * `redirect_chain` - The redirect hops recorded while fetching the document.
+///
+/// # Returns
+///
+/// A `Result` containing the `ScrapedContent`, or an `ExtractionError` if the document has no text.
+fn pdf_page(url: &str, text: &str, robots_tag: Option<String>, redirect_chain: Vec<String>) -> Result<ScrapedContent> {
+    let content = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if content.is_empty() {
+        return Err(ScraperError::ExtractionError(format!("The PDF {} has no text", url)));
+    }
+
+    let mut metadata = HashMap::from([("content_type".to_string(), "application/pdf".to_string())]);
+    insert_reading_stats(&mut metadata, &content);
+    if let Some(tag) = robots_tag {
+        metadata.insert("x-robots-tag".to_string(), tag);
+    }
+
+    Ok(ScrapedContent {
+        url: url.to_string(),
+        content,
+        metadata,
+        timestamp: chrono::Utc::now(),
+        raw_html: None,
+        redirect_chain,
+        published: urls::date_from_url(url),
+        faq: Vec::new(),
+    })
+}
+
+/// Checks whether a URL points at one of Google's own pages rather than a search result.
+///
+/// These are search, redirect and image result pages on `google.*` hosts, account pages and
+/// cached copies.
+///
+/// # Arguments
+///
+/// * `url` - The parsed URL.
+///
+/// # Returns
+///
+/// `true` if the URL is Google-internal, `false` otherwise.
+fn is_google_internal(url: &url::Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+
+    if host.starts_with("accounts.google.") || host == "webcache.googleusercontent.com" {
+        return true;
+    }
+    host.starts_with("google.")
+        && GOOGLE_INTERNAL_PATHS.iter().any(|&path| has_path_prefix(url.path(), path))
+}
+
+/// Checks whether a URL path is `prefix` or lies below it.
+///
+/// # Arguments
+///
+/// * `path` - The URL path, e.g. `/settings/profile`.
+/// * `prefix` - The path prefix, e.g. `/settings`.
+///
+/// # Returns
+///
+/// `true` if the path is the prefix itself or one of its sub-paths, `false` otherwise.
+fn has_path_prefix(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .map(|rest| rest.is_empty() || rest.starts_with('/'))
+        .unwrap_or(false)
+}
+
+/// Parses a `Retry-After` header value, given either in seconds or as an HTTP date.
+///
+/// # Arguments
+///
+/// * `value` - The header value, e.g. `120` or `Wed, 21 Oct 2015 07:28:00 GMT`.
+///
+/// # Returns
+///
+/// An `Option` containing the delay to wait, or `None` if the value is malformed or the date has passed.
+fn retry_after_delay(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+}
+
+/// Checks whether an HTTP status means retrying the same request is pointless.
+///
+/// # Arguments
+///
+/// * `status` - The HTTP status code.
+///
+/// # Returns
+///
+/// `true` for client errors other than 408 (Request Timeout) and 429 (Too Many Requests),
+/// `false` otherwise.
+fn is_permanent_status(status: u16) -> bool {
+    (400..500).contains(&status) && status != 408 && status != 429
+}
+
+/// Finds the canonical page of an AMP document.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+/// * `page_url` - The URL the document was fetched from, used to resolve relative links.
+///
+/// # Returns
+///
+/// An `Option` containing the canonical URL, or `None` if the document isn't an AMP page or
+/// already is its own canonical page.
+fn amp_canonical_target(document: &Html, page_url: &str) -> Option<String> {
+    let root = document.root_element();
+    if root.value().attr("amp").is_none() && root.value().attr("⚡").is_none() {
+        return None;
+    }
+
+    let selector = Selector::parse("link[rel='canonical'][href]").ok()?;
+    let href = document.select(&selector).next()?.value().attr("href")?;
+
+    urls::resolve_url(&urls::base_url(document, page_url), href).filter(|target| target != page_url)
+}
+
+/// Finds the element wrapping the search result a link belongs to.
+///
+/// # Arguments
+///
+/// * `link` - The result link element.
+/// * `classes` - The classes of a result container.
+///
+/// # Returns
+///
+/// An `Option` containing the nearest result container, or `None` if the link is not inside one.
+fn result_container<'a>(link: ElementRef<'a>, classes: &[String]) -> Option<ElementRef<'a>> {
+    link.ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|element| {
+            element
+                .value()
+                .classes()
+                .any(|class| classes.iter().any(|container| container == class))
+        })
+}
+
+/// Extracts the title of a search result.
+///
+/// Prefers the `h3` inside the link, then the `h3` inside the result container, then the link text.
+///
+/// # Arguments
+///
+/// * `link` - The result link element.
+/// * `container` - The result container, if any.
+///
+/// # Returns
+///
+/// An `Option` containing the title, or `None` if no title text was found.
+fn result_title(link: ElementRef<'_>, container: Option<ElementRef<'_>>) -> Option<String> {
+    let heading = Selector::parse("h3").ok()?;
+
+    link.select(&heading)
+        .next()
+        .or_else(|| container.and_then(|c| c.select(&heading).next()))
+        .map(element_text)
+        .filter(|title| !title.is_empty())
+        .or_else(|| Some(element_text(link)).filter(|text| !text.is_empty()))
+}
+
+/// Extracts the snippet of a search result.
+///
+/// Tries each of the snippet selectors, falling back to the container's text minus its title.
+///
+/// # Arguments
+///
+/// * `container` - The result container.
+/// * `selectors` - The snippet selectors, in order of preference.
+///
+/// # Returns
+///
+/// An `Option` containing the snippet, or `None` if no snippet text was found.
+fn result_snippet(container: ElementRef<'_>, selectors: &[String]) -> Option<String> {
+    for pattern in selectors {
+        if let Ok(selector) = Selector::parse(pattern) {
+            let snippet = container
+                .select(&selector)
+                .map(element_text)
+                .find(|text| !text.is_empty());
+
+            if snippet.is_some() {
+                return snippet;
+            }
+        }
+    }
+
+    // Fall back to the container text without the title
+    let mut text = element_text(container);
+    if let Some(title) = Selector::parse("h3")
+        .ok()
+        .and_then(|heading| container.select(&heading).next())
+        .map(element_text)
+    {
+        text = text.replacen(&title, "", 1);
+    }
+
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// Collects the text of an element with whitespace normalized.
+///
+/// # Arguments
+///
+/// * `element` - The element to collect text from.
+///
+/// # Returns
+///
+/// The element's text content.
+fn element_text(element: ElementRef<'_>) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Builds the fields stored with a cached page, so a cache hit reports the same redirect hops and
+/// `X-Robots-Tag` header as the original fetch.
+///
+/// # Arguments
+///
+/// * `hops` - The redirect hops recorded while downloading the page.
+/// * `robots_tag` - The `X-Robots-Tag` header of the response, if any.
+///
+/// # Returns
+///
+/// The name and value pairs of the cache entry fields.
+fn cache_fields(hops: &[String], robots_tag: Option<&str>) -> Vec<(String, String)> {
+    hops.iter()
+        .map(|hop| (CACHED_REDIRECT_FIELD.to_string(), hop.clone()))
+        .chain(robots_tag.map(|tag| (CACHED_ROBOTS_TAG_FIELD.to_string(), tag.to_string())))
+        .collect()
+}
+
+/// Builds an HTTP client for fetching pages and search results.
+///
+/// # Arguments
+///
+/// * `config` - The configuration settings for the scraper.
+/// * `redirect` - The redirect policy of the client.
+///
+/// # Returns
+///
+/// A `Result` containing the `Client`, or an error if the proxy is invalid or the client could not be built.
+fn build_client(config: &ScraperConfig, redirect: Policy) -> Result<Client> {
+    let mut client = Client::builder()
+        .user_agent(&config.user_agent)
+        .timeout(config.timeout)
+        // Accept-Encoding is set by the client from these, so every advertised encoding is decoded
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .redirect(redirect);
+    if let Some(proxy) = &config.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| ScraperError::ConfigError(format!("Invalid proxy URL: {}", e)))?;
+        client = client.proxy(proxy);
+    }
+    client.build().map_err(ScraperError::RequestError)
+}
+
+/// Builds the redirect policy for the HTTP client.
+///
+/// # Arguments
+///
+/// * `config` - The configuration settings for the scraper.
+///
+/// # Returns
+///
+/// A redirect `Policy` that follows up to `MAX_REDIRECTS` redirects, rejecting redirects to a
+/// different host when `block_cross_domain_redirects` is set.
+fn redirect_policy(config: &ScraperConfig) -> Policy {
+    let block_cross_domain = config.block_cross_domain_redirects;
+    if !block_cross_domain {
+        return Policy::limited(MAX_REDIRECTS);
+    }
+
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+
+        let original = attempt.previous().first();
+
+        if block_cross_domain && original.and_then(|url| url.host_str()) != attempt.url().host_str() {
+            debug!("Blocking cross-domain redirect to {}", attempt.url());
+            return attempt.error(CROSS_DOMAIN_REDIRECT_BLOCKED);
+        }
+
+        attempt.follow()
+    })
+}
+
+/// Checks whether two URLs have the same host, the comparison `redirect_policy` uses to block
+/// cross-domain redirects.
+///
+/// # Arguments
+///
+/// * `original` - The URL the fetch started from.
+/// * `target` - The URL being redirected to.
+///
+/// # Returns
+///
+/// `true` if both URLs parse and have the same host, `false` otherwise.
+fn same_host(original: &str, target: &str) -> bool {
+    match (reqwest::Url::parse(original), reqwest::Url::parse(target)) {
+        (Ok(original), Ok(target)) => original.host_str().is_some() && original.host_str() == target.host_str(),
+        _ => false,
+    }
+}
+
+/// Checks whether a request error was caused by a blocked cross-domain redirect.
+///
+/// # Arguments
+///
+/// * `error` - The request error to inspect.
+///
+/// # Returns
+///
+/// `true` if the redirect policy rejected a cross-domain redirect, `false` otherwise.
+fn is_cross_domain_redirect(error: &reqwest::Error) -> bool {
+    if !error.is_redirect() {
+        return false;
+    }
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        if cause.to_string() == CROSS_DOMAIN_REDIRECT_BLOCKED {
+            return true;
+        }
+        source = cause.source();
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE_HTML: &str = r#"
+        <html>
+            <body>
+                <article>
+                    <p>This is the landing page content.</p>
+                </article>
+            </body>
+        </html>
+    "#;
+
+    const SERP_HTML: &str = r#"
+        <html>
+            <body>
+                <div class="g">
+                    <div class="yuRUbf">
+                        <a href="https://www.rust-lang.org/"><h3>Rust Programming Language</h3></a>
+                    </div>
+                    <div class="VwiC3b">A language empowering everyone to build reliable software.</div>
+                </div>
+                <div class="g">
+                    <div class="yuRUbf">
+                        <a href="https://doc.rust-lang.org/book/"><h3>The Rust Book</h3></a>
+                    </div>
+                    <span class="st">An introductory book about Rust.</span>
+                </div>
+                <div class="g">
+                    <div class="yuRUbf">
+                        <a href="https://en.wikipedia.org/wiki/Rust"><h3>Rust - Wikipedia</h3></a>
+                    </div>
+                    <div data-sncf="1">Rust is a general-purpose programming language.</div>
+                </div>
+                <div class="g">
+                    <div class="yuRUbf">
+                        <a href="https://crates.io/"><h3>crates.io</h3></a>
+                    </div>
+                    <div>The Rust community's crate registry.</div>
+                </div>
+            </body>
+        </html>
+    "#;
+
+    /// Tests that titles and snippets are extracted from each result block.
+    #[test]
+    fn test_extract_results_snippets() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let document = Html::parse_document(SERP_HTML);
+        let results = engine.extract_results(&document, usize::MAX).unwrap();
+
+        let find = |url: &str| results.iter().find(|r| r.url == url).unwrap();
+
+        let rust = find("https://www.rust-lang.org/");
+        assert_eq!(rust.title.as_deref(), Some("Rust Programming Language"));
+        assert_eq!(
+            rust.snippet.as_deref(),
+            Some("A language empowering everyone to build reliable software.")
+        );
+
+        let book = find("https://doc.rust-lang.org/book/");
+        assert_eq!(book.snippet.as_deref(), Some("An introductory book about Rust."));
+
+        let wiki = find("https://en.wikipedia.org/wiki/Rust");
+        assert_eq!(
+            wiki.snippet.as_deref(),
+            Some("Rust is a general-purpose programming language.")
+        );
+
+        // No known snippet container: falls back to the result text minus the title
+        let crates = find("https://crates.io/");
+        assert_eq!(crates.title.as_deref(), Some("crates.io"));
+        assert_eq!(
+            crates.snippet.as_deref(),
+            Some("The Rust community's crate registry.")
+        );
+    }
+
+    /// Tests extracting results from new result page markup through `result_selectors`.
+    #[test]
+    fn test_custom_result_selectors() {
+        let html = r#"
+            <div class="result-card">
+                <a class="result-link" href="https://www.rust-lang.org/"><h3>Rust Programming Language</h3></a>
+                <p class="result-summary">A language empowering everyone to build reliable software.</p>
+            </div>
+            <div class="result-card">
+                <a class="result-link" href="https://crates.io/"><h3>crates.io</h3></a>
+                <span>The Rust community's crate registry.</span>
+            </div>
+        "#;
+        let document = Html::parse_document(html);
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        assert!(engine.extract_results(&document, usize::MAX).unwrap().is_empty());
+
+        let mut config = ScraperConfig::default();
+        config.result_selectors = crate::config::ResultSelectors {
+            links: vec!["div.result-card > a.result-link".to_string()],
+            containers: vec!["result-card".to_string()],
+            snippets: vec![".result-summary".to_string()],
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let results = engine.extract_results(&document, usize::MAX).unwrap();
+
+        assert_eq!(SearchHit::urls(&results), vec!["https://www.rust-lang.org/", "https://crates.io/"]);
+        assert_eq!(results[0].title.as_deref(), Some("Rust Programming Language"));
+        assert_eq!(
+            results[0].snippet.as_deref(),
+            Some("A language empowering everyone to build reliable software.")
+        );
+        assert_eq!(results[1].snippet.as_deref(), Some("The Rust community's crate registry."));
+    }
+
+    const DUCKDUCKGO_HTML: &str = r#"
+        <html>
+            <body>
+                <div class="result results_links results_links_deep result--ad">
+                    <h2 class="result__title">
+                        <a class="result__a" href="https://duckduckgo.com/y.js?ad_domain=ads.example.com&amp;u3=x">Sponsored</a>
+                    </h2>
+                </div>
+                <div class="result results_links results_links_deep web-result">
+                    <h2 class="result__title">
+                        <a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust%2Dlang.org%2F&amp;rut=abc123">Rust Programming Language</a>
+                    </h2>
+                    <a class="result__snippet" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust%2Dlang.org%2F">A language empowering everyone to build <b>reliable</b> software.</a>
+                </div>
+                <div class="result results_links results_links_deep web-result">
+                    <h2 class="result__title">
+                        <a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fdoc.rust%2Dlang.org%2Fbook%2F&amp;rut=def456">The Rust Programming Language - The Rust Book</a>
+                    </h2>
+                    <a class="result__snippet" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fdoc.rust%2Dlang.org%2Fbook%2F">An introductory book about Rust.</a>
+                </div>
+                <div class="result results_links results_links_deep web-result">
+                    <h2 class="result__title">
+                        <a rel="nofollow" class="result__a" href="https://en.wikipedia.org/wiki/Rust_(programming_language)">Rust (programming language) - Wikipedia</a>
+                    </h2>
+                </div>
+            </body>
+        </html>
+    "#;
+
+    /// Tests that DuckDuckGo results are unwrapped from their redirects, with ads skipped.
+    #[test]
+    fn test_extract_duckduckgo_results() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let document = Html::parse_document(DUCKDUCKGO_HTML);
+        let results = engine.extract_duckduckgo_results(&document, usize::MAX).unwrap();
+
+        let urls: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.rust-lang.org/",
+                "https://doc.rust-lang.org/book/",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            ]
+        );
+        assert_eq!(results[0].title.as_deref(), Some("Rust Programming Language"));
+        assert_eq!(
+            results[0].snippet.as_deref(),
+            Some("A language empowering everyone to build reliable software.")
+        );
+        assert_eq!(results[2].snippet, None);
+
+        let limited = engine.extract_duckduckgo_results(&document, 1).unwrap();
+        assert_eq!(limited.len(), 1);
+
+        assert_eq!(
+            engine.clean_duckduckgo_url("//duckduckgo.com/l/?uddg=https%3A%2F%2Fcrates.io%2F&rut=1"),
+            Some("https://crates.io/".to_string())
+        );
+        assert_eq!(engine.clean_duckduckgo_url("//duckduckgo.com/l/?rut=1"), None);
+        assert_eq!(engine.clean_duckduckgo_url("/html/?q=rust"), None);
+    }
+
+    /// Tests that searching with the DuckDuckGo provider queries its HTML endpoint.
+    #[tokio::test]
+    async fn test_search_duckduckgo() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/html/")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("q".to_string(), "rust".to_string()),
+                mockito::Matcher::UrlEncoded("df".to_string(), "w".to_string()),
+            ]))
+            .with_body(DUCKDUCKGO_HTML)
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            search_provider: SearchProvider::DuckDuckGo,
+            recency: Some(crate::config::Recency::Week),
+            ..ScraperConfig::default()
+        };
+        let mut engine = SearchEngine::new(config).unwrap();
+        engine.search_base_url = format!("{}/html/", server.url());
+
+        let results = engine.search("rust", 2).await.unwrap();
+        assert_eq!(
+            SearchHit::urls(&results),
+            vec!["https://www.rust-lang.org/", "https://doc.rust-lang.org/book/"]
+        );
+        mock.assert_async().await;
+    }
+
+    const SEARXNG_JSON: &str = r#"{
+        "query": "rust",
+        "number_of_results": 0,
+        "results": [
+            {"url": "https://www.rust-lang.org/", "title": "Rust Programming Language", "content": "A language empowering everyone.", "engine": "google"},
+            {"url": "https://doc.rust-lang.org/book/", "title": "The Rust Book", "content": "", "engine": "bing"},
+            {"url": "https://www.rust-lang.org/", "title": "Rust", "content": "Duplicate.", "engine": "duckduckgo"},
+            {"url": "https://crates.io/", "engine": "brave"}
+        ],
+        "suggestions": []
+    }"#;
+
+    /// Tests that SearXNG results are deserialized with titles and snippets, and that a disabled
+    /// JSON format is reported clearly.
+    #[tokio::test]
+    async fn test_search_searxng() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("q".to_string(), "rust".to_string()),
+                mockito::Matcher::UrlEncoded("format".to_string(), "json".to_string()),
+                mockito::Matcher::UrlEncoded("pageno".to_string(), "1".to_string()),
+            ]))
+            .with_header("Content-Type", "application/json")
+            .with_body(SEARXNG_JSON)
+            .create_async()
+            .await;
+        // Three results are fewer than asked for, so the second page is requested too
+        let second_page = server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("q".to_string(), "rust".to_string()),
+                mockito::Matcher::UrlEncoded("pageno".to_string(), "2".to_string()),
+            ]))
+            .with_header("Content-Type", "application/json")
+            .with_body(r#"{"results": []}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::UrlEncoded("q".to_string(), "go".to_string()))
+            .with_status(403)
+            .with_body("<html><body>403 Forbidden</body></html>")
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            search_provider: SearchProvider::SearXNG,
+            searxng_endpoint: Some(format!("{}/", server.url())),
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+
+        let results = engine.search("rust", 10).await.unwrap();
+        let urls: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://www.rust-lang.org/", "https://doc.rust-lang.org/book/", "https://crates.io/"]);
+        assert_eq!(results[0].title.as_deref(), Some("Rust Programming Language"));
+        assert_eq!(results[0].snippet.as_deref(), Some("A language empowering everyone."));
+        assert_eq!(results[1].snippet, None);
+        assert_eq!(results[2].title, None);
+        second_page.assert_async().await;
+
+        let result = engine.search("go", 10).await;
+        assert!(matches!(result, Err(ScraperError::SearchError(message)) if message.contains("JSON output disabled")));
+
+        let missing_endpoint = ScraperConfig {
+            search_provider: SearchProvider::SearXNG,
+            ..ScraperConfig::default()
+        };
+        assert!(matches!(SearchEngine::new(missing_endpoint), Err(ScraperError::ConfigError(_))));
+    }
+
+    /// Tests that URLs with query strings are valid while Google's own pages are rejected.
+    #[test]
+    fn test_is_valid_url() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+
+        assert!(engine.is_valid_url("https://example.com/article?id=123&lang=en"));
+        assert!(engine.is_valid_url("https://shop.example.com/item?ref=search&page=2#reviews"));
+        assert!(engine.is_valid_url("https://developers.google.com/search/docs?hl=en&authuser=0"));
+        assert!(engine.is_valid_url("https://example.com/docs/settings?tab=general&view=all"));
+
+        assert!(!engine.is_valid_url("https://www.google.com/search?q=rust&start=10"));
+        assert!(!engine.is_valid_url("https://www.google.com/url?q=https://example.com/&sa=U"));
+        assert!(!engine.is_valid_url("https://www.google.co.uk/imgres?imgurl=x&imgrefurl=y"));
+        assert!(!engine.is_valid_url("https://accounts.google.com/ServiceLogin?hl=en&continue=x"));
+        assert!(!engine.is_valid_url("https://webcache.googleusercontent.com/search?q=cache:x"));
+        assert!(!engine.is_valid_url("https://www.google.com/preferences?hl=en"));
+        assert!(!engine.is_valid_url("http://example.com/article?id=123&lang=en"));
+        assert!(!engine.is_valid_url("javascript:void(0)"));
+        assert!(!engine.is_valid_url("not a url"));
+    }
+
+    /// Tests that the domain allowlist and blocklist match subdomains, and that an empty
+    /// allowlist allows every domain.
+    #[test]
+    fn test_domain_filters() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        assert!(engine.is_valid_url("https://www.pinterest.com/pin/1"));
+        assert!(engine.is_valid_url("https://docs.rs/tokio"));
+
+        let config = ScraperConfig {
+            allowed_domains: vec!["docs.rs".to_string(), "wikipedia.org".to_string()],
+            blocked_domains: vec!["pinterest.com".to_string(), "de.wikipedia.org".to_string()],
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+
+        assert!(engine.is_valid_url("https://docs.rs/tokio"));
+        assert!(engine.is_valid_url("https://en.wikipedia.org/wiki/Rust"));
+        assert!(engine.is_valid_url("https://www.wikipedia.org/"));
+        assert!(!engine.is_valid_url("https://de.wikipedia.org/wiki/Rust"));
+        assert!(!engine.is_valid_url("https://www.pinterest.com/pin/1"));
+        assert!(!engine.is_valid_url("https://notwikipedia.org/"));
+        assert!(!engine.is_valid_url("https://crates.io/crates/tokio"));
+
+        let blocklist_only = ScraperConfig {
+            blocked_domains: vec!["pinterest.com".to_string()],
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(blocklist_only).unwrap();
+        assert!(engine.is_valid_url("https://crates.io/crates/tokio"));
+        assert!(!engine.is_valid_url("https://uk.pinterest.com/pin/1"));
+    }
+
+    /// Tests that at most `max_per_domain` results are kept per host, keeping the highest-ranked ones.
+    #[test]
+    fn test_max_per_domain() {
+        let html = r#"
+            <div class="g"><a href="https://a.example.com/1"><h3>A1</h3></a></div>
+            <div class="g"><a href="https://b.example.com/1"><h3>B1</h3></a></div>
+            <div class="g"><a href="https://a.example.com/2"><h3>A2</h3></a></div>
+            <div class="g"><a href="https://a.example.com/3"><h3>A3</h3></a></div>
+            <div class="g"><a href="https://www.a.example.com/4"><h3>A4</h3></a></div>
+            <div class="g"><a href="https://b.example.com/2"><h3>B2</h3></a></div>
+        "#;
+        let document = Html::parse_document(html);
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let urls = SearchHit::urls(&engine.extract_results(&document, usize::MAX).unwrap());
+        assert_eq!(
+            urls,
+            vec![
+                "https://a.example.com/1",
+                "https://b.example.com/1",
+                "https://a.example.com/2",
+                "https://b.example.com/2",
+            ]
+        );
+
+        let uncapped = ScraperConfig {
+            max_per_domain: 0,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(uncapped).unwrap();
+        assert_eq!(engine.extract_results(&document, usize::MAX).unwrap().len(), 6);
+    }
+
+    /// Builds a result page with `count` results, each linked twice.
+    fn large_serp(count: usize) -> String {
+        let results: String = (0..count)
+            .map(|i| {
+                format!(
+                    r#"<div class="g"><div class="yuRUbf"><a href="https://site{i}.example.com/page"><h3>Result {i}</h3></a></div>
+                       <div class="VwiC3b">Snippet {i}</div><a href="https://site{i}.example.com/page">Again</a></div>"#
+                )
+            })
+            .collect();
+        format!("<html><body><div id=\"search\">{}</div></body></html>", results)
+    }
+
+    /// Tests that extraction stops after the limit, skipping duplicates and keeping the first results.
+    #[test]
+    fn test_extract_results_limit() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let document = Html::parse_document(&large_serp(10));
+
+        let results = engine.extract_results(&document, 3).unwrap();
+        let urls: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://site0.example.com/page",
+                "https://site1.example.com/page",
+                "https://site2.example.com/page",
+            ]
+        );
+
+        assert_eq!(engine.extract_results(&document, usize::MAX).unwrap().len(), 10);
+    }
+
+    /// Tests that results keep the page's ranking, with duplicates kept at their first position.
+    #[test]
+    fn test_extract_results_order() {
+        let html = r#"
+            <div class="g"><div class="yuRUbf"><a href="https://zeta.example.com/"><h3>Zeta</h3></a></div></div>
+            <div class="g"><div class="yuRUbf"><a href="https://beta.example.com/"><h3>Beta</h3></a></div></div>
+            <div class="g"><div class="yuRUbf"><a href="https://zeta.example.com/"><h3>Zeta again</h3></a></div></div>
+            <div class="g"><div class="yuRUbf"><a href="https://mu.example.com/"><h3>Mu</h3></a></div></div>
+            <div class="g"><div class="yuRUbf"><a href="https://alpha.example.com/"><h3>Alpha</h3></a></div></div>
+            <div class="g"><div class="yuRUbf"><a href="https://beta.example.com/"><h3>Beta again</h3></a></div></div>
+        "#;
+        let document = Html::parse_document(html);
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let results = engine.extract_results(&document, usize::MAX).unwrap();
+        assert_eq!(
+            SearchHit::urls(&results),
+            vec![
+                "https://zeta.example.com/",
+                "https://beta.example.com/",
+                "https://mu.example.com/",
+                "https://alpha.example.com/",
+            ]
+        );
+        assert_eq!(results[0].title.as_deref(), Some("Zeta"));
+    }
+
+    /// Compares extraction with and without the early exit on a large result page.
+    /// Run with `cargo test --release -- --ignored --nocapture bench_extract_results`.
+    #[test]
+    #[ignore]
+    fn bench_extract_results() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let document = Html::parse_document(&large_serp(2000));
+
+        let time = |limit: usize| {
+            let start = std::time::Instant::now();
+            for _ in 0..10 {
+                engine.extract_results(&document, limit).unwrap();
+            }
+            start.elapsed() / 10
+        };
+
+        let full = time(usize::MAX);
+        let limited = time(10);
+        println!("full: {:?}, limited to 10: {:?}", full, limited);
+    }
+
+    /// Tests that `rank_with` sorts by descending score and truncates to the count.
+    #[test]
+    fn test_rank_with() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let urls = vec![
+            "https://blog.example.com/rust".to_string(),
+            "https://docs.rs/tokio".to_string(),
+            "https://www.pinterest.com/rust".to_string(),
+            "https://doc.rust-lang.org/book/".to_string(),
+        ];
+
+        let ranked = engine.rank_with(urls, 3, |url| {
+            if url.contains("docs.rs") || url.contains("doc.rust-lang.org") {
+                1.0
+            } else if url.contains("pinterest") {
+                -1.0
+            } else {
+                0.0
+            }
+        });
+
+        assert_eq!(
+            ranked,
+            vec![
+                "https://docs.rs/tokio".to_string(),
+                "https://doc.rust-lang.org/book/".to_string(),
+                "https://blog.example.com/rust".to_string(),
+            ]
+        );
+    }
+
+    /// Tests domain round-robin selection against plain top-ranked selection.
+    #[test]
+    fn test_select_urls() {
+        let urls: Vec<String> = [
+            "https://a.com/1",
+            "https://www.a.com/2",
+            "https://a.com/3",
+            "https://b.com/1",
+            "https://c.com/1",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        assert_eq!(
+            engine.select_urls(urls.clone(), 3),
+            vec!["https://a.com/1", "https://www.a.com/2", "https://a.com/3"]
+        );
+
+        let config = ScraperConfig {
+            selection_strategy: SelectionStrategy::DomainRoundRobin,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        assert_eq!(
+            engine.select_urls(urls.clone(), 4),
+            vec!["https://a.com/1", "https://b.com/1", "https://c.com/1", "https://www.a.com/2"]
+        );
+        assert_eq!(engine.select_urls(urls, 10).len(), 5);
+    }
+
+    /// Tests that `fetch_and_fold` folds every fetched page into the accumulator.
+    #[tokio::test]
+    async fn test_fetch_and_fold() {
+        let mut server = mockito::Server::new_async().await;
+        for path in ["/one", "/two", "/three"] {
+            server
+                .mock("GET", path)
+                .with_body(ARTICLE_HTML)
+                .create_async()
+                .await;
+        }
+
+        let mut config = ScraperConfig::default();
+        config.rate_limit.requests_per_second = 100.0;
+        config.max_retries = 1;
+        let engine = SearchEngine::new(config).unwrap();
+        let urls = ["/one", "/two", "/three", "/missing"]
+            .iter()
+            .map(|path| format!("{}{}", server.url(), path))
+            .collect();
+
+        let total_chars = engine
+            .fetch_and_fold(urls, 0, |acc, content| acc + content.content.len())
+            .await
+            .unwrap();
+
+        assert_eq!(total_chars, 3 * "This is the landing page content.".len());
+    }
+
+    /// Tests that jittered pacing delays fall within the configured range.
+    #[test]
+    fn test_pacing_delay_jitter() {
+        let mut rng = rand::thread_rng();
+        let interval = Duration::from_millis(500);
+        let jitter = Duration::from_millis(200);
+        // Allow for float rounding when converting back to a `Duration`
+        let epsilon = Duration::from_micros(1);
+
+        let delays: Vec<Duration> = (0..1000)
+            .map(|_| pacing_delay(interval, Some(jitter), &mut rng))
+            .collect();
+
+        assert!(delays
+            .iter()
+            .all(|d| *d + epsilon >= interval - jitter && *d <= interval + jitter + epsilon));
+        assert!(delays.iter().any(|d| *d != delays[0]), "delays should vary");
+
+        // Without jitter the interval is used as-is
+        assert_eq!(pacing_delay(interval, None, &mut rng), interval);
+
+        // Jitter larger than the interval never produces a delay beyond interval + jitter
+        let short = Duration::from_millis(100);
+        assert!((0..100).all(|_| pacing_delay(short, Some(jitter), &mut rng) <= short + jitter + epsilon));
+    }
+
+    /// Tests that meta-refresh redirects are followed to the target page.
+    #[tokio::test]
+    async fn test_meta_refresh_followed() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/old")
+            .with_body(r#"<html><head><meta http-equiv="Refresh" content="0; URL='/new'"></head><body></body></html>"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/new")
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let old_url = format!("{}/old", server.url());
+        let content = engine.try_fetch_content(&old_url).await.unwrap();
+
+        assert_eq!(content.url, old_url);
+        assert!(content.content.contains("landing page content"));
+    }
+
+    /// Tests that a meta refresh to another host is blocked like an HTTP redirect when
+    /// `block_cross_domain_redirects` is set.
+    #[tokio::test]
+    async fn test_cross_domain_meta_refresh_blocked() {
+        let mut server = mockito::Server::new_async().await;
+        let target = format!("http://localhost:{}/landing", server.socket_address().port());
+        server
+            .mock("GET", "/start")
+            .with_body(format!(r#"<html><head><meta http-equiv="refresh" content="0; url={}"></head></html>"#, target))
+            .create_async()
+            .await;
+        let landing = server
+            .mock("GET", "/landing")
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            block_cross_domain_redirects: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+
+        match engine.try_fetch_content(&format!("{}/start", server.url())).await {
+            Err(ScraperError::ExtractionError(msg)) => assert_eq!(msg, CROSS_DOMAIN_REDIRECT_BLOCKED),
+            other => panic!("expected blocked meta refresh, got {:?}", other),
+        }
+
+        // Refreshes within the host are still followed, and are the only hit on the landing page
+        server
+            .mock("GET", "/same-host")
+            .with_body(r#"<html><head><meta http-equiv="refresh" content="0; url=/landing"></head></html>"#)
+            .create_async()
+            .await;
+        let content = engine.try_fetch_content(&format!("{}/same-host", server.url())).await.unwrap();
+        assert!(content.content.contains("landing page content"));
+        landing.assert_async().await;
+    }
+
+    /// Tests that meta-refresh loops are bounded by the configured count.
+    #[tokio::test]
+    async fn test_meta_refresh_loop_bounded() {
+        let mut server = mockito::Server::new_async().await;
+        let refresh = server
+            .mock("GET", "/loop")
+            .with_body(r#"<html><head><meta http-equiv="refresh" content="0;url=/loop"></head></html>"#)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            max_meta_refreshes: 2,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+
+        let result = engine.try_fetch_content(&format!("{}/loop", server.url())).await;
+
+        assert!(matches!(result, Err(ScraperError::ExtractionError(_))));
+        refresh.assert_async().await;
+    }
+
+    /// Tests that the pages of a two-page article are combined when pagination is followed.
+    #[tokio::test]
+    async fn test_follow_article_pagination() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/story")
+            .with_body(
+                r#"<html><head><link rel="next" href="/story/page/2"></head><body><article>
+                    <p>The first half of the story explains ownership.</p>
+                </article></body></html>"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/story/page/2")
+            .with_body(
+                r#"<html><body><article>
+                    <p>The second half of the story explains borrowing.</p>
+                    <a href="/story">Previous</a> <a href="/story">Next &raquo;</a>
+                </article></body></html>"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let url = format!("{}/story", server.url());
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert!(!content.content.contains("borrowing"));
+
+        let config = ScraperConfig {
+            follow_article_pagination: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+
+        assert!(content.content.contains("explains ownership"));
+        assert!(content.content.contains("explains borrowing"));
+        assert_eq!(content.metadata.get("article_pages").map(String::as_str), Some("2"));
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    /// Tests detection of next-page links by `rel="next"` and by link text.
+    #[test]
+    fn test_next_page_target() {
+        let page = "https://example.com/story?page=1";
+        let find = |html: &str| next_page_target(&Html::parse_document(html), page);
+
+        assert_eq!(
+            find(r#"<a rel="next" href="?page=2">2</a>"#).as_deref(),
+            Some("https://example.com/story?page=2")
+        );
+        assert_eq!(
+            find(r#"<a href="/story?page=3">Next &raquo;</a>"#).as_deref(),
+            Some("https://example.com/story?page=3")
+        );
+        assert_eq!(find(r#"<a href="https://other.com/story?page=2">Next</a>"#), None);
+        assert_eq!(find(r#"<a href="/story?page=2">Next week's issue</a>"#), None);
+    }
+
+    /// Tests that error pages aren't scraped, and that client errors aren't retried.
+    #[tokio::test]
+    async fn test_http_status_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let missing = server
+            .mock("GET", "/missing")
+            .with_status(404)
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+        let broken = server
+            .mock("GET", "/broken")
+            .with_status(500)
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            max_retries: 3,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let result = engine.fetch_content(&format!("{}/missing", server.url())).await;
+        assert!(matches!(result, Err(ScraperError::HttpStatus(404))));
+        missing.assert_async().await;
+
+        let config = ScraperConfig {
+            max_retries: 1,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let result = engine.fetch_content(&format!("{}/broken", server.url())).await;
+        assert!(matches!(result, Err(ScraperError::HttpStatus(500))));
+        broken.assert_async().await;
+    }
+
+    /// Tests that a 429 response is retried after the delay given by its `Retry-After` header.
+    #[tokio::test]
+    async fn test_retry_after() {
+        let mut server = mockito::Server::new_async().await;
+        let limited = server
+            .mock("GET", "/page")
+            .with_status(429)
+            .with_header("Retry-After", "1")
+            .expect(1)
+            .create_async()
+            .await;
+        let page = server
+            .mock("GET", "/page")
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            max_retries: 2,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let url = format!("{}/page", server.url());
+        let started = Instant::now();
+        let content = engine.fetch_content(&url).await.unwrap();
+
+        // The host was held back by the Retry-After delay, which the retry then waited out
+        let ready_at = engine.host_schedules.lock().unwrap()[&urls::host(&url).unwrap()].ready_at;
+        assert!(ready_at >= started + Duration::from_secs(1));
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(content.content.contains("landing page content"));
+        limited.assert_async().await;
+        page.assert_async().await;
+
+        assert_eq!(retry_after_delay(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after_delay("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(retry_after_delay("soon"), None);
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        assert!(retry_after_delay(&date).is_some_and(|delay| delay > Duration::from_secs(50)));
+    }
+
+    /// Tests that a URL is skipped once it reaches the failure threshold, until the blacklist is reset.
+    #[tokio::test]
+    async fn test_failure_blacklist() {
+        let mut server = mockito::Server::new_async().await;
+        let dead = server
+            .mock("GET", "/dead")
+            .with_status(404)
+            .with_body("<html><body></body></html>")
+            .expect(3)
+            .create_async()
+            .await;
+        let url = format!("{}/dead", server.url());
+
+        let mut config = ScraperConfig {
+            max_retries: 1,
+            failure_blacklist_threshold: Some(2),
+            ..ScraperConfig::default()
+        };
+        config.rate_limit.requests_per_second = 1000.0;
+        let engine = SearchEngine::new(config).unwrap();
+
+        for _ in 0..2 {
+            let results = engine.fetch_all_results(vec![url.clone()]).await;
+            assert!(matches!(results[0].1, Err(ScraperError::HttpStatus(404))));
+        }
+        let results = engine.fetch_all_results(vec![url.clone()]).await;
+        assert!(matches!(results[0].1, Err(ScraperError::Blacklisted(_))));
+        assert_eq!(engine.blacklisted_urls(), vec![url.clone()]);
+
+        engine.reset_blacklist();
+        assert!(engine.blacklisted_urls().is_empty());
+        let results = engine.fetch_all_results(vec![url]).await;
+        assert!(matches!(results[0].1, Err(ScraperError::HttpStatus(404))));
+        dead.assert_async().await;
+    }
+
+    /// Tests that raw HTML is kept only when configured and omitted from JSON when absent.
+    #[tokio::test]
+    async fn test_keep_raw_html() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/page")
+            .with_body(ARTICLE_HTML)
+            .expect(2)
+            .create_async()
+            .await;
+        let url = format!("{}/page", server.url());
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert!(content.raw_html.is_none());
+        assert!(!serde_json::to_string(&content).unwrap().contains("raw_html"));
+
+        let config = ScraperConfig {
+            keep_raw_html: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert!(content.raw_html.unwrap().contains("<article>"));
+    }
+
+    /// Tests that requests are sent through the configured proxy and that bad proxy URLs are rejected.
+    #[tokio::test]
+    async fn test_proxy() {
+        let mut proxy = mockito::Server::new_async().await;
+        let mock = proxy
+            .mock("GET", "/page")
+            .match_header("host", "docs.example.invalid")
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            proxy: Some(proxy.url()),
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.try_fetch_content("http://docs.example.invalid/page").await.unwrap();
+        assert!(!content.content.is_empty());
+        mock.assert_async().await;
+
+        let config = ScraperConfig {
+            proxy: Some("not a proxy".to_string()),
+            ..ScraperConfig::default()
+        };
+        assert!(matches!(SearchEngine::new(config), Err(ScraperError::ConfigError(_))));
+    }
+
+    /// Tests that redirects to a different host are rejected when blocking is enabled.
+    #[tokio::test]
+    async fn test_cross_domain_redirect_blocked() {
+        let mut server = mockito::Server::new_async().await;
+        let target = format!("http://localhost:{}/landing", server.socket_address().port());
+        server
+            .mock("GET", "/start")
+            .with_status(302)
+            .with_header("Location", &target)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/landing")
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+
+        // Recording redirects follows them without the client's redirect policy, and must block too
+        for record_redirects in [false, true] {
+            let config = ScraperConfig {
+                block_cross_domain_redirects: true,
+                record_redirects,
+                ..ScraperConfig::default()
+            };
+            let engine = SearchEngine::new(config).unwrap();
+
+            match engine.try_fetch_content(&format!("{}/start", server.url())).await {
+                Err(ScraperError::ExtractionError(msg)) => assert_eq!(msg, CROSS_DOMAIN_REDIRECT_BLOCKED),
+                other => panic!("expected blocked redirect, got {:?}", other),
+            }
+        }
+    }
+
+    /// Tests that cross-domain redirects are followed when blocking is disabled.
+    #[tokio::test]
+    async fn test_cross_domain_redirect_followed_by_default() {
+        let mut server = mockito::Server::new_async().await;
+        let target = format!("http://localhost:{}/landing", server.socket_address().port());
+        server
+            .mock("GET", "/start")
+            .with_status(302)
+            .with_header("Location", &target)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/landing")
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine
+            .try_fetch_content(&format!("{}/start", server.url()))
+            .await
+            .unwrap();
+
+        assert!(content.content.contains("landing page content"));
+    }
+
+    /// Tests that queries producing malformed search URLs are rejected.
+    #[test]
+    fn test_validate_query() {
+        assert!(validate_query("rust programming").is_ok());
+        assert!(validate_query(r#""rust book" site:doc.rust-lang.org -(beta OR nightly)"#).is_ok());
+
+        let invalid = [
+            ("   ", "empty"),
+            (r#""rust programming"#, "quote"),
+            ("(rust OR go", "parentheses"),
+            ("rust) (go", "parentheses"),
+            ("rust site:", "missing a value"),
+            ("rust -inurl:", "missing a value"),
+            ("OR rust", "boolean"),
+            ("rust AND", "boolean"),
+        ];
+        for (query, expected) in invalid {
+            match validate_query(query) {
+                Err(ScraperError::SearchError(message)) => {
+                    assert!(message.contains(expected), "{}: {}", query, message)
+                }
+                other => panic!("expected a search error for {:?}, got {:?}", query, other),
+            }
+        }
+
+        let long_query = "rust ".repeat(500);
+        match validate_query(&long_query) {
+            Err(ScraperError::SearchError(message)) => assert!(message.contains("too long")),
+            other => panic!("expected a search error, got {:?}", other),
+        }
+    }
+
+    /// Tests that user-supplied URLs are checked like search results.
+    #[test]
+    fn test_validate_urls() {
+        let config = ScraperConfig {
+            blocked_domains: vec!["pinterest.com".to_string()],
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+
+        let valid = vec!["https://www.rust-lang.org/".to_string(), "https://doc.rust-lang.org/book/".to_string()];
+        assert!(engine.validate_urls(&valid).is_ok());
+        assert!(engine.validate_urls(&[]).is_ok());
+
+        let invalid = vec![
+            "https://www.rust-lang.org/".to_string(),
+            "not a url".to_string(),
+            "http://example.com/".to_string(),
+            "https://www.pinterest.com/pin/1".to_string(),
+        ];
+        match engine.validate_urls(&invalid) {
+            Err(ScraperError::SearchError(message)) => {
+                assert!(message.ends_with("not a url, http://example.com/, https://www.pinterest.com/pin/1"), "{}", message)
+            }
+            other => panic!("expected a search error, got {:?}", other),
+        }
+    }
+
+    /// Tests that result counts outside `1..=MAX_RESULT_COUNT` are rejected before searching.
+    #[tokio::test]
+    async fn test_search_result_count_range() {
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+
+        for count in [0, MAX_RESULT_COUNT + 1] {
+            match engine.search("rust", count).await {
+                Err(ScraperError::SearchError(message)) => assert!(message.contains("result count")),
+                other => panic!("expected a search error for {}, got {:?}", count, other),
+            }
+        }
+    }
+
+    /// Starts a bare HTTP server for tests whose responses must stall. Stalling inside a mockito
+    /// body would block the shared mock server for every other test.
+    ///
+    /// # Arguments
+    ///
+    /// * `respond` - Maps a request path to the delay before answering and the raw response.
+    ///
+    /// # Returns
+    ///
+    /// The base URL of the server.
+    async fn stalling_server<F>(respond: F) -> String
+    where
+        F: Fn(&str) -> (Duration, String) + Send + Sync + 'static,
+    {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let respond = Arc::new(respond);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let respond = respond.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        match stream.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => request.extend_from_slice(&buffer[..read]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or("/");
+                    let (delay, response) = respond(path);
+                    sleep(delay).await;
+                    // The client may have given up on a stalled response already
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        base
+    }
+
+    /// Builds a raw HTTP response that closes its connection, so each request gets a new one.
+    fn raw_response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    /// Tests that every hop of a multi-hop redirect is recorded when enabled, once per fetch even
+    /// when fetches of the same URL overlap or are hedged.
+    #[tokio::test]
+    async fn test_redirect_chain_recorded() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let base = stalling_server(move |path| match path {
+            "/start" => (Duration::ZERO, raw_response("301 Moved Permanently", "Location: /middle\r\n", "")),
+            "/middle" => (Duration::ZERO, raw_response("302 Found", "Location: /end\r\n", "")),
+            "/end" => {
+                // The fifth request, the first one of the hedged fetch, stalls
+                let delay = if counter.fetch_add(1, Ordering::SeqCst) == 4 {
+                    Duration::from_secs(3)
+                } else {
+                    Duration::ZERO
+                };
+                (delay, raw_response("200 OK", "", ARTICLE_HTML))
+            }
+            _ => (Duration::ZERO, raw_response("404 Not Found", "", "")),
+        })
+        .await;
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine.try_fetch_content(&format!("{}/start", base)).await.unwrap();
+        assert!(content.redirect_chain.is_empty());
+
+        let config = ScraperConfig {
+            record_redirects: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let start = format!("{}/start", base);
+        let expected = vec![start.clone(), format!("{}/middle", base), format!("{}/end", base)];
+        let content = engine.try_fetch_content(&start).await.unwrap();
+
+        assert_eq!(content.redirect_chain, expected);
+        assert!(content.content.contains("landing page content"));
+
+        // Concurrent fetches of the same URL each record only their own hops
+        let (first, second) = tokio::join!(engine.try_fetch_content(&start), engine.try_fetch_content(&start));
+        assert_eq!(first.unwrap().redirect_chain, expected);
+        assert_eq!(second.unwrap().redirect_chain, expected);
+
+        let mut config = ScraperConfig {
+            record_redirects: true,
+            hedge_after: Some(Duration::from_millis(100)),
+            ..ScraperConfig::default()
+        };
+        config.rate_limit.requests_per_second = 1000.0;
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.try_fetch_hedged(&start).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 6);
+        assert_eq!(content.redirect_chain, expected);
+    }
+
+    /// Tests that the fetch fallback strategy is tried once after the regular attempts fail.
+    #[tokio::test]
+    async fn test_fetch_fallback_user_agent() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/page")
+            .match_header("user-agent", mockito::Matcher::Regex("RustBot".to_string()))
+            .with_body("<html><body></body></html>")
+            .create_async()
+            .await;
+        let fallback = server
+            .mock("GET", "/page")
+            .match_header("user-agent", "FallbackAgent/1.0")
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+        let url = format!("{}/page", server.url());
+
+        let config = ScraperConfig {
+            max_retries: 1,
+            user_agent_mode: UserAgentMode::Fixed,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config.clone()).unwrap();
+        assert!(engine.fetch_content(&url).await.is_err());
+
+        let config = ScraperConfig {
+            fetch_fallback: Some(FetchStrategy::UserAgent("FallbackAgent/1.0".to_string())),
+            ..config
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.fetch_content(&url).await.unwrap();
+
+        assert!(content.content.contains("landing page content"));
+        fallback.assert_async().await;
+    }
+
+    /// Tests that each user agent mode picks from the right source.
+    #[test]
+    fn test_pick_user_agent() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(pick_user_agent(&UserAgentMode::Fixed, &mut rng), None);
+
+        let list = UserAgentMode::List(vec!["Agent/1.0".to_string(), "Agent/2.0".to_string()]);
+        for _ in 0..10 {
+            let user_agent = pick_user_agent(&list, &mut rng).unwrap();
+            assert!(user_agent == "Agent/1.0" || user_agent == "Agent/2.0");
+
+            let user_agent = pick_user_agent(&UserAgentMode::RealisticRandom, &mut rng).unwrap();
+            assert!(REALISTIC_USER_AGENTS.contains(&user_agent.as_str()));
+            assert!(!user_agent.to_lowercase().contains("bot"));
+        }
+    }
+
+    /// Tests that engines with the same `seed` rotate through user agents in the same order.
+    #[test]
+    fn test_seeded_user_agents() {
+        let config = ScraperConfig {
+            seed: Some(42),
+            ..ScraperConfig::default()
+        };
+        let picks = |config: &ScraperConfig| {
+            let engine = SearchEngine::new(config.clone()).unwrap();
+            (0..20).map(|_| engine.pick_user_agent().unwrap()).collect::<Vec<_>>()
+        };
+
+        let first = picks(&config);
+        assert_eq!(first, picks(&config));
+        assert!(first.iter().any(|user_agent| *user_agent != first[0]), "user agents should rotate");
+
+        let other_seed = ScraperConfig {
+            seed: Some(7),
+            ..ScraperConfig::default()
+        };
+        assert_ne!(first, picks(&other_seed));
+    }
+
+    /// Tests that page requests send a user agent from the configured list.
+    #[tokio::test]
+    async fn test_user_agent_list() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/page")
+            .match_header("user-agent", "ListAgent/1.0")
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            user_agent_mode: UserAgentMode::List(vec!["ListAgent/1.0".to_string()]),
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        engine.fetch_content(&format!("{}/page", server.url())).await.unwrap();
+
+        page.assert_async().await;
+    }
+
+    /// Tests that pages are extracted as Markdown when `markdown_content` is set.
+    #[tokio::test]
+    async fn test_markdown_content() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/guide")
+            .with_body(
+                r#"<html><body><main>
+                    <h2>Install</h2>
+                    <p>Run the <a href="/install.sh">installer</a> first.</p>
+                    <ul><li>Linux and BSD</li><li>macOS 14 or later</li><li>Soon</li></ul>
+                </main></body></html>"#,
+            )
+            .create_async()
+            .await;
+        let url = format!("{}/guide", server.url());
+
+        let config = ScraperConfig {
+            markdown_content: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert_eq!(
+            content.content,
+            format!("## Install\nRun the [installer]({}/install.sh) first.\n- Linux and BSD\n- macOS 14 or later\n- Soon", server.url())
+        );
+    }
+
+    /// Tests that script and style text inside the selected elements is left out of the content.
+    #[test]
+    fn test_extract_text_skips_scripts() {
+        let document = Html::parse_document(
+            r#"<html><body><article>
+                <p>Cargo builds your code. <script>var tracking = "Tracking code that is not content";</script></p>
+                <li>Cargo also downloads dependencies.<style>li { color: red; }</style></li>
+            </article></body></html>"#,
+        );
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        assert_eq!(
+            engine.extract_text(&document).unwrap(),
+            "Cargo builds your code.\nCargo also downloads dependencies."
+        );
+    }
+
+    /// Builds a one-page PDF document showing the given lines of text.
+    #[cfg(feature = "pdf")]
+    fn pdf_document(lines: &[&str]) -> Vec<u8> {
+        let text = lines
+            .iter()
+            .map(|line| format!("({}) Tj T*", line))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let stream = format!("BT /F1 12 Tf 14 TL 72 720 Td {} ET", text);
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>".to_string(),
+            format!("<< /Length {} >>\nstream\n{}\nendstream", stream.len(), stream),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+        ];
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", index + 1, object));
+        }
+        let xref = pdf.len();
+        pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+        for offset in offsets {
+            pdf.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        pdf.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref));
+        pdf.into_bytes()
+    }
+
+    /// Tests recognizing PDF content types.
+    #[test]
+    fn test_pdf_content_type() {
+        assert!(is_pdf_content_type("application/pdf"));
+        assert!(is_pdf_content_type("Application/PDF; charset=binary"));
+        assert!(!is_pdf_content_type("text/html; charset=utf-8"));
+        assert!(!is_pdf_content_type("application/pdf-signature"));
+    }
+
+    /// Tests that the text of pages served as PDFs is extracted into the content.
+    #[cfg(feature = "pdf")]
+    #[tokio::test]
+    async fn test_pdf_content() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/2024/07/spec.pdf")
+            .with_header("Content-Type", "application/pdf")
+            .with_body(pdf_document(&["Rust 1.80 stabilizes lazy cells.", "LazyLock replaces lazy_static."]))
+            .create_async()
+            .await;
+        let url = format!("{}/2024/07/spec.pdf", server.url());
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+
+        assert!(content.content.contains("Rust 1.80 stabilizes lazy cells."));
+        assert!(content.content.contains("LazyLock replaces lazy_static."));
+        assert!(!content.content.contains("endstream"));
+        assert_eq!(content.metadata.get("content_type").map(String::as_str), Some("application/pdf"));
+        assert!(content.raw_html.is_none());
+        assert_eq!(content.published.map(|date| date.to_rfc3339()).as_deref(), Some("2024-07-01T00:00:00+00:00"));
+    }
+
+    /// Tests that pages served as PDFs are rejected without the `pdf` feature.
+    #[cfg(not(feature = "pdf"))]
+    #[tokio::test]
+    async fn test_pdf_requires_feature() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/spec.pdf")
+            .with_header("Content-Type", "application/pdf")
+            .with_body("%PDF-1.4\n%%EOF\n")
+            .create_async()
+            .await;
+        let url = format!("{}/spec.pdf", server.url());
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        match engine.try_fetch_content(&url).await {
+            Err(ScraperError::ExtractionError(message)) => assert!(message.contains("`pdf` feature")),
+            other => panic!("expected an extraction error, got {:?}", other),
+        }
+    }
+
+    /// Tests that incomplete UTF-8 sequences are dropped from the end of a cut-off body.
+    #[test]
+    fn test_truncate_utf8() {
+        let text = "caf\u{e9} \u{20ac}\u{1f980}";
+        let cut = |len: usize| {
+            let mut bytes = text.as_bytes()[..len].to_vec();
+            truncate_utf8(&mut bytes);
+            String::from_utf8(bytes).unwrap()
+        };
+
+        assert_eq!(cut(4), "caf");
+        assert_eq!(cut(5), "caf\u{e9}");
+        assert_eq!(cut(8), "caf\u{e9} ");
+        assert_eq!(cut(9), "caf\u{e9} \u{20ac}");
+        assert_eq!(cut(12), "caf\u{e9} \u{20ac}");
+        assert_eq!(cut(text.len()), text);
+        assert_eq!(cut(0), "");
+    }
+
+    /// Tests that pages larger than `max_content_bytes` are cut off and marked as truncated.
+    #[tokio::test]
+    async fn test_max_content_bytes() {
+        let body = format!(
+            "<html><body><article><p>Rust 1.80 stabilise les cellules paresseuses.</p><p>{}</p></article></body></html>",
+            "Deuxième paragraphe très long. ".repeat(500)
+        );
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/article")
+            .with_body(&body)
+            .create_async()
+            .await;
+        let url = format!("{}/article", server.url());
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert!(content.content.ends_with("Deuxième paragraphe très long."));
+        assert!(!content.metadata.contains_key("truncated"));
+
+        // Cut inside the two-byte "è"
+        let config = ScraperConfig {
+            max_content_bytes: body.find("Deuxième").unwrap() + "Deuxi".len() + 1,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert_eq!(content.content, "Rust 1.80 stabilise les cellules paresseuses.");
+        assert_eq!(content.metadata.get("truncated").map(String::as_str), Some("true"));
+        assert!(engine.bytes_downloaded() < body.len());
+    }
+
+    /// Tests that the archived copy is used and marked when the live page fails.
+    #[tokio::test]
+    async fn test_archive_fallback() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/page")
+            .with_status(503)
+            .with_body("<html><body></body></html>")
+            .create_async()
+            .await;
+        server
+            .mock("GET", mockito::Matcher::Regex(r"^/web/2/.*/page$".to_string()))
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+        let url = format!("{}/page", server.url());
+
+        let config = ScraperConfig {
+            max_retries: 1,
+            ..ScraperConfig::default()
+        };
+        let mut engine = SearchEngine::new(config.clone()).unwrap();
+        engine.archive_base_url = format!("{}/web/2/", server.url());
+        assert!(engine.fetch_content(&url).await.is_err());
+
+        let config = ScraperConfig {
+            archive_fallback: true,
+            ..config
+        };
+        let mut engine = SearchEngine::new(config).unwrap();
+        engine.archive_base_url = format!("{}/web/2/", server.url());
+        let content = engine.fetch_content(&url).await.unwrap();
+
+        assert_eq!(content.url, url);
+        assert!(content.content.contains("landing page content"));
+        assert_eq!(
+            content.metadata.get("archive_url").unwrap(),
+            &format!("{}/web/2/{}", server.url(), url)
+        );
+    }
+
+    /// Tests that pages missing required metadata are dropped.
+    #[tokio::test]
+    async fn test_required_metadata() {
+        let mut server = mockito::Server::new_async().await;
+        let pages = [
+            ("/complete", r#"<html><head><title>Complete</title><meta name="date" content="2024-05-01"></head><body><p>Full metadata.</p></body></html>"#),
+            ("/untitled", r#"<html><head><meta name="date" content="2024-05-01"></head><body><p>No title.</p></body></html>"#),
+            ("/undated", r#"<html><head><title>Undated</title></head><body><p>No date.</p></body></html>"#),
+        ];
+        for (path, body) in pages {
+            server.mock("GET", path).with_body(body).create_async().await;
+        }
+        let urls: Vec<String> = pages.iter().map(|(path, _)| format!("{}{}", server.url(), path)).collect();
+
+        let mut config = ScraperConfig {
+            max_retries: 1,
+            ..ScraperConfig::default()
+        };
+        config.rate_limit.requests_per_second = 100.0;
+
+        let engine = SearchEngine::new(config.clone()).unwrap();
+        assert_eq!(engine.fetch_all(urls.clone()).await.unwrap().len(), 3);
+
+        config.required_metadata = vec!["title".to_string(), "date".to_string()];
+        let engine = SearchEngine::new(config).unwrap();
+        let contents = engine.fetch_all(urls).await.unwrap();
+
+        assert_eq!(contents.len(), 1);
+        assert!(contents[0].url.ends_with("/complete"));
+        assert_eq!(contents[0].metadata.get("title").unwrap(), "Complete");
+    }
+
+    /// Tests that cached pages are served from disk instead of being fetched again, keeping their
+    /// redirect hops and `X-Robots-Tag` header.
+    #[tokio::test]
+    async fn test_cached_fetch() {
+        let mut server = mockito::Server::new_async().await;
+        let redirect = server
+            .mock("GET", "/page")
+            .with_status(301)
+            .with_header("location", "/landing")
+            .expect(1)
+            .create_async()
+            .await;
+        let page = server
+            .mock("GET", "/landing")
+            .with_header("x-robots-tag", "nosnippet")
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+        let url = format!("{}/page", server.url());
+        let dir = std::env::temp_dir().join(format!("sollama-search-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let config = ScraperConfig {
+            cache_dir: Some(dir.clone()),
+            record_redirects: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+
+        let first = engine.try_fetch_content(&url).await.unwrap();
+        let second = engine.try_fetch_content(&url).await.unwrap();
+
+        assert_eq!(first.content, second.content);
+        assert_eq!(second.redirect_chain, vec![url.clone(), format!("{}/landing", server.url())]);
+        assert_eq!(second.redirect_chain, first.redirect_chain);
+        assert_eq!(second.metadata.get("x-robots-tag").unwrap(), "nosnippet");
+        redirect.assert_async().await;
+        page.assert_async().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Tests that no new fetches start once the download limit is reached.
+    #[tokio::test]
+    async fn test_max_total_bytes() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", mockito::Matcher::Regex(r"^/page\d$".to_string()))
+            .with_body(ARTICLE_HTML)
+            .expect(1)
+            .create_async()
+            .await;
+        let urls: Vec<String> = (1..=3).map(|i| format!("{}/page{}", server.url(), i)).collect();
+
+        let mut config = ScraperConfig {
+            concurrent_requests: 1,
+            max_retries: 1,
+            max_total_bytes: Some(ARTICLE_HTML.len() / 2),
+            ..ScraperConfig::default()
+        };
+        config.rate_limit.requests_per_second = 100.0;
+        let engine = SearchEngine::new(config).unwrap();
+
+        let results = engine.fetch_all_results(urls).await;
+
+        assert!(results[0].1.is_ok());
+        assert!(results[1..]
+            .iter()
+            .all(|(_, result)| matches!(result, Err(ScraperError::BandwidthExceeded(_)))));
+        assert!(engine.bandwidth_exceeded());
+        assert_eq!(engine.bytes_downloaded(), ARTICLE_HTML.len());
+    }
+
+    /// Tests that AMP pages are replaced by their canonical page when enabled.
+    #[tokio::test]
+    async fn test_amp_canonical_followed() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/story/amp")
+            .with_body(r#"<html amp><head><link rel="canonical" href="/story"></head><body><p>AMP version.</p></body></html>"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/story")
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+        let url = format!("{}/story/amp", server.url());
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert!(content.content.contains("AMP version."));
+
+        let config = ScraperConfig {
+            prefer_canonical_urls: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let content = engine.try_fetch_content(&url).await.unwrap();
+        assert!(content.content.contains("landing page content"));
+    }
+
+    /// Tests that the recency window is mapped to the matching `tbs=qdr` parameter.
+    #[test]
+    fn test_search_url_recency() {
+        use crate::config::Recency;
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        assert_eq!(
+            engine.search_url("rust news", 5, 0),
+            "https://www.google.com/search?q=rust%20news&hl=en&num=5"
+        );
+
+        for (recency, code) in [
+            (Recency::Hour, "h"),
+            (Recency::Day, "d"),
+            (Recency::Week, "w"),
+            (Recency::Month, "m"),
+            (Recency::Year, "y"),
+        ] {
+            let config = ScraperConfig {
+                recency: Some(recency),
+                ..ScraperConfig::default()
+            };
+            let engine = SearchEngine::new(config).unwrap();
+            assert!(engine.search_url("rust news", 5, 0).ends_with(&format!("&tbs=qdr:{}", code)));
+        }
+
+        assert_eq!("Week".parse::<Recency>().unwrap(), Recency::Week);
+        assert!("fortnight".parse::<Recency>().is_err());
+    }
+
+    /// Tests that results of several queries are merged without duplicates in both search modes.
+    #[tokio::test]
+    async fn test_search_all() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::UrlEncoded("q".to_string(), "rust".to_string()))
+            .with_body(SERP_HTML)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::UrlEncoded("q".to_string(), "rust book".to_string()))
+            .with_body(
+                r#"<div class="g"><a href="https://doc.rust-lang.org/book/"><h3>The Rust Book</h3></a></div>
+                   <div class="g"><a href="https://rust-book.cs.brown.edu/"><h3>Interactive Rust Book</h3></a></div>"#,
+            )
+            .create_async()
+            .await;
+        let queries = vec!["rust".to_string(), "rust book".to_string()];
+
+        for sequential in [true, false] {
+            let config = ScraperConfig {
+                sequential_search: sequential,
+                ..ScraperConfig::default()
+            };
+            let mut engine = SearchEngine::new(config).unwrap();
+            engine.search_base_url = format!("{}/search", server.url());
+
+            let urls = SearchHit::urls(&engine.search_all(&queries, 10).await.unwrap());
+
+            assert_eq!(urls.len(), 5);
+            assert_eq!(urls.iter().filter(|url| url.contains("doc.rust-lang.org/book")).count(), 1);
+            assert_eq!(urls.last().unwrap(), "https://rust-book.cs.brown.edu/");
+        }
+    }
+
+    /// Tests that `X-Robots-Tag` is recorded by default and enforced when respected.
+    #[tokio::test]
+    async fn test_x_robots_tag() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/private")
+            .with_header("X-Robots-Tag", "noindex, nofollow")
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/public")
+            .with_header("X-Robots-Tag", "nofollow")
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        let content = engine.try_fetch_content(&format!("{}/private", server.url())).await.unwrap();
+        assert_eq!(content.metadata.get("x-robots-tag").unwrap(), "noindex, nofollow");
+
+        let config = ScraperConfig {
+            respect_robots: true,
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+
+        let result = engine.try_fetch_content(&format!("{}/private", server.url())).await;
+        assert!(matches!(result, Err(ScraperError::RobotsDisallowed(_))));
+        assert!(engine.try_fetch_content(&format!("{}/public", server.url())).await.is_ok());
+
+        assert!(robots_disallows("googlebot: noarchive"));
+        assert!(robots_disallows("NONE"));
+        assert!(!robots_disallows("unavailable_after: 25 Jun 2010 15:00:00 PST"));
+    }
+
+    /// Tests that robots.txt is fetched once per host and engine, and that the paths disallowed
+    /// for the user agent actually sent are skipped.
+    #[tokio::test]
+    async fn test_robots_txt() {
+        let mut server = mockito::Server::new_async().await;
+        let robots = server
+            .mock("GET", "/robots.txt")
+            .with_body("User-agent: *\nDisallow: /private\n\nUser-agent: RustBot\nDisallow: /drafts/\n")
+            .expect(2)
+            .create_async()
+            .await;
+        // Fetched with a browser user agent and once robots.txt is no longer respected
+        let draft = server
+            .mock("GET", "/drafts/post")
+            .with_body(ARTICLE_HTML)
+            .expect(2)
+            .create_async()
+            .await;
+        server
+            .mock("GET", mockito::Matcher::Regex(r"^/(private|public)$".to_string()))
+            .with_body(ARTICLE_HTML)
+            .create_async()
+            .await;
+        let urls = vec![
+            format!("{}/drafts/post", server.url()),
+            format!("{}/public", server.url()),
+            format!("{}/private", server.url()),
+        ];
+
+        let mut config = ScraperConfig::default();
+        config.rate_limit.requests_per_second = 1000.0;
+        config.user_agent_mode = UserAgentMode::Fixed;
+        let engine = SearchEngine::new(config.clone()).unwrap();
+
+        for (url, result) in engine.fetch_all_results(urls.clone()).await {
+            if url.ends_with("/drafts/post") {
+                assert!(matches!(result, Err(ScraperError::DisallowedByRobots(_))));
+            } else {
+                assert!(result.is_ok(), "{} should be allowed for RustBot", url);
+            }
+        }
+
+        // Rotated browser user agents fall under the `*` group instead
+        config.user_agent_mode = UserAgentMode::RealisticRandom;
+        let engine = SearchEngine::new(config.clone()).unwrap();
+
+        for (url, result) in engine.fetch_all_results(urls).await {
+            if url.ends_with("/private") {
+                assert!(matches!(result, Err(ScraperError::DisallowedByRobots(_))));
+            } else {
+                assert!(result.is_ok(), "{} should be allowed for a browser", url);
+            }
+        }
+        robots.assert_async().await;
+
+        config.respect_robots_txt = false;
+        let engine = SearchEngine::new(config).unwrap();
+        assert_eq!(engine.fetch_all(vec![format!("{}/drafts/post", server.url())]).await.unwrap().len(), 1);
+        draft.assert_async().await;
+        robots.assert_async().await;
+    }
+
+    /// Tests that a brotli-compressed search results page is decoded before extraction.
+    #[tokio::test]
+    async fn test_search_brotli_response() {
+        use std::io::Write;
+
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            writer.write_all(SERP_HTML.as_bytes()).unwrap();
+        }
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::Any)
+            .match_header("accept-encoding", mockito::Matcher::Regex("br".to_string()))
+            .with_header("Content-Encoding", "br")
+            .with_body(compressed)
+            .create_async()
+            .await;
+
+        let mut engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        engine.search_base_url = format!("{}/search", server.url());
+
+        let urls = SearchHit::urls(&engine.search("rust", 10).await.unwrap());
+        assert!(urls.contains(&"https://www.rust-lang.org/".to_string()));
+    }
+
+    /// Tests that a slow host is given a multiple of its response time before the next fetch, and
+    /// that concurrent fetches of one host reserve separate slots.
+    #[tokio::test]
+    async fn test_politeness_factor() {
+        tokio::time::pause();
+
+        let config = ScraperConfig {
+            politeness_factor: Some(2.0),
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let url = "https://example.com/slow";
+
+        // Hosts without a recorded response can be requested right away
+        assert_eq!(engine.reserve_request_time(url), None);
+
+        let start = Instant::now();
+        engine.record_response_time(url, Duration::from_millis(200));
+        assert_eq!(engine.next_request_time(url), Some(start + Duration::from_millis(400)));
+
+        // Each reservation takes the next slot, so the fetches are spaced by the politeness delay
+        assert_eq!(engine.reserve_request_time(url), Some(start + Duration::from_millis(400)));
+        assert_eq!(engine.reserve_request_time(url), Some(start + Duration::from_millis(800)));
+        assert_eq!(engine.reserve_request_time("https://other.example.org/"), None);
+
+        // A response recorded later never moves the host before the slots already reserved
+        tokio::time::advance(Duration::from_millis(500)).await;
+        engine.record_response_time(url, Duration::from_millis(50));
+        assert_eq!(engine.reserve_request_time(url), Some(start + Duration::from_millis(1200)));
+        assert_eq!(engine.reserve_request_time(url), Some(start + Duration::from_millis(1300)));
+
+        // Without a politeness factor responses don't delay the host
+        let engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        engine.record_response_time(url, Duration::from_millis(200));
+        assert_eq!(engine.reserve_request_time(url), None);
+    }
+
+    /// Tests broadening queries by removing narrowing syntax, then trailing words.
+    #[test]
+    fn test_broaden_query() {
+        assert_eq!(
+            broaden_query(r#""rust borrow checker" site:reddit.com -beginner"#).as_deref(),
+            Some("rust borrow checker")
+        );
+        assert_eq!(
+            broaden_query("(tokio OR async-std) intitle:benchmark").as_deref(),
+            Some("tokio async-std")
+        );
+        assert_eq!(broaden_query("rust async trait").as_deref(), Some("rust async"));
+        assert_eq!(broaden_query("rust async"), None);
+        assert_eq!(broaden_query("site:example.com"), None);
+    }
+
+    /// Tests that a hedged request wins when the first request is slow.
+    #[tokio::test]
+    async fn test_hedged_fetch() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let base = stalling_server(move |path| {
+            if path != "/slow-start" {
+                return (Duration::ZERO, raw_response("404 Not Found", "", ""));
+            }
+            // Only the first request stalls
+            let delay = if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                Duration::from_secs(3)
+            } else {
+                Duration::ZERO
+            };
+            (delay, raw_response("200 OK", "", ARTICLE_HTML))
+        })
+        .await;
+
+        let mut config = ScraperConfig::default();
+        config.rate_limit.requests_per_second = 1000.0;
+        config.hedge_after = Some(Duration::from_millis(100));
+        let engine = SearchEngine::new(config).unwrap();
+
+        let started = std::time::Instant::now();
+        let contents = engine.fetch_all(vec![format!("{}/slow-start", base)]).await.unwrap();
+
+        assert!(contents[0].content.contains("landing page content"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    /// Tests that only changes since the cached version of a page are reported.
+    #[tokio::test]
+    async fn test_fetch_and_diff() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/status")
+            .with_body("<article><p>Service is up.</p><p>Version 1.0 released.</p></article>")
+            .create_async()
+            .await;
+
+        let dir = std::env::temp_dir().join(format!("sollama-diff-cache-{}", std::process::id()));
+        let config = ScraperConfig {
+            cache_dir: Some(dir.clone()),
+            ..ScraperConfig::default()
+        };
+        let engine = SearchEngine::new(config).unwrap();
+        let url = format!("{}/status", server.url());
+
+        // The first fetch stores the baseline, and an unchanged page has no diff
+        assert_eq!(engine.fetch_and_diff(&url).await.unwrap(), None);
+        assert_eq!(engine.fetch_and_diff(&url).await.unwrap(), None);
+
+        first.remove_async().await;
+        server
+            .mock("GET", "/status")
+            .with_body("<article><p>Service is up.</p><p>Version 1.1 released.</p></article>")
+            .create_async()
+            .await;
+
+        let diff = engine.fetch_and_diff(&url).await.unwrap().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(diff.contains("- Version 1.0 released."));
+        assert!(diff.contains("+ Version 1.1 released."));
+        assert!(diff.contains("  Service is up."));
+
+        let uncached = SearchEngine::new(ScraperConfig::default()).unwrap();
+        assert!(matches!(uncached.fetch_and_diff(&url).await, Err(ScraperError::ConfigError(_))));
+    }
+
+    /// Tests that domain round-robin selection gets extra candidates to swap in other domains.
+    #[tokio::test]
+    async fn test_round_robin_search_candidates() {
+        let mut server = mockito::Server::new_async().await;
+        let search = server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::Regex("num=6$".to_string()))
+            .with_body(
+                r#"<div class="g"><a href="https://a.example.com/1"><h3>A 1</h3></a></div>
+                   <div class="g"><a href="https://a.example.com/2"><h3>A 2</h3></a></div>
+                   <div class="g"><a href="https://b.example.com/1"><h3>B 1</h3></a></div>
+                   <div class="g"><a href="https://c.example.com/1"><h3>C 1</h3></a></div>"#,
+            )
+            .create_async()
+            .await;
+
+        let config = ScraperConfig {
+            selection_strategy: SelectionStrategy::DomainRoundRobin,
+            ..ScraperConfig::default()
+        };
+        let mut engine = SearchEngine::new(config).unwrap();
+        engine.search_base_url = format!("{}/search", server.url());
+
+        let urls = SearchHit::urls(&engine.search("rust", 2).await.unwrap());
+        assert_eq!(urls, vec!["https://a.example.com/1", "https://b.example.com/1"]);
+        search.assert_async().await;
+    }
+
+    /// Tests that further result pages are requested when filtering leaves too few URLs.
+    #[tokio::test]
+    async fn test_search_paginates_after_filtering() {
+        let mut server = mockito::Server::new_async().await;
+        let first_page = server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::Regex("num=3$".to_string()))
+            .with_body(
+                r#"<div class="g"><a href="https://www.rust-lang.org/"><h3>Rust</h3></a></div>
+                   <div class="g"><a href="https://www.google.com/search?q=rust+book"><h3>More results</h3></a></div>
+                   <div class="g"><a href="http://insecure.example.com/"><h3>Insecure</h3></a></div>
+                   <div class="g"><a href="https://accounts.google.com/login"><h3>Sign in</h3></a></div>"#,
+            )
+            .create_async()
+            .await;
+        let second_page = server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::Regex("start=3".to_string()))
+            .with_body(
+                r#"<div class="g"><a href="https://www.rust-lang.org/"><h3>Rust again</h3></a></div>
+                   <div class="g"><a href="https://doc.rust-lang.org/book/"><h3>The Rust Book</h3></a></div>
+                   <div class="g"><a href="https://crates.io/"><h3>crates.io</h3></a></div>"#,
+            )
+            .create_async()
+            .await;
+
+        let mut engine = SearchEngine::new(ScraperConfig::default()).unwrap();
+        engine.search_base_url = format!("{}/search", server.url());
+
+        let urls = SearchHit::urls(&engine.search("rust", 3).await.unwrap());
+
+        assert_eq!(urls.len(), 3);
+        assert!(urls.contains(&"https://www.rust-lang.org/".to_string()));
+        assert!(urls.contains(&"https://doc.rust-lang.org/book/".to_string()));
+        assert!(urls.contains(&"https://crates.io/".to_string()));
+        first_page.assert_async().await;
+        second_page.assert_async().await;
+    }
+}
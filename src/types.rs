@@ -13,6 +13,30 @@ pub struct ScrapedContent {
     pub metadata: HashMap<String, String>,
     /// The timestamp when the content was scraped.
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// The original HTML of the page, kept only when `ScraperConfig::keep_raw_html` is set.
+    /// It is omitted from serialized output when absent to keep JSON small.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html: Option<String>,
+    /// The URLs the fetch passed through, from the requested URL to the one the content came from.
+    /// It is recorded only when `ScraperConfig::record_redirects` is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirect_chain: Vec<String>,
+    /// When the page was published, if known: from its metadata (such as the Open Graph
+    /// `article:published_time` tag or a `<time datetime>` element), or else from a date in its URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<chrono::DateTime<chrono::Utc>>,
+    /// The question and answer pairs found in the page's FAQ sections.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faq: Vec<FaqEntry>,
+}
+
+/// The `FaqEntry` struct represents a single question and answer pair from a page's FAQ.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaqEntry {
+    /// The question.
+    pub question: String,
+    /// The answer to the question.
+    pub answer: String,
 }
 
 /// The `SearchResult` struct represents the result of a search operation.
@@ -29,6 +53,57 @@ pub struct SearchResult {
     pub processing_time: std::time::Duration,
 }
 
+/// The `SearchHit` struct represents a single result returned by a search.
+/// It includes the result URL along with the title and snippet shown on the results page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// The URL of the result.
+    pub url: String,
+    /// The title of the result, if one was found.
+    pub title: Option<String>,
+    /// The snippet (description) shown below the result, if one was found.
+    pub snippet: Option<String>,
+}
+
+impl SearchHit {
+    /// Returns just the URLs of search results, for callers that don't need titles or snippets.
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - The search results.
+    ///
+    /// # Returns
+    ///
+    /// The URLs in result order.
+    pub fn urls(results: &[SearchHit]) -> Vec<String> {
+        results.iter().map(|result| result.url.clone()).collect()
+    }
+}
+
+impl AsRef<str> for SearchHit {
+    /// Returns the URL of the result.
+    fn as_ref(&self) -> &str {
+        &self.url
+    }
+}
+
+impl From<SearchHit> for String {
+    /// Converts a search result into its URL, so results can be passed straight to `fetch_all`.
+    fn from(result: SearchHit) -> Self {
+        result.url
+    }
+}
+
+/// The `PageSummary` struct represents the LLM summary of a single scraped page.
+/// It is produced by the map step of map-reduce summarization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageSummary {
+    /// The URL of the summarized page.
+    pub url: String,
+    /// The summary generated for the page.
+    pub summary: String,
+}
+
 /// The `LLMRequest` struct represents a request to a Language Model (LLM).
 /// It includes the model name, the prompt, the temperature, and the maximum number of tokens.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,487 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use scraper::{Html, Selector};
+use url::Url;
+
+/// Resolves a (possibly relative) `href` against a base URL.
+///
+/// Handles absolute URLs, root-relative (`/docs/page`) and path-relative (`../page`) links,
+/// protocol-relative links (`//host/path`) and fragments. Fragments are stripped from the
+/// result since they point into the same document and would only produce duplicate fetches.
+///
+/// # Arguments
+///
+/// * `base` - The absolute URL of the page (or its `<base href>`) the link was found on.
+/// * `href` - The raw `href` attribute value.
+///
+/// # Returns
+///
+/// An `Option` containing the absolute `http`/`https` URL, or `None` if the link cannot be
+/// resolved or points to a non-web scheme such as `mailto:` or `javascript:`.
+pub fn resolve_url(base: &str, href: &str) -> Option<String> {
+    let href = href.trim();
+    if href.is_empty() {
+        return None;
+    }
+
+    let base = Url::parse(base).ok()?;
+    let mut resolved = base.join(href).ok()?;
+
+    if !matches!(resolved.scheme(), "http" | "https") {
+        return None;
+    }
+
+    resolved.set_fragment(None);
+    Some(resolved.into())
+}
+
+/// Extracts the host of a URL for per-domain grouping, ignoring a leading `www.`.
+///
+/// # Arguments
+///
+/// * `url` - The URL to extract the host from.
+///
+/// # Returns
+///
+/// An `Option` containing the lowercase host, or `None` if the URL has no host.
+pub fn host(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+/// Checks whether a host is covered by a host pattern: the same host or one of its subdomains.
+/// A leading `www.` is ignored on both sides and the comparison is case-insensitive.
+///
+/// # Arguments
+///
+/// * `host` - The host to check.
+/// * `pattern` - The host pattern, e.g. `stackoverflow.com`.
+///
+/// # Returns
+///
+/// `true` if the host matches the pattern, `false` otherwise.
+pub fn host_matches(host: &str, pattern: &str) -> bool {
+    let host = host.to_lowercase();
+    let pattern = pattern.trim().trim_start_matches("*.").to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let pattern = pattern.strip_prefix("www.").unwrap_or(&pattern);
+
+    !pattern.is_empty()
+        && (host == pattern || host.strip_suffix(pattern).is_some_and(|prefix| prefix.ends_with('.')))
+}
+
+/// Parses a list of URLs with one URL per line, as read from a URL file.
+///
+/// Blank lines and lines starting with `#` are ignored. Lines that aren't absolute `http` or
+/// `https` URLs are returned separately so they can be reported without dropping the rest.
+///
+/// # Arguments
+///
+/// * `text` - The contents of the URL list.
+///
+/// # Returns
+///
+/// A tuple of the valid URLs, in order, and the malformed lines with their 1-based line numbers.
+pub fn parse_url_list(text: &str) -> (Vec<String>, Vec<(usize, String)>) {
+    let mut valid = Vec::new();
+    let mut malformed = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match Url::parse(line) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {
+                valid.push(line.to_string())
+            }
+            _ => malformed.push((index + 1, line.to_string())),
+        }
+    }
+
+    (valid, malformed)
+}
+
+/// Extracts a publication date embedded in a URL path.
+///
+/// Recognizes date directories (`/2024/03/15/`, or `/2024/03/` for the first of the month) and
+/// dates inside a path segment (`2024-03-15`, `2024_03_15` or `20240315`). Only years from 1990
+/// to 2100 are accepted, to avoid mistaking IDs for dates.
+///
+/// # Arguments
+///
+/// * `url` - The URL to inspect.
+///
+/// # Returns
+///
+/// An `Option` containing the date at midnight UTC, or `None` if the path contains no valid date.
+pub fn date_from_url(url: &str) -> Option<DateTime<Utc>> {
+    let parsed = Url::parse(url).ok()?;
+    let segments: Vec<&str> = parsed.path().split('/').filter(|s| !s.is_empty()).collect();
+
+    let number = |segment: Option<&&str>, max_len: usize| -> Option<u32> {
+        let segment = segment?;
+        if segment.is_empty() || segment.len() > max_len || !segment.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        segment.parse().ok()
+    };
+
+    // Date directories: /yyyy/mm/dd/ or /yyyy/mm/
+    for i in 0..segments.len() {
+        let Some(year) = number(segments.get(i), 4).filter(|y| (1990..=2100).contains(y)) else {
+            continue;
+        };
+        let Some(month) = number(segments.get(i + 1), 2) else {
+            continue;
+        };
+        let day = number(segments.get(i + 2), 2).unwrap_or(1);
+        if let Some(date) = NaiveDate::from_ymd_opt(year as i32, month, day) {
+            return date.and_hms_opt(0, 0, 0).map(|time| time.and_utc());
+        }
+    }
+
+    // Dates inside a segment: yyyy-mm-dd, yyyy_mm_dd or yyyymmdd
+    segments.iter().find_map(|segment| date_in_segment(segment))
+}
+
+/// Finds a `yyyy-mm-dd`, `yyyy_mm_dd` or `yyyymmdd` date inside a single path segment.
+///
+/// # Arguments
+///
+/// * `segment` - The path segment.
+///
+/// # Returns
+///
+/// An `Option` containing the date at midnight UTC, or `None` if the segment contains no valid date.
+fn date_in_segment(segment: &str) -> Option<DateTime<Utc>> {
+    let bytes = segment.as_bytes();
+    let digits = |range: std::ops::Range<usize>| -> Option<u32> {
+        let part = segment.get(range)?;
+        part.chars().all(|c| c.is_ascii_digit()).then(|| part.parse().ok())?
+    };
+
+    for start in 0..bytes.len() {
+        // Dates must not be part of a longer number
+        if start > 0 && bytes[start - 1].is_ascii_digit() {
+            continue;
+        }
+
+        let candidates = [
+            (10, matches!(bytes.get(start + 4), Some(b'-' | b'_')) && bytes.get(start + 7) == bytes.get(start + 4), (5, 8)),
+            (8, true, (4, 6)),
+        ];
+
+        for (len, separators_ok, (month_start, day_start)) in candidates {
+            let end = start + len;
+            if !separators_ok || end > bytes.len() || bytes.get(end).is_some_and(|b| b.is_ascii_digit()) {
+                continue;
+            }
+
+            let date = digits(start..start + 4)
+                .filter(|year| (1990..=2100).contains(year))
+                .zip(digits(start + month_start..start + month_start + 2))
+                .zip(digits(start + day_start..start + day_start + 2))
+                .and_then(|((year, month), day)| NaiveDate::from_ymd_opt(year as i32, month, day));
+
+            if let Some(date) = date {
+                return date.and_hms_opt(0, 0, 0).map(|time| time.and_utc());
+            }
+        }
+    }
+
+    None
+}
+
+/// Rewrites known AMP and mobile URL variants to their canonical desktop form.
+///
+/// Handles Google and `cdn.ampproject.org` AMP cache URLs, `amp` path segments and query
+/// parameters, `.amp.html` pages and mobile hosts (`m.`, `mobile.` and `xx.m.` subdomains, with
+/// `m.wikipedia.org` mapped to `en.wikipedia.org`). Other URLs are returned unchanged.
+///
+/// # Arguments
+///
+/// * `url` - The URL to canonicalize.
+///
+/// # Returns
+///
+/// The canonical URL.
+pub fn canonical_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let host = parsed.host_str().unwrap_or_default().to_lowercase();
+
+    // AMP caches embed the original URL in the path
+    let cache_target = if host.ends_with(".cdn.ampproject.org") {
+        amp_cache_target(parsed.path(), "/c/s/", "/c/")
+    } else if host == "google.com" || host == "www.google.com" {
+        amp_cache_target(parsed.path(), "/amp/s/", "/amp/")
+    } else {
+        None
+    };
+    if let Some(mut target) = cache_target {
+        if let Some(query) = parsed.query() {
+            target = format!("{}?{}", target, query);
+        }
+        return canonical_url(&target);
+    }
+
+    let mobile_host = if host == "m.wikipedia.org" {
+        Some("en.wikipedia.org".to_string())
+    } else if let Some(rest) = host.strip_prefix("m.").or_else(|| host.strip_prefix("mobile.")) {
+        Some(format!("www.{}", rest))
+    } else if host.contains(".m.") {
+        Some(host.replacen(".m.", ".", 1))
+    } else {
+        None
+    };
+    if let Some(mobile_host) = mobile_host {
+        let _ = parsed.set_host(Some(&mobile_host));
+    }
+
+    let segments: Vec<&str> = parsed.path().split('/').filter(|s| !s.is_empty()).collect();
+    let last = segments.len().saturating_sub(1);
+    let mut kept: Vec<String> = segments
+        .iter()
+        .enumerate()
+        .filter(|(i, segment)| !((*i == 0 || *i == last) && segment.eq_ignore_ascii_case("amp")))
+        .map(|(_, segment)| segment.to_string())
+        .collect();
+    if let Some(page) = kept.last_mut() {
+        if let Some(stem) = page.strip_suffix(".amp.html") {
+            *page = format!("{}.html", stem);
+        }
+    }
+    if kept.len() != segments.len() || kept.last() != segments.last().map(|s| s.to_string()).as_ref() {
+        let trailing_slash = if parsed.path().ends_with('/') && !kept.is_empty() { "/" } else { "" };
+        parsed.set_path(&format!("/{}{}", kept.join("/"), trailing_slash));
+    }
+
+    let pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+    let kept_pairs: Vec<&(String, String)> = pairs
+        .iter()
+        .filter(|(key, value)| !(key == "amp" || (key == "outputType" && value == "amp")))
+        .collect();
+    if kept_pairs.len() != pairs.len() {
+        if kept_pairs.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(kept_pairs);
+        }
+    }
+
+    parsed.into()
+}
+
+/// Extracts the original URL from the path of an AMP cache URL.
+///
+/// # Arguments
+///
+/// * `path` - The path of the AMP cache URL.
+/// * `https_prefix` - The path prefix used for `https` origins.
+/// * `http_prefix` - The path prefix used for `http` origins.
+///
+/// # Returns
+///
+/// An `Option` containing the original URL, or `None` if the path doesn't embed one.
+fn amp_cache_target(path: &str, https_prefix: &str, http_prefix: &str) -> Option<String> {
+    path.strip_prefix(https_prefix)
+        .map(|rest| format!("https://{}", rest))
+        .or_else(|| path.strip_prefix(http_prefix).map(|rest| format!("http://{}", rest)))
+}
+
+/// Determines the base URL that relative links in a document are resolved against.
+///
+/// If the document contains a `<base href>` tag, its value (itself resolved against the page
+/// URL) is used. Otherwise the page URL is the base.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML document.
+/// * `page_url` - The URL the document was fetched from.
+///
+/// # Returns
+///
+/// The effective base URL as a string.
+pub fn base_url(document: &Html, page_url: &str) -> String {
+    Selector::parse("base[href]")
+        .ok()
+        .and_then(|selector| {
+            document
+                .select(&selector)
+                .next()
+                .and_then(|base| base.value().attr("href"))
+                .and_then(|href| resolve_url(page_url, href))
+        })
+        .unwrap_or_else(|| page_url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://example.com/docs/guide/intro.html";
+
+    /// Tests that absolute URLs are returned unchanged.
+    #[test]
+    fn test_resolve_absolute_url() {
+        assert_eq!(
+            resolve_url(BASE, "https://other.org/page").as_deref(),
+            Some("https://other.org/page")
+        );
+    }
+
+    /// Tests resolving root-relative links.
+    #[test]
+    fn test_resolve_root_relative_url() {
+        assert_eq!(
+            resolve_url(BASE, "/docs/page").as_deref(),
+            Some("https://example.com/docs/page")
+        );
+    }
+
+    /// Tests resolving path-relative links, including parent directory references.
+    #[test]
+    fn test_resolve_path_relative_url() {
+        assert_eq!(
+            resolve_url(BASE, "setup.html").as_deref(),
+            Some("https://example.com/docs/guide/setup.html")
+        );
+        assert_eq!(
+            resolve_url(BASE, "../api/index.html").as_deref(),
+            Some("https://example.com/docs/api/index.html")
+        );
+    }
+
+    /// Tests that protocol-relative links inherit the base scheme.
+    #[test]
+    fn test_resolve_protocol_relative_url() {
+        assert_eq!(
+            resolve_url(BASE, "//cdn.example.net/article").as_deref(),
+            Some("https://cdn.example.net/article")
+        );
+    }
+
+    /// Tests that fragments are stripped from resolved URLs.
+    #[test]
+    fn test_resolve_strips_fragments() {
+        assert_eq!(
+            resolve_url(BASE, "#installation").as_deref(),
+            Some("https://example.com/docs/guide/intro.html")
+        );
+        assert_eq!(
+            resolve_url(BASE, "/faq#general").as_deref(),
+            Some("https://example.com/faq")
+        );
+    }
+
+    /// Tests that non-web schemes and empty links are rejected.
+    #[test]
+    fn test_resolve_rejects_non_http_links() {
+        assert_eq!(resolve_url(BASE, "mailto:team@example.com"), None);
+        assert_eq!(resolve_url(BASE, "javascript:void(0)"), None);
+        assert_eq!(resolve_url(BASE, "   "), None);
+        assert_eq!(resolve_url("not a url", "/page"), None);
+    }
+
+    /// Tests host extraction for per-domain grouping.
+    #[test]
+    fn test_host() {
+        assert_eq!(host("https://www.Example.com/a").as_deref(), Some("example.com"));
+        assert_eq!(host("https://en.wikipedia.org/wiki/Rust").as_deref(), Some("en.wikipedia.org"));
+        assert_eq!(host("not a url"), None);
+    }
+
+    /// Tests that a `<base href>` tag takes precedence over the page URL.
+    #[test]
+    fn test_base_url_from_base_tag() {
+        let html = r#"
+            <html>
+                <head><base href="/static/v2/"></head>
+                <body><a href="page.html">Link</a></body>
+            </html>
+        "#;
+        let document = Html::parse_document(html);
+        let base = base_url(&document, BASE);
+
+        assert_eq!(base, "https://example.com/static/v2/");
+        assert_eq!(
+            resolve_url(&base, "page.html").as_deref(),
+            Some("https://example.com/static/v2/page.html")
+        );
+    }
+
+    /// Tests that the page URL is used when no `<base href>` tag is present.
+    #[test]
+    fn test_base_url_defaults_to_page_url() {
+        let document = Html::parse_document("<html><body><p>No base</p></body></html>");
+        assert_eq!(base_url(&document, BASE), BASE);
+    }
+
+    /// Tests rewriting of common AMP and mobile URL variants.
+    #[test]
+    fn test_canonical_url() {
+        let cases = [
+            ("https://www.google.com/amp/s/example.com/news/story", "https://example.com/news/story"),
+            ("https://example-com.cdn.ampproject.org/c/s/example.com/news/story?id=7", "https://example.com/news/story?id=7"),
+            ("https://example.com/news/story/amp/", "https://example.com/news/story/"),
+            ("https://example.com/amp/news/story", "https://example.com/news/story"),
+            ("https://example.com/news/story.amp.html", "https://example.com/news/story.html"),
+            ("https://example.com/news/story?amp=1&id=7", "https://example.com/news/story?id=7"),
+            ("https://example.com/news/story?outputType=amp", "https://example.com/news/story"),
+            ("https://m.wikipedia.org/wiki/Rust", "https://en.wikipedia.org/wiki/Rust"),
+            ("https://de.m.wikipedia.org/wiki/Rust", "https://de.wikipedia.org/wiki/Rust"),
+            ("https://m.example.com/page", "https://www.example.com/page"),
+            ("https://example.com/ampersand/example", "https://example.com/ampersand/example"),
+            ("https://example.com/a/amp/b", "https://example.com/a/amp/b"),
+        ];
+
+        for (url, expected) in cases {
+            assert_eq!(canonical_url(url), expected, "{}", url);
+        }
+    }
+
+    /// Tests extracting dates from common URL date patterns.
+    #[test]
+    fn test_date_from_url() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+        let cases = [
+            ("https://news.example.com/2024/03/15/rust-release", Some(date(2024, 3, 15))),
+            ("https://blog.example.com/2023/11/post-title/", Some(date(2023, 11, 1))),
+            ("https://example.com/articles/2024-03-15-rust-release", Some(date(2024, 3, 15))),
+            ("https://example.com/news/story_2022_07_04.html", Some(date(2022, 7, 4))),
+            ("https://example.com/archive/20210109/item", Some(date(2021, 1, 9))),
+            ("https://example.com/products/12345678", None),
+            ("https://example.com/2024/13/40/invalid", None),
+            ("https://example.com/item/2024", None),
+            ("https://example.com/page?date=2024-03-15", None),
+        ];
+
+        for (url, expected) in cases {
+            assert_eq!(date_from_url(url), expected, "{}", url);
+        }
+    }
+
+    /// Tests parsing a URL file with comments, blank lines and malformed entries.
+    #[test]
+    fn test_parse_url_list() {
+        let text = "# Sources\n\
+            https://example.com/a\n\
+            \n\
+              https://example.org/b  \n\
+            not a url\n\
+            ftp://example.com/file\n\
+            # https://example.com/commented\n\
+            http://example.net/c\n";
+
+        let (valid, malformed) = parse_url_list(text);
+        assert_eq!(valid, vec!["https://example.com/a", "https://example.org/b", "http://example.net/c"]);
+        assert_eq!(
+            malformed,
+            vec![(5, "not a url".to_string()), (6, "ftp://example.com/file".to_string())]
+        );
+    }
+}
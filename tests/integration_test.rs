@@ -1,9 +1,9 @@
 use sollama::{
     config::ScraperConfig,
+    extract_from_html,
     prompt::PromptBuilder,
     search::SearchEngine,
 };
-use tokio;
 
 #[tokio::test]
 async fn test_full_search_workflow() {
@@ -12,11 +12,11 @@ async fn test_full_search_workflow() {
     let query = "rust programming test";
 
     // Test search
-    let urls = search_engine.search(query).await.unwrap();
-    assert!(!urls.is_empty(), "Search should return at least one URL");
+    let results = search_engine.search(query, 5).await.unwrap();
+    assert!(!results.is_empty(), "Search should return at least one URL");
 
     // Test content fetching
-    let contents = search_engine.fetch_all(urls).await.unwrap();
+    let contents = search_engine.fetch_all(results).await.unwrap();
     assert!(!contents.is_empty(), "Should fetch content from at least one URL");
 
     // Test prompt building
@@ -37,9 +37,27 @@ async fn test_rate_limiting() {
     let start = std::time::Instant::now();
 
     for _ in 0..3 {
-        let _ = search_engine.search("test").await.unwrap();
+        let _ = search_engine.search("test", 5).await.unwrap();
     }
 
     let elapsed = start.elapsed();
     assert!(elapsed.as_secs() >= 2, "Rate limiting should space out requests");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_extract_from_html() {
+    let html = r#"
+        <html>
+            <head><title>Offline page</title></head>
+            <body><article><p>Extraction works without a network connection.</p></article></body>
+        </html>
+    "#;
+
+    let content = extract_from_html(html, "https://example.com/2024/05/01/offline").unwrap();
+    assert_eq!(content.url, "https://example.com/2024/05/01/offline");
+    assert!(content.content.contains("without a network connection"));
+    assert_eq!(content.metadata.get("title").map(String::as_str), Some("Offline page"));
+    assert!(content.published.is_some());
+
+    assert!(extract_from_html("<html><body></body></html>", "https://example.com").is_err());
+}